@@ -0,0 +1,168 @@
+//! Sum cages
+//!
+//! A cage is any set of playable cells whose digits combine to a clue, like
+//! the cages of Killer Sudoku. Cages sit on top of the grid: a board may mix
+//! them with ordinary clued runs, or use them alone on a grid of playable
+//! cells. A cage without a sum only asks for distinct digits, which is how
+//! the rows, columns and boxes of Killer Sudoku are written.
+//!
+//! Runs and cages are both turned into [`Constraint`]s, which the checker
+//! and solver treat alike.
+
+use crate::{Direction, Run, rules::Operation};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// Cells whose digits combine to a clue
+pub struct Cage {
+    /// Coordinates of the cells, as (row, col)
+    pub cells: Vec<(usize, usize)>,
+    /// Value the digits combine to, or None if they only need to be distinct
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sum: Option<u32>,
+    /// How the digits combine
+    #[serde(default, skip_serializing_if = "Operation::is_sum")]
+    pub operation: Operation,
+    /// Whether a digit may appear more than once
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub repeats: bool,
+}
+
+impl Cage {
+    /// Creates a cage of distinct digits adding up to `sum`
+    pub fn new(cells: Vec<(usize, usize)>, sum: Option<u32>) -> Self {
+        Self {
+            cells,
+            sum,
+            ..Self::default()
+        }
+    }
+
+    /// Returns the cell the cage's label is drawn in: the top left one
+    pub fn anchor(&self) -> Option<(usize, usize)> {
+        self.cells.iter().min().copied()
+    }
+
+    /// Returns the label drawn on the cage, such as "12" or "×24"
+    pub fn label(&self) -> Option<String> {
+        let sum = self.sum?;
+        Some(match self.operation {
+            Operation::Sum => sum.to_string(),
+            Operation::Product => format!("×{}", sum),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Where a constraint comes from
+pub enum Source {
+    /// A clued run, from its clue cell and direction
+    Run {
+        /// Row of the clue cell
+        row: usize,
+        /// Column of the clue cell
+        col: usize,
+        /// Direction of the run
+        direction: Direction,
+    },
+    /// A cage, by its index on the board
+    Cage(usize),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Cells whose digits must combine to a value, with or without repeats
+pub struct Constraint {
+    /// Run or cage the constraint comes from
+    pub source: Source,
+    /// Coordinates of the cells
+    pub cells: Vec<(usize, usize)>,
+    /// Value the digits combine to, or None for distinctness alone
+    pub sum: Option<u32>,
+    /// How the digits combine
+    pub operation: Operation,
+    /// Whether every digit must be different
+    pub distinct: bool,
+}
+
+impl Constraint {
+    /// Creates the constraint of a clued run
+    pub fn from_run(run: &Run, distinct: bool) -> Self {
+        Self {
+            source: Source::Run {
+                row: run.clue_row,
+                col: run.clue_col,
+                direction: run.direction,
+            },
+            cells: run.cells.clone(),
+            sum: Some(run.sum),
+            operation: run.operation,
+            distinct,
+        }
+    }
+
+    /// Creates the constraint of the cage at `index`
+    pub fn from_cage(index: usize, cage: &Cage) -> Self {
+        Self {
+            source: Source::Cage(index),
+            cells: cage.cells.clone(),
+            sum: cage.sum,
+            operation: cage.operation,
+            distinct: !cage.repeats,
+        }
+    }
+}
+
+impl fmt::Display for Constraint {
+    /// Names the constraint, such as "across 16 in 2 cells at row 1, col 0"
+    /// or "cage ×24 in 3 cells at row 2, col 3"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self.source {
+            Source::Run {
+                direction: Direction::Across,
+                ..
+            } => "across",
+            Source::Run {
+                direction: Direction::Down,
+                ..
+            } => "down",
+            Source::Cage(_) => "cage",
+        };
+        let (row, col) = match self.source {
+            Source::Run { row, col, .. } => (row, col),
+            Source::Cage(_) => self.cells.iter().min().copied().unwrap_or_default(),
+        };
+        let value = match (self.sum, self.operation) {
+            (None, _) => "of distinct digits".to_string(),
+            (Some(sum), Operation::Sum) => sum.to_string(),
+            (Some(sum), Operation::Product) => format!("×{}", sum),
+        };
+        write!(
+            f,
+            "{} {} in {} cells at row {}, col {}",
+            name,
+            value,
+            self.cells.len(),
+            row,
+            col
+        )
+    }
+}
+
+#[cfg(test)]
+mod cage_tests {
+    use super::*;
+
+    #[test]
+    fn test_labels_and_names() {
+        let mut cage = Cage::new(vec![(2, 3), (1, 4), (2, 4)], Some(24));
+        assert_eq!(cage.anchor(), Some((1, 4)));
+        assert_eq!(cage.label().as_deref(), Some("24"));
+        cage.operation = Operation::Product;
+        assert_eq!(
+            Constraint::from_cage(0, &cage).to_string(),
+            "cage ×24 in 3 cells at row 1, col 4"
+        );
+        assert_eq!(Cage::new(vec![(0, 0)], None).label(), None);
+    }
+}
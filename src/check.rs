@@ -0,0 +1,408 @@
+//! Validation of a filled or partly filled grid
+//!
+//! Checking never changes the board: it returns a [`CheckReport`] listing
+//! every run with its expected and actual sums, repeated digits and empty
+//! cells, and the same for every cage. The UI flags clues from the report.
+//! Repeated digits are not an error when the board's rules, or a cage,
+//! allow repeats.
+
+use crate::{
+    Board, Direction,
+    cage::{Constraint, Source},
+    rules::Operation,
+};
+use crossterm::style::Color;
+use serde::Serialize;
+use std::{collections::BTreeMap, fmt};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+/// Result of checking one run
+pub enum RunStatus {
+    /// Every cell is filled, without repeats, and the digits add up
+    Correct,
+    /// Every cell is filled without repeats but the digits do not add up
+    WrongSum,
+    /// A digit appears more than once
+    Duplicate,
+    /// Some cells are still empty
+    Incomplete,
+}
+
+impl RunStatus {
+    /// Every status, in the order summaries list them
+    pub const ALL: [RunStatus; 4] = [
+        RunStatus::Correct,
+        RunStatus::WrongSum,
+        RunStatus::Duplicate,
+        RunStatus::Incomplete,
+    ];
+
+    /// Returns the background colour a clue is drawn with, if any
+    pub(crate) fn color(self) -> Option<Color> {
+        match self {
+            RunStatus::Correct => None,
+            RunStatus::WrongSum => Some(Color::Red),
+            RunStatus::Duplicate => Some(Color::Magenta),
+            RunStatus::Incomplete => Some(Color::DarkGrey),
+        }
+    }
+}
+
+impl fmt::Display for RunStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            RunStatus::Correct => "correct",
+            RunStatus::WrongSum => "wrong sum",
+            RunStatus::Duplicate => "duplicate",
+            RunStatus::Incomplete => "incomplete",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+/// Check of a single run
+pub struct RunCheck {
+    /// Row of the clue cell
+    pub row: usize,
+    /// Column of the clue cell
+    pub col: usize,
+    /// Direction of the run
+    pub direction: Direction,
+    /// Sum given by the clue, or product for a product clue
+    pub expected: u32,
+    /// Sum, or product, of the digits filled in so far, None if a product
+    /// is too large to hold
+    pub actual: Option<u32>,
+    /// How the clue combines the digits, left out for sums
+    #[serde(skip_serializing_if = "Operation::is_sum")]
+    pub operation: Operation,
+    /// Coordinates of the run's playable cells, in order
+    pub cells: Vec<(usize, usize)>,
+    /// Digits that appear more than once, ascending, empty when the rules
+    /// allow repeats
+    pub duplicates: Vec<u8>,
+    /// Coordinates of the cells still empty
+    pub empty: Vec<(usize, usize)>,
+    /// Overall verdict: duplicates first, then empty cells, then the sum
+    pub status: RunStatus,
+}
+
+impl fmt::Display for RunCheck {
+    /// Describes why the run fails, such as "wrong sum, got 18"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        describe(
+            f,
+            self.status,
+            self.operation,
+            self.actual,
+            &self.duplicates,
+            &self.empty,
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+/// Check of a single cage
+pub struct CageCheck {
+    /// Index of the cage on the board
+    pub index: usize,
+    /// Value given by the cage, None if its digits only need to differ
+    pub expected: Option<u32>,
+    /// Sum, or product, of the digits filled in so far, None if a product
+    /// is too large to hold
+    pub actual: Option<u32>,
+    /// How the cage combines the digits, left out for sums
+    #[serde(skip_serializing_if = "Operation::is_sum")]
+    pub operation: Operation,
+    /// Coordinates of the cage's cells
+    pub cells: Vec<(usize, usize)>,
+    /// Digits that appear more than once, ascending, empty when the cage
+    /// allows repeats
+    pub duplicates: Vec<u8>,
+    /// Coordinates of the cells still empty
+    pub empty: Vec<(usize, usize)>,
+    /// Overall verdict, as for runs
+    pub status: RunStatus,
+}
+
+impl fmt::Display for CageCheck {
+    /// Describes why the cage fails, such as "duplicate 4"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        describe(
+            f,
+            self.status,
+            self.operation,
+            self.actual,
+            &self.duplicates,
+            &self.empty,
+        )
+    }
+}
+
+/// Writes why a run or cage fails
+fn describe(
+    f: &mut fmt::Formatter<'_>,
+    status: RunStatus,
+    operation: Operation,
+    actual: Option<u32>,
+    duplicates: &[u8],
+    empty: &[(usize, usize)],
+) -> fmt::Result {
+    match status {
+        RunStatus::Correct => write!(f, "correct"),
+        RunStatus::WrongSum => match actual {
+            Some(actual) => write!(f, "wrong {}, got {}", operation, actual),
+            None => write!(f, "wrong {}, got too large a value", operation),
+        },
+        RunStatus::Duplicate => {
+            let digits: Vec<String> = duplicates.iter().map(|d| d.to_string()).collect();
+            write!(f, "duplicate {}", digits.join(" "))
+        }
+        RunStatus::Incomplete => write!(f, "incomplete, {} empty", empty.len()),
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+/// Check of every run and cage on a board
+pub struct CheckReport {
+    /// Every run, across before down for each clue in row-major order
+    pub runs: Vec<RunCheck>,
+    /// Every cage, in the board's order
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cages: Vec<CageCheck>,
+}
+
+impl CheckReport {
+    /// Returns whether every run and cage is correct
+    pub fn is_solved(&self) -> bool {
+        self.statuses().all(|status| status == RunStatus::Correct)
+    }
+
+    /// Returns whether the report has nothing in it, as before any check
+    pub fn is_empty(&self) -> bool {
+        self.runs.is_empty() && self.cages.is_empty()
+    }
+
+    /// Returns the runs that are not correct
+    pub fn failures(&self) -> impl Iterator<Item = &RunCheck> {
+        self.runs.iter().filter(|r| r.status != RunStatus::Correct)
+    }
+
+    /// Returns the cages that are not correct
+    pub fn cage_failures(&self) -> impl Iterator<Item = &CageCheck> {
+        self.cages.iter().filter(|c| c.status != RunStatus::Correct)
+    }
+
+    /// Returns the check of a clue's run
+    pub fn get(&self, row: usize, col: usize, direction: Direction) -> Option<&RunCheck> {
+        self.runs
+            .iter()
+            .find(|r| (r.row, r.col, r.direction) == (row, col, direction))
+    }
+
+    /// Returns the status of every run, then every cage
+    fn statuses(&self) -> impl Iterator<Item = RunStatus> {
+        let runs = self.runs.iter().map(|r| r.status);
+        runs.chain(self.cages.iter().map(|c| c.status))
+    }
+
+    /// Summarises the report, such as "4 correct, 1 wrong sum"
+    pub fn summary(&self) -> String {
+        RunStatus::ALL
+            .iter()
+            .filter_map(|&status| {
+                let n = self.statuses().filter(|&s| s == status).count();
+                (n > 0).then(|| format!("{} {}", n, status))
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Digits found in a constraint's cells
+struct Tally {
+    /// Sum, or product, of the filled digits, None if it overflows
+    actual: Option<u32>,
+    /// Digits repeated where they must be distinct
+    duplicates: Vec<u8>,
+    /// Cells still empty
+    empty: Vec<(usize, usize)>,
+    /// Overall verdict
+    status: RunStatus,
+}
+
+/// Checks a run or cage's cells against its value and distinctness
+fn tally(board: &Board, constraint: &Constraint) -> Tally {
+    let mut seen: BTreeMap<u8, usize> = BTreeMap::new();
+    let mut empty = Vec::new();
+    for &(r, c) in &constraint.cells {
+        match board.cell(r, c).value() {
+            Some(v) => *seen.entry(v).or_default() += 1,
+            None => empty.push((r, c)),
+        }
+    }
+    let actual = constraint
+        .operation
+        .apply(seen.iter().flat_map(|(&d, &n)| std::iter::repeat_n(d, n)));
+    let duplicates: Vec<u8> = seen
+        .iter()
+        .filter(|&(_, &n)| n > 1 && constraint.distinct)
+        .map(|(&d, _)| d)
+        .collect();
+    let status = if !duplicates.is_empty() {
+        RunStatus::Duplicate
+    } else if !empty.is_empty() {
+        RunStatus::Incomplete
+    } else if constraint.sum.is_some_and(|sum| Some(sum) != actual) {
+        RunStatus::WrongSum
+    } else {
+        RunStatus::Correct
+    };
+    Tally {
+        actual,
+        duplicates,
+        empty,
+        status,
+    }
+}
+
+/// Checks every run against its clue and every cage against its sum
+pub fn check(board: &Board) -> CheckReport {
+    let mut report = CheckReport::default();
+    for constraint in board.constraints() {
+        let Tally {
+            actual,
+            duplicates,
+            empty,
+            status,
+        } = tally(board, &constraint);
+        match constraint.source {
+            Source::Run {
+                row,
+                col,
+                direction,
+            } => report.runs.push(RunCheck {
+                row,
+                col,
+                direction,
+                expected: constraint.sum.unwrap_or_default(),
+                actual,
+                operation: constraint.operation,
+                cells: constraint.cells,
+                duplicates,
+                empty,
+                status,
+            }),
+            Source::Cage(index) => report.cages.push(CageCheck {
+                index,
+                expected: constraint.sum,
+                actual,
+                operation: constraint.operation,
+                cells: constraint.cells,
+                duplicates,
+                empty,
+                status,
+            }),
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod check_tests {
+    use super::*;
+    use crate::{cage::Cage, format};
+
+    #[test]
+    fn test_report() {
+        let mut board = format::parse("\\ 16\\ 17\\\n\\16 9 9\n\\17 _ 7\n").unwrap();
+        let report = check(&board);
+        assert!(!report.is_solved());
+        let across = report.get(1, 0, Direction::Across).unwrap();
+        assert_eq!((across.expected, across.actual), (16, Some(18)));
+        assert_eq!(across.duplicates, [9]);
+        assert_eq!(across.to_string(), "duplicate 9");
+        let down = report.get(0, 1, Direction::Down).unwrap();
+        assert_eq!(down.empty, [(2, 1)]);
+        assert_eq!(down.to_string(), "incomplete, 1 empty");
+        let right = report.get(0, 2, Direction::Down).unwrap();
+        assert_eq!(right.status, RunStatus::WrongSum);
+        assert_eq!(right.to_string(), "wrong sum, got 16");
+        assert_eq!(report.summary(), "1 wrong sum, 1 duplicate, 2 incomplete");
+        assert_eq!(report.failures().count(), 4);
+        // Checking leaves the board's flags alone
+        assert!(!board.cell(1, 0).is_a_incorrect());
+
+        board.cell_mut(1, 1).set_value(Some(7));
+        board.cell_mut(2, 1).set_value(Some(9));
+        board.cell_mut(1, 2).set_value(Some(9));
+        board.cell_mut(2, 2).set_value(Some(8));
+        assert!(check(&board).is_solved());
+    }
+
+    #[test]
+    fn test_products() {
+        let mut board = format::parse("\\ 12* 2*\n*3 3 1\n*8 4 2\n").unwrap();
+        assert!(check(&board).is_solved());
+        board.cell_mut(1, 2).set_value(Some(2));
+        let report = check(&board);
+        let across = report.get(1, 0, Direction::Across).unwrap();
+        assert_eq!(
+            (across.operation, across.actual),
+            (Operation::Product, Some(6))
+        );
+        assert_eq!(across.to_string(), "wrong product, got 6");
+    }
+
+    #[test]
+    fn test_product_overflow() {
+        let cells: Vec<String> = (0..3)
+            .flat_map(|r| (0..4).map(move |c| format!("{},{}", r, c)))
+            .collect();
+        let text = format!(
+            "cage: *3486784401 repeats {}\n9 9 9 9\n9 9 9 9\n9 9 9 9\n",
+            cells.join(" ")
+        );
+        let report = check(&format::parse(&text).unwrap());
+        let cage = &report.cages[0];
+        assert_eq!((cage.status, cage.actual), (RunStatus::WrongSum, None));
+        assert_eq!(cage.to_string(), "wrong product, got too large a value");
+    }
+
+    #[test]
+    fn test_repeats_allowed() {
+        let text = "\\ 4\\ 4\\\n\\4 2 2\n\\4 2 2\n";
+        let report = check(&format::parse(text).unwrap());
+        assert_eq!(report.summary(), "4 duplicate");
+        let report = check(&format::parse(&format!("repeats: yes\n{}", text)).unwrap());
+        assert!(report.is_solved());
+        assert!(report.runs.iter().all(|r| r.duplicates.is_empty()));
+    }
+
+    #[test]
+    fn test_cages() {
+        let mut board = format::parse("_ _\n_ _\n").unwrap();
+        board.set_cages(vec![
+            Cage::new(vec![(0, 0), (0, 1), (1, 0)], Some(6)),
+            Cage::new(vec![(0, 1), (1, 1)], None),
+        ]);
+        for (r, c, d) in [(0, 0, 1), (0, 1, 2), (1, 0, 3), (1, 1, 2)] {
+            board.cell_mut(r, c).set_value(Some(d));
+        }
+        let report = check(&board);
+        assert!(report.runs.is_empty());
+        assert_eq!(report.cages[0].status, RunStatus::Correct);
+        assert_eq!(report.cages[1].to_string(), "duplicate 2");
+        assert_eq!(report.summary(), "1 correct, 1 duplicate");
+        board.cell_mut(1, 1).set_value(Some(4));
+        board.cell_mut(1, 0).set_value(Some(4));
+        let report = check(&board);
+        assert_eq!(
+            report.cage_failures().next().unwrap().to_string(),
+            "wrong sum, got 7"
+        );
+        assert!(!report.is_solved());
+    }
+}
@@ -0,0 +1,308 @@
+//! Non-interactive subcommands
+
+use kakuro::{
+    Board, Direction, daily, format, generate, import,
+    json::{self, Puzzle},
+    layout::Symmetry,
+    share, solver,
+};
+use std::{
+    fs,
+    io::{Read, stdin},
+    process::ExitCode,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const USAGE: &str = "\
+Usage: kakuro [--compact | --boxed] [COMMAND]
+
+Without a command the interactive game starts. It draws boards boxed, or
+compact when they do not fit the terminal; --compact and --boxed pick one.
+
+Commands:
+  solve FILE                        print the solved puzzle
+  check FILE [--json]               check a filled grid, exiting 1 if it is wrong;
+                                    --json prints every run's sums, repeated
+                                    digits and empty cells
+  generate [--size RxC] [--seed N]  print a new puzzle (default 9x9)
+           [--symmetry S]           none, rotational, left-right or top-bottom
+  daily [--date YYYY-MM-DD]         print the daily puzzle (default today, UTC)
+  rate FILE                         print how hard the puzzle is
+  count FILE [--cap N]              count solutions (default cap 100) and list
+                                    the cells two of them disagree on
+  render FILE                       draw the puzzle as text
+  convert FILE --to json|text       print the puzzle in another format
+  share FILE [--progress]           print a share code for the puzzle, with the
+                                    digits filled in if --progress is given
+  play FILE | --code CODE           play a puzzle file or share code
+  race host FILE [--port N]         race another player on the puzzle, waiting
+                                    for them on port N (default 4747)
+  race join ADDR                    join a race hosted at HOST:PORT
+  coop host FILE [--port N]         share the puzzle for players to solve
+                                    together, on port N (default 4848)
+  coop join ADDR                    join a shared board hosted at HOST:PORT
+  help                              print this message
+
+FILE is a puzzle in the text, JSON, CSV or Tatham-style format, or - for
+standard input.
+";
+
+/// Exit code for a puzzle that failed a check or has no solution
+const FAILURE: u8 = 1;
+/// Exit code for bad arguments or unreadable input
+const USAGE_ERROR: u8 = 2;
+
+/// Returns whether text starts with a Tatham-style `<cols>x<rows>...:` header
+fn is_tatham(text: &str) -> bool {
+    let params = text.trim_start().split_once(':').map_or("", |(p, _)| p);
+    params.split_once('x').is_some_and(|(cols, rest)| {
+        !cols.is_empty()
+            && cols.chars().all(|c| c.is_ascii_digit())
+            && rest.starts_with(|c: char| c.is_ascii_digit())
+    })
+}
+
+/// Reads and parses a puzzle file, or standard input for `-`. JSON is
+/// recognised by its leading brace, Tatham-style descriptions by their size
+/// header and CSV by commas outside `key: value` header lines.
+pub fn load(path: &str) -> Result<Puzzle, String> {
+    let text = if path == "-" {
+        let mut text = String::new();
+        stdin()
+            .read_to_string(&mut text)
+            .map_err(|e| format!("stdin: {}", e))?;
+        text
+    } else {
+        fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?
+    };
+    if text.trim_start().starts_with('{') {
+        json::from_json(&text).map_err(|e| format!("{}: {}", path, e))
+    } else if is_tatham(&text) {
+        import::tatham::parse(&text)
+            .map(Puzzle::new)
+            .map_err(|e| format!("{}: {}", path, e))
+    } else if text.lines().any(|l| l.contains(',') && !l.contains(':')) {
+        import::csv::parse(&text)
+            .map(Puzzle::new)
+            .map_err(|e| format!("{}: {}", path, e))
+    } else {
+        format::parse(&text)
+            .map(Puzzle::new)
+            .map_err(|e| format!("{}: {}", path, e))
+    }
+}
+
+/// Parses a `RxC` size
+fn parse_size(size: &str) -> Result<(usize, usize), String> {
+    size.split_once(['x', 'X'])
+        .and_then(|(r, c)| Some((r.parse().ok()?, c.parse().ok()?)))
+        .ok_or(format!("invalid size '{}', expected RxC", size))
+}
+
+/// Prints the solved puzzle
+fn solve(board: &Board) -> Result<ExitCode, String> {
+    match solver::solve(board) {
+        Some(solved) => {
+            print!("{}", format::to_text(&solved));
+            Ok(ExitCode::SUCCESS)
+        }
+        None => {
+            eprintln!("no solution");
+            Ok(ExitCode::from(FAILURE))
+        }
+    }
+}
+
+/// Checks a filled grid and says why each failing run or cage is wrong, or prints
+/// the whole report as JSON with `--json`
+fn check(board: &Board, args: &[String]) -> Result<ExitCode, String> {
+    let report = board.check_report();
+    match args {
+        [] => {
+            if report.is_solved() {
+                println!("correct");
+            }
+            for run in report.failures() {
+                let name = match run.direction {
+                    Direction::Across => "across",
+                    Direction::Down => "down",
+                };
+                println!(
+                    "row {} col {} {} {}: {}",
+                    run.row + 1,
+                    run.col + 1,
+                    name,
+                    run.expected,
+                    run
+                );
+            }
+            for cage in report.cage_failures() {
+                let (row, col) = cage.cells.iter().min().copied().unwrap_or_default();
+                println!(
+                    "row {} col {} cage {}: {}",
+                    row + 1,
+                    col + 1,
+                    cage.index + 1,
+                    cage
+                );
+            }
+        }
+        [flag] if flag == "--json" => println!(
+            "{}",
+            serde_json::to_string_pretty(&report).expect("report serializes")
+        ),
+        _ => return Err("check takes FILE and an optional --json".to_string()),
+    }
+    Ok(if report.is_solved() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(FAILURE)
+    })
+}
+
+/// Generates and prints a new puzzle
+fn generate(args: &[String]) -> Result<ExitCode, String> {
+    let mut size = (9, 9);
+    let mut seed = None;
+    let mut symmetry = Symmetry::None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--size" => size = parse_size(value()?)?,
+            "--seed" => {
+                let v = value()?;
+                seed = Some(v.parse().map_err(|_| format!("invalid seed '{}'", v))?);
+            }
+            "--symmetry" => symmetry = value()?.parse()?,
+            _ => return Err(format!("unexpected argument '{}'", arg)),
+        }
+    }
+    let seed = seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0)
+    });
+    if size.0 < 3 || size.1 < 3 {
+        return Err("size must be at least 3x3".to_string());
+    }
+    let board = generate::generate_symmetric(size.0, size.1, symmetry, seed).ok_or(format!(
+        "could not lay out a {}x{} puzzle, try a wider size",
+        size.0, size.1
+    ))?;
+    print!("{}", format::to_text(&board));
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Prints the daily puzzle for today or a given date
+fn daily(args: &[String]) -> Result<ExitCode, String> {
+    let date = match args {
+        [] => daily::Date::today(),
+        [flag, date] if flag == "--date" => date.parse()?,
+        _ => return Err("daily takes an optional --date YYYY-MM-DD".to_string()),
+    };
+    print!("{}", format::to_text(&daily::puzzle(date)));
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Prints a puzzle in the requested format
+fn convert(puzzle: &Puzzle, args: &[String]) -> Result<ExitCode, String> {
+    match args {
+        [flag, to] if flag == "--to" && to == "json" => println!("{}", json::to_json(puzzle)),
+        [flag, to] if flag == "--to" && to == "text" => {
+            print!("{}", format::to_text(&puzzle.board))
+        }
+        _ => return Err("convert needs --to json or --to text".to_string()),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Prints the puzzle's difficulty
+fn rate(board: &Board) -> Result<ExitCode, String> {
+    let Some(rating) = solver::rate(board) else {
+        eprintln!("no solution");
+        return Ok(ExitCode::from(FAILURE));
+    };
+    print!("{} ({} guesses)", rating.difficulty, rating.guesses);
+    if !rating.unique {
+        print!(", multiple solutions");
+    }
+    println!();
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Counts the puzzle's solutions, exiting 1 unless there is exactly one
+fn count(board: &Board, args: &[String]) -> Result<ExitCode, String> {
+    let cap = match args {
+        [] => 100,
+        [flag, n] if flag == "--cap" => n.parse().map_err(|_| format!("invalid cap '{}'", n))?,
+        _ => return Err("count takes FILE and an optional --cap N".to_string()),
+    };
+    let count = solver::count_solutions(board, cap);
+    let at_least = if count.capped { "at least " } else { "" };
+    let plural = if count.count == 1 { "" } else { "s" };
+    println!("{}{} solution{}", at_least, count.count, plural);
+    for (r, c) in &count.ambiguous {
+        println!("row {} col {}: ambiguous", r + 1, c + 1);
+    }
+    Ok(if count.count == 1 && !count.capped {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(FAILURE)
+    })
+}
+
+/// Prints the puzzle's share code
+fn share(board: &Board, args: &[String]) -> Result<ExitCode, String> {
+    let progress = match args {
+        [] => false,
+        [flag] if flag == "--progress" => true,
+        _ => return Err("share takes FILE and an optional --progress".to_string()),
+    };
+    println!("{}", share::encode(board, progress));
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Runs a subcommand, printing errors and returning the exit code
+pub fn run(args: &[String]) -> ExitCode {
+    let file = || match args {
+        [_, path] => load(path).map(|p| p.board),
+        _ => Err(format!("{} takes exactly one FILE\n\n{}", args[0], USAGE)),
+    };
+    let result = match args[0].as_str() {
+        "solve" => file().and_then(|b| solve(&b)),
+        "check" => match args {
+            [_, path, rest @ ..] => load(path).and_then(|p| check(&p.board, rest)),
+            _ => Err(format!("check takes a FILE\n\n{}", USAGE)),
+        },
+        "generate" => generate(&args[1..]),
+        "daily" => daily(&args[1..]),
+        "rate" => file().and_then(|b| rate(&b)),
+        "render" => file().map(|b| {
+            print!("{}", b.render());
+            ExitCode::SUCCESS
+        }),
+        "count" => match args {
+            [_, path, rest @ ..] => load(path).and_then(|p| count(&p.board, rest)),
+            _ => Err(format!("count takes a FILE\n\n{}", USAGE)),
+        },
+        "convert" => match args {
+            [_, path, rest @ ..] => load(path).and_then(|p| convert(&p, rest)),
+            _ => Err(format!("convert takes a FILE\n\n{}", USAGE)),
+        },
+        "share" => match args {
+            [_, path, rest @ ..] => load(path).and_then(|p| share(&p.board, rest)),
+            _ => Err(format!("share takes a FILE\n\n{}", USAGE)),
+        },
+        "help" | "-h" | "--help" => {
+            print!("{}", USAGE);
+            Ok(ExitCode::SUCCESS)
+        }
+        other => Err(format!("unknown command '{}'\n\n{}", other, USAGE)),
+    };
+    result.unwrap_or_else(|e| {
+        eprintln!("kakuro: {}", e);
+        ExitCode::from(USAGE_ERROR)
+    })
+}
@@ -0,0 +1,622 @@
+//! Cooperative play on a shared board
+//!
+//! A host process owns the board and any number of players connect to it
+//! over TCP, each with a cursor drawn in their own colour on everyone
+//! else's screen. Players send the cells they change and where their cursor
+//! is; the host applies changes in the order they arrive, so when two
+//! players write the same cell the later one wins, and sends every change
+//! and cursor move on to all players. The host keeps each player's changes,
+//! so undo takes back that player's last change only, writing the cell back
+//! as it was before it. A change another player has since overwritten is
+//! theirs now and is skipped. Messages are JSON, one per line.
+
+use crate::{
+    Board, Cell, Result, Turn,
+    json::{Puzzle, PuzzleFile},
+    net::{self, Peer, TICK, invalid, spawn_reader, write_line},
+    render, term, win,
+};
+use crossterm::{
+    event::{self, Event, KeyCode},
+    style::Color,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    io::BufReader,
+    net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs},
+    sync::mpsc::{self, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Port coop hosts listen on unless told otherwise
+pub const DEFAULT_PORT: u16 = 4848;
+
+/// Cursor colours and their names, taken in turn by player number
+const COLORS: [(Color, &str); 6] = [
+    (Color::DarkCyan, "cyan"),
+    (Color::DarkMagenta, "magenta"),
+    (Color::DarkYellow, "yellow"),
+    (Color::DarkGreen, "green"),
+    (Color::DarkBlue, "blue"),
+    (Color::DarkRed, "red"),
+];
+
+/// Returns a player's cursor colour and its name
+pub fn color(player: usize) -> (Color, &'static str) {
+    COLORS[player % COLORS.len()]
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// The contents of a playable cell
+pub struct Entry {
+    /// Value, if filled in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<u8>,
+    /// Pencil marks, one bit per digit
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub notes: u16,
+}
+
+/// Returns whether a bit set is empty, for leaving out empty notes
+fn is_zero(notes: &u16) -> bool {
+    *notes == 0
+}
+
+impl Entry {
+    /// Returns the contents of a cell
+    pub fn of(cell: &Cell) -> Self {
+        Self {
+            value: cell.value(),
+            notes: cell.notes(),
+        }
+    }
+
+    /// Writes the contents into a cell
+    pub fn apply(self, cell: &mut Cell) {
+        cell.set_value(self.value);
+        cell.set_notes(self.notes);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// A line sent by a player to the host
+pub enum Request {
+    /// Sets a cell's contents
+    Set {
+        /// Row of the cell
+        row: usize,
+        /// Column of the cell
+        col: usize,
+        /// New contents
+        entry: Entry,
+    },
+    /// Moves the player's cursor
+    Cursor {
+        /// Row of the cursor
+        row: usize,
+        /// Column of the cursor
+        col: usize,
+    },
+    /// Takes back the player's last change
+    Undo,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// A line sent by the host to players
+pub enum Update {
+    /// Sent once on joining: the player's number and the board as it stands
+    Welcome {
+        /// Number of the player joining
+        player: usize,
+        /// The shared board, with every player's values and notes
+        puzzle: Box<PuzzleFile>,
+    },
+    /// A cell's contents changed
+    Set {
+        /// Row of the cell
+        row: usize,
+        /// Column of the cell
+        col: usize,
+        /// New contents
+        entry: Entry,
+    },
+    /// A player's cursor moved
+    Cursor {
+        /// Number of the player
+        player: usize,
+        /// Row of the cursor
+        row: usize,
+        /// Column of the cursor
+        col: usize,
+    },
+    /// A player left
+    Left {
+        /// Number of the player
+        player: usize,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// A change a player made, kept so they can undo it
+struct Change {
+    /// Row of the cell
+    row: usize,
+    /// Column of the cell
+    col: usize,
+    /// Contents before the change
+    before: Entry,
+    /// Contents the change wrote
+    after: Entry,
+}
+
+/// The host's copy of the shared board, with every player's cursor and
+/// changes
+pub struct Session {
+    /// The authoritative board
+    board: Board,
+    /// Each connected player's changes, oldest first
+    history: BTreeMap<usize, Vec<Change>>,
+    /// Each connected player's cursor, once they have sent one
+    cursors: BTreeMap<usize, (usize, usize)>,
+    /// Number the next player to join gets
+    next: usize,
+}
+
+impl Session {
+    /// Starts a session on a board
+    pub fn new(board: Board) -> Self {
+        Self {
+            board,
+            history: BTreeMap::new(),
+            cursors: BTreeMap::new(),
+            next: 1,
+        }
+    }
+
+    /// Returns the shared board
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Adds a player, returning their number and the updates that bring
+    /// them up to date: the board, then the other players' cursors
+    pub fn join(&mut self) -> (usize, Vec<Update>) {
+        let player = self.next;
+        self.next += 1;
+        self.history.insert(player, Vec::new());
+        let mut file = Puzzle::new(self.board.clone()).to_file();
+        for cell in &mut file.cells {
+            cell.solution = None;
+        }
+        let mut updates = vec![Update::Welcome {
+            player,
+            puzzle: Box::new(file),
+        }];
+        for (&other, &(row, col)) in &self.cursors {
+            updates.push(Update::Cursor {
+                player: other,
+                row,
+                col,
+            });
+        }
+        (player, updates)
+    }
+
+    /// Removes a player and their history, returning the update for the
+    /// others
+    pub fn leave(&mut self, player: usize) -> Update {
+        self.history.remove(&player);
+        self.cursors.remove(&player);
+        Update::Left { player }
+    }
+
+    /// Applies a player's request, returning the updates for every player.
+    /// Requests for cells off the board, clue cells or digits the rules do
+    /// not allow change nothing.
+    pub fn apply(&mut self, player: usize, request: Request) -> Vec<Update> {
+        match request {
+            Request::Set { row, col, entry } => {
+                let rules = self.board.rules();
+                let allowed = entry.value.is_none_or(|d| rules.contains(d))
+                    && entry.notes & !rules.mask() == 0;
+                let before = match self.board.get(row, col) {
+                    Some(cell) if allowed && !cell.is_clue() => Entry::of(cell),
+                    _ => return Vec::new(),
+                };
+                if before == entry {
+                    return Vec::new();
+                }
+                if let Some(history) = self.history.get_mut(&player) {
+                    history.push(Change {
+                        row,
+                        col,
+                        before,
+                        after: entry,
+                    });
+                }
+                entry.apply(self.board.cell_mut(row, col));
+                vec![Update::Set { row, col, entry }]
+            }
+            Request::Cursor { row, col } if self.board.get(row, col).is_some() => {
+                self.cursors.insert(player, (row, col));
+                vec![Update::Cursor { player, row, col }]
+            }
+            Request::Cursor { .. } => Vec::new(),
+            Request::Undo => {
+                let Some(history) = self.history.get_mut(&player) else {
+                    return Vec::new();
+                };
+                // Changes others have written over since are dropped
+                let change = std::iter::from_fn(|| history.pop()).find(|change| {
+                    Entry::of(self.board.cell(change.row, change.col)) == change.after
+                });
+                let Some(Change {
+                    row, col, before, ..
+                }) = change
+                else {
+                    return Vec::new();
+                };
+                before.apply(self.board.cell_mut(row, col));
+                vec![Update::Set {
+                    row,
+                    col,
+                    entry: before,
+                }]
+            }
+        }
+    }
+}
+
+/// Something that happened on one of the host's connections
+enum Incoming {
+    /// A player connected
+    Joined(TcpStream),
+    /// A player sent a request
+    Request(usize, Request),
+    /// A player's connection closed
+    Left(usize),
+}
+
+/// Sends updates to every player, leaving any that cannot be written to
+/// for their reading thread to notice
+fn broadcast(players: &mut BTreeMap<usize, TcpStream>, updates: &[Update]) {
+    for stream in players.values_mut() {
+        for update in updates {
+            if write_line(stream, update).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// Hosts a board for players connecting to `listener`, until the listener
+/// fails
+pub fn serve(listener: TcpListener, board: Board) -> Result<()> {
+    let (sender, events) = mpsc::channel();
+    let accepted = sender.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            if accepted.send(Incoming::Joined(stream)).is_err() {
+                return;
+            }
+        }
+    });
+    let mut session = Session::new(board);
+    let mut players = BTreeMap::new();
+    for event in events {
+        match event {
+            Incoming::Joined(mut stream) => {
+                let (player, welcome) = session.join();
+                let sent = welcome.iter().try_for_each(|u| write_line(&mut stream, u));
+                let reader = stream.try_clone();
+                let (Ok(()), Ok(reader)) = (sent, reader) else {
+                    session.leave(player);
+                    continue;
+                };
+                let requests = sender.clone();
+                let read = spawn_reader(BufReader::new(reader));
+                thread::spawn(move || {
+                    for request in read {
+                        if requests.send(Incoming::Request(player, request)).is_err() {
+                            return;
+                        }
+                    }
+                    let _ = requests.send(Incoming::Left(player));
+                });
+                players.insert(player, stream);
+            }
+            Incoming::Request(player, request) => {
+                let updates = session.apply(player, request);
+                broadcast(&mut players, &updates);
+            }
+            Incoming::Left(player) => {
+                if let Some(stream) = players.remove(&player) {
+                    let _ = stream.shutdown(Shutdown::Both);
+                }
+                let update = session.leave(player);
+                broadcast(&mut players, &[update]);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A player's connection to the host
+pub struct Client {
+    /// The host's end of the connection
+    peer: Peer<Update>,
+    /// The player's number
+    player: usize,
+}
+
+impl Client {
+    /// Connects to a host and reads the board as it stands
+    pub fn join(addr: impl ToSocketAddrs) -> Result<(Self, Puzzle)> {
+        let (stream, reader, first) = net::connect(addr)?;
+        let Update::Welcome { player, puzzle } = first else {
+            return Err(invalid("host did not send the board").into());
+        };
+        let puzzle = Puzzle::from_file(*puzzle).map_err(|e| invalid(e.to_string()))?;
+        let client = Self {
+            peer: Peer::new(stream, reader),
+            player,
+        };
+        Ok((client, puzzle))
+    }
+
+    /// Returns the player's number
+    pub fn player(&self) -> usize {
+        self.player
+    }
+
+    /// Sends a request to the host
+    pub fn send(&mut self, request: Request) -> Result<()> {
+        self.peer.send(&request)
+    }
+
+    /// Applies any updates the host has sent since the last call to the
+    /// board and the other players' cursors, returning false once the host
+    /// has gone
+    pub fn update(&self, board: &mut Board, cursors: &mut BTreeMap<usize, (usize, usize)>) -> bool {
+        loop {
+            match self.peer.try_recv() {
+                Ok(Update::Set { row, col, entry }) => {
+                    if let Some(cell) = board.get_mut(row, col) {
+                        entry.apply(cell);
+                    }
+                }
+                Ok(Update::Cursor { player, row, col }) if player != self.player => {
+                    cursors.insert(player, (row, col));
+                }
+                Ok(Update::Left { player }) => {
+                    cursors.remove(&player);
+                }
+                Ok(_) => {}
+                Err(TryRecvError::Empty) => return true,
+                Err(TryRecvError::Disconnected) => return false,
+            }
+        }
+    }
+}
+
+/// Returns the status line: who the player is, who else is playing in
+/// which colour, and the player's last check
+pub fn status(
+    player: usize,
+    others: &BTreeMap<usize, (usize, usize)>,
+    connected: bool,
+    finish: Option<Duration>,
+) -> String {
+    let mut line = format!("you: player {}", player);
+    if !connected {
+        line.push_str(" | host gone");
+    } else if others.is_empty() {
+        line.push_str(" | no one else here");
+    } else {
+        let names: Vec<String> = (others.keys())
+            .map(|&other| format!("{} {}", other, color(other).1))
+            .collect();
+        line.push_str(&format!(" | others: {}", names.join(", ")));
+    }
+    if let Some(time) = finish {
+        line.push_str(&format!(" | solved in {}", win::clock(time.as_secs())));
+    }
+    line
+}
+
+/// Returns the help line, with undo in place of the solver walkthrough
+fn help(board: &Board) -> String {
+    let keys = [
+        ("esc", "leave"),
+        ("return", "check"),
+        ("u", "undo"),
+        ("n", "notes"),
+        ("h", "highlight"),
+        ("s", "same digits"),
+        ("v", "view"),
+    ];
+    render::help_line(&keys, &board.mode_tag())
+}
+
+/// Plays on the shared board until the player leaves, sending each change
+/// and cursor move to the host and drawing what the others do
+pub fn play(mut client: Client, mut board: Board) -> Result<()> {
+    let start = Instant::now();
+    let mut cursors = BTreeMap::new();
+    let mut connected = true;
+    let mut sent = None;
+    let mut finish = None;
+    let _guard = term::Guard::new()?;
+    render::invalidate();
+    loop {
+        connected &= client.update(&mut board, &mut cursors);
+        let cursor = board.cursor();
+        if connected && sent != Some(cursor) {
+            let (row, col) = cursor;
+            connected = client.send(Request::Cursor { row, col }).is_ok();
+            sent = Some(cursor);
+        }
+        let drawn = cursors.iter().map(|(&p, &(r, c))| (r, c, color(p).0));
+        board.set_cursors(drawn.collect());
+        let line = status(client.player(), &cursors, connected, finish);
+        board.draw_footer(&help(&board), &line)?;
+
+        if !event::poll(TICK)? {
+            continue;
+        }
+        let read = term::read()?;
+        if let Event::Resize(..) = read {
+            render::invalidate();
+        }
+        let Event::Key(key) = read else {
+            continue;
+        };
+        match key.code {
+            KeyCode::Esc => return Ok(()),
+            KeyCode::Char('u') => connected &= client.send(Request::Undo).is_ok(),
+            KeyCode::Char('w') => {}
+            _ => {
+                let (row, col) = board.cursor();
+                let before = Entry::of(board.cell(row, col));
+                let turn = board.key(key)?;
+                let entry = Entry::of(board.cell(row, col));
+                if entry != before {
+                    connected &= client.send(Request::Set { row, col, entry }).is_ok();
+                }
+                if turn == Turn::Solved {
+                    finish = Some(start.elapsed());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod coop_tests {
+    use super::*;
+    use crate::format;
+
+    fn board() -> Board {
+        format::parse("\\ 16\\ 17\\\n\\16 _ _\n\\17 _ _\n").unwrap()
+    }
+
+    fn set(row: usize, col: usize, value: u8) -> Request {
+        let entry = Entry {
+            value: Some(value),
+            notes: 0,
+        };
+        Request::Set { row, col, entry }
+    }
+
+    #[test]
+    fn test_last_writer_wins_and_undo() {
+        let mut session = Session::new(board());
+        let (one, _) = session.join();
+        let (two, _) = session.join();
+        assert_eq!(session.apply(one, set(1, 1, 7)).len(), 1);
+        session.apply(two, set(1, 1, 9));
+        assert_eq!(session.board().cell(1, 1).value(), Some(9));
+        // A change someone has written over is no longer the player's to undo
+        session.apply(one, set(1, 2, 8));
+        assert_eq!(
+            session.apply(one, Request::Undo),
+            vec![Update::Set {
+                row: 1,
+                col: 2,
+                entry: Entry::default()
+            }]
+        );
+        assert!(session.apply(one, Request::Undo).is_empty());
+        assert_eq!(session.board().cell(1, 1).value(), Some(9));
+        // Undo takes back the asking player's change, restoring what it
+        // replaced
+        session.apply(two, Request::Undo);
+        assert_eq!(session.board().cell(1, 1).value(), Some(7));
+        assert!(session.apply(two, Request::Undo).is_empty());
+        // Clue cells, cells off the board and unchanged cells are ignored
+        assert!(session.apply(one, set(0, 0, 1)).is_empty());
+        assert!(session.apply(one, set(5, 5, 1)).is_empty());
+        assert!(session.apply(one, set(1, 1, 7)).is_empty());
+    }
+
+    #[test]
+    fn test_join_sends_board_and_cursors() {
+        let mut session = Session::new(board());
+        let (one, _) = session.join();
+        session.apply(one, set(1, 2, 9));
+        session.apply(one, Request::Cursor { row: 1, col: 2 });
+        let (two, updates) = session.join();
+        assert_eq!(two, 2);
+        let Update::Welcome { player, puzzle } = &updates[0] else {
+            panic!("no welcome");
+        };
+        assert_eq!(*player, 2);
+        let shared = Puzzle::from_file(*puzzle.clone()).unwrap();
+        assert_eq!(shared.board.cell(1, 2).value(), Some(9));
+        assert_eq!(
+            updates[1],
+            Update::Cursor {
+                player: 1,
+                row: 1,
+                col: 2
+            }
+        );
+        assert_eq!(session.leave(one), Update::Left { player: 1 });
+        assert_eq!(session.join().1.len(), 1);
+    }
+
+    #[test]
+    fn test_status() {
+        let mut others = BTreeMap::new();
+        assert_eq!(
+            status(1, &others, true, None),
+            "you: player 1 | no one else here"
+        );
+        others.insert(2, (0, 0));
+        others.insert(3, (1, 1));
+        assert_eq!(
+            status(1, &others, true, Some(Duration::from_secs(65))),
+            "you: player 1 | others: 2 yellow, 3 green | solved in 1:05"
+        );
+        assert_eq!(status(1, &others, false, None), "you: player 1 | host gone");
+    }
+
+    #[test]
+    fn test_serve_over_loopback() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || serve(listener, board()));
+        let (mut one, puzzle) = Client::join(addr).unwrap();
+        let (two, _) = Client::join(addr).unwrap();
+        assert_eq!((one.player(), two.player()), (1, 2));
+
+        let mut mine = puzzle.board.clone();
+        let mut theirs = puzzle.board;
+        let mut cursors = BTreeMap::new();
+        one.send(set(2, 2, 8)).unwrap();
+        one.send(Request::Cursor { row: 2, col: 2 }).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while cursors.get(&1) != Some(&(2, 2)) {
+            assert!(Instant::now() < deadline, "updates did not arrive");
+            assert!(two.update(&mut theirs, &mut cursors));
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(theirs.cell(2, 2).value(), Some(8));
+
+        // The sender hears of its own change too, but not its own cursor
+        let mut seen = BTreeMap::new();
+        while mine.cell(2, 2).value().is_none() {
+            assert!(Instant::now() < deadline, "updates did not arrive");
+            assert!(one.update(&mut mine, &mut seen));
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(seen.is_empty());
+    }
+}
@@ -0,0 +1,293 @@
+//! Daily Kakuro
+//!
+//! Everyone gets the same puzzle on the same day: the generator is seeded
+//! from the UTC date, so no server is needed. Completion times are kept per
+//! date in a local JSON stats file, from which the current streak of
+//! consecutive days is counted.
+
+use crate::{
+    Board, error, generate, store, term,
+    win::{self, Choice},
+};
+use crossterm::{
+    cursor::MoveTo,
+    event::Event,
+    execute,
+    style::{Print, Stylize},
+    terminal,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt, io,
+    io::stdout,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Rows and columns of the daily puzzle, including the clue row and column
+pub const SIZE: (usize, usize) = (9, 9);
+/// Environment variable overriding where stats are kept
+pub const STATS_ENV: &str = "KAKURO_STATS";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+/// A calendar date
+pub struct Date {
+    /// Year
+    pub year: i32,
+    /// Month, 1-12
+    pub month: u32,
+    /// Day of the month, 1-31
+    pub day: u32,
+}
+
+impl Date {
+    /// Returns the date a number of days after 1970-01-01
+    pub fn from_days(days: i64) -> Self {
+        // Days to civil date, counting eras of 400 years from 0000-03-01
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = (yoe + era * 400 + i64::from(month <= 2)) as i32;
+        Self { year, month, day }
+    }
+
+    /// Returns the number of days since 1970-01-01
+    pub fn days(self) -> i64 {
+        let year = i64::from(self.year) - i64::from(self.month <= 2);
+        let era = year.div_euclid(400);
+        let yoe = year.rem_euclid(400);
+        let m = i64::from(self.month);
+        let mp = if m > 2 { m - 3 } else { m + 9 };
+        let doy = (153 * mp + 2) / 5 + i64::from(self.day) - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146_097 + doe - 719_468
+    }
+
+    /// Returns today's date in UTC
+    pub fn today() -> Self {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self::from_days((secs / 86_400) as i64)
+    }
+
+    /// Returns the date before this one
+    pub fn previous(self) -> Self {
+        Self::from_days(self.days() - 1)
+    }
+
+    /// Returns the generator seed for this date
+    pub fn seed(self) -> u64 {
+        self.year as u64 * 10_000 + u64::from(self.month) * 100 + u64::from(self.day)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl FromStr for Date {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid date '{}', expected YYYY-MM-DD", s);
+        let mut parts = s.splitn(3, '-');
+        let mut part = || {
+            parts
+                .next()
+                .and_then(|p| p.parse::<i64>().ok())
+                .ok_or_else(invalid)
+        };
+        let (year, month, day) = (part()?, part()?, part()?);
+        let date = Date {
+            year: year as i32,
+            month: month as u32,
+            day: day as u32,
+        };
+        // Reject days past the end of the month by round tripping
+        if Date::from_days(date.days()) == date {
+            Ok(date)
+        } else {
+            Err(invalid())
+        }
+    }
+}
+
+/// Returns the daily puzzle for a date
+pub fn puzzle(date: Date) -> Board {
+    generate::generate(SIZE.0, SIZE.1, date.seed()).expect("daily size is valid")
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// Local record of completed daily puzzles
+pub struct Stats {
+    /// Best completion time in seconds, keyed by `YYYY-MM-DD` date
+    pub completed: BTreeMap<String, u64>,
+}
+
+impl Stats {
+    /// Reads stats from a file, starting empty if it does not exist
+    pub fn load(path: &Path) -> io::Result<Self> {
+        store::load(path)
+    }
+
+    /// Writes stats to a file, creating its directory if needed
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        store::save(self, path)
+    }
+
+    /// Records a completion, keeping the best time for the date
+    pub fn record(&mut self, date: Date, secs: u64) {
+        let best = self.completed.entry(date.to_string()).or_insert(secs);
+        *best = (*best).min(secs);
+    }
+
+    /// Returns the completion time for a date
+    pub fn time(&self, date: Date) -> Option<u64> {
+        self.completed.get(&date.to_string()).copied()
+    }
+
+    /// Returns the number of consecutive days completed up to `today`. A
+    /// streak is still alive if today has not been played yet.
+    pub fn streak(&self, today: Date) -> usize {
+        let mut date = today;
+        if self.time(date).is_none() {
+            date = date.previous();
+        }
+        let mut streak = 0;
+        while self.time(date).is_some() {
+            streak += 1;
+            date = date.previous();
+        }
+        streak
+    }
+}
+
+/// Returns where stats are kept: `$KAKURO_STATS`, or `.kakuro/stats.json`
+/// in the home directory
+pub fn stats_path() -> Option<PathBuf> {
+    store::path(STATS_ENV, "stats.json")
+}
+
+/// Plays today's puzzle, records the time if it is solved and shows the
+/// win screen with the current streak. Leaving unsolved shows the streak
+/// without recording anything.
+pub fn play() -> error::Result<()> {
+    let today = Date::today();
+    let title = format!("Daily Kakuro {}", today);
+    let path = stats_path();
+    loop {
+        let mut board = puzzle(today);
+        let solved = board.run()?;
+        let mut stats = path
+            .as_deref()
+            .and_then(|p| Stats::load(p).ok())
+            .unwrap_or_default();
+        let Some(time) = solved else {
+            let mut lines = vec!["Not solved yet".to_string()];
+            if let Some(best) = stats.time(today) {
+                lines.push(format!("Best time today: {}", win::clock(best)));
+            }
+            lines.push(format!("Streak: {} days", stats.streak(today)));
+            return summary(&title, &lines);
+        };
+        stats.record(today, time.as_secs());
+        let mut lines = win::summary(time, board.checks());
+        if let Some(Err(e)) = path.as_deref().map(|p| stats.save(p)) {
+            lines.push(format!("Could not save stats: {}", e));
+        }
+        if let Some(best) = stats.time(today) {
+            lines.push(format!("Best time today: {}", win::clock(best)));
+        }
+        lines.push(format!("Streak: {} days", stats.streak(today)));
+        if win::show(&title, &lines, false)? != Choice::Replay {
+            return Ok(());
+        }
+    }
+}
+
+/// Shows a title and lines until a key is pressed
+fn summary(title: &str, lines: &[String]) -> error::Result<()> {
+    let _guard = term::Guard::new()?;
+    let mut stdout = stdout();
+    execute!(
+        stdout,
+        terminal::Clear(terminal::ClearType::All),
+        MoveTo(0, 0),
+        Print(title.dark_magenta()),
+    )?;
+    for (i, line) in lines.iter().enumerate() {
+        execute!(stdout, MoveTo(0, i as u16 + 2), Print(line))?;
+    }
+    execute!(
+        stdout,
+        MoveTo(0, lines.len() as u16 + 3),
+        Print("Press any key to go home")
+    )?;
+    while !matches!(term::read()?, Event::Key(_)) {}
+    Ok(())
+}
+
+#[cfg(test)]
+mod daily_tests {
+    use super::*;
+    use std::{env, fs};
+
+    #[test]
+    fn test_dates() {
+        let date = Date {
+            year: 2024,
+            month: 2,
+            day: 29,
+        };
+        assert_eq!(date.days(), 19_782);
+        assert_eq!(Date::from_days(19_782), date);
+        assert_eq!(Date::from_days(0).to_string(), "1970-01-01");
+        assert_eq!(date.previous().to_string(), "2024-02-28");
+        assert_eq!("2024-03-01".parse::<Date>().unwrap().previous(), date);
+        assert!("2023-02-29".parse::<Date>().is_err());
+        assert!("2023-1".parse::<Date>().is_err());
+        assert_eq!(date.seed(), 20_240_229);
+    }
+
+    #[test]
+    fn test_puzzle_is_the_same_all_day() {
+        let date = "2025-06-01".parse().unwrap();
+        let text = crate::format::to_text(&puzzle(date));
+        assert_eq!(crate::format::to_text(&puzzle(date)), text);
+        assert_ne!(crate::format::to_text(&puzzle(date.previous())), text);
+    }
+
+    #[test]
+    fn test_stats() {
+        let today: Date = "2025-01-02".parse().unwrap();
+        let mut stats = Stats::default();
+        assert_eq!(stats.streak(today), 0);
+        stats.record(today.previous(), 300);
+        assert_eq!(stats.streak(today), 1);
+        stats.record(today, 200);
+        stats.record(today, 250);
+        assert_eq!(stats.time(today), Some(200));
+        stats.record("2024-12-31".parse().unwrap(), 100);
+        stats.record("2024-12-29".parse().unwrap(), 100);
+        assert_eq!(stats.streak(today), 3);
+
+        let path = env::temp_dir()
+            .join(format!("kakuro-stats-{}", std::process::id()))
+            .join("stats.json");
+        stats.save(&path).unwrap();
+        assert_eq!(Stats::load(&path).unwrap(), stats);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert_eq!(Stats::load(&path).unwrap(), Stats::default());
+    }
+}
@@ -0,0 +1,573 @@
+//! Interactive puzzle editor
+//!
+//! Cells are toggled between blank blocks and playable cells, together with
+//! their image under the chosen [`Symmetry`]. Clue sums can be typed straight
+//! into clue cells, or a solution typed into the playable cells, in which case
+//! the sum of every completely filled run is derived from it. A clue cell can
+//! be switched to a product clue, whose runs multiply to their clues. Puzzles
+//! are saved in the text format, or as JSON with their solution when the file
+//! name ends in `.json`.
+
+use crate::{
+    Board, Cell, Direction, Result, format,
+    json::{self, Puzzle},
+    layout::{self, Symmetry},
+    render,
+    rules::Operation,
+    solver::Solver,
+    term,
+};
+use crossterm::{
+    cursor::MoveTo,
+    event::{Event, KeyCode},
+    execute,
+    style::{Attribute, Print, Stylize},
+    terminal,
+};
+use std::{fs, io::stdout};
+
+/// Smallest board the editor creates
+pub const MIN_SIZE: usize = 3;
+/// Largest board the editor creates
+pub const MAX_SIZE: usize = 20;
+/// Largest sum a run of distinct digits can have
+const MAX_SUM: u32 = 45;
+/// Largest product a run of distinct digits can have
+const MAX_PRODUCT: u32 = 362_880;
+/// Returns the largest value a clue of this operation may be typed up to
+fn max_value(operation: Operation) -> u32 {
+    match operation {
+        Operation::Sum => MAX_SUM,
+        Operation::Product => MAX_PRODUCT,
+    }
+}
+
+/// Guesses the uniqueness check may make before giving up
+const SOLVER_BUDGET: usize = 20_000;
+/// Solutions the uniqueness check counts before stopping
+const SOLUTION_CAP: usize = 100;
+
+/// Puzzle editor state
+pub struct Editor {
+    /// Board being edited, its values forming the solution
+    board: Board,
+    /// Which sum digits typed on a clue cell change
+    direction: Direction,
+    /// Symmetry kept when toggling cells
+    symmetry: Symmetry,
+    /// Result of the last check or save
+    status: String,
+}
+
+impl Editor {
+    /// Creates an editor for a board with a blank top row and left column and
+    /// every other cell playable
+    pub fn new(rows: usize, cols: usize) -> Self {
+        let grid = (0..rows)
+            .map(|r| {
+                (0..cols)
+                    .map(|c| {
+                        if r == 0 || c == 0 {
+                            Cell::new_clue(None, None)
+                        } else {
+                            Cell::new_play()
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        Self {
+            board: Board::new(grid, rows, cols),
+            direction: Direction::Across,
+            symmetry: Symmetry::None,
+            status: String::new(),
+        }
+    }
+
+    /// Returns the board being edited
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Returns a mutable reference to the board being edited
+    pub fn board_mut(&mut self) -> &mut Board {
+        &mut self.board
+    }
+
+    /// Returns which sum typed digits change
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Returns the symmetry kept when toggling cells
+    pub fn symmetry(&self) -> Symmetry {
+        self.symmetry
+    }
+
+    /// Switches to the next symmetry
+    pub fn cycle_symmetry(&mut self) {
+        self.symmetry = self.symmetry.next();
+    }
+
+    /// Returns the result of the last check or save
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+
+    /// Switches between typing across and down sums
+    pub fn switch_direction(&mut self) {
+        self.direction = match self.direction {
+            Direction::Across => Direction::Down,
+            Direction::Down => Direction::Across,
+        };
+    }
+
+    /// Switches the clue cell under the cursor between sums and products,
+    /// rederiving its sums from any filled runs
+    pub fn toggle_operation(&mut self) {
+        let cell = self.board.cur_cell();
+        if !cell.is_clue() {
+            return;
+        }
+        cell.set_operation(cell.operation().toggled());
+        self.board.set_marked(Vec::new());
+        self.derive_sums();
+    }
+
+    /// Turns the cell under the cursor, and its symmetric image, into a blank
+    /// block or a playable cell
+    pub fn toggle_cell(&mut self) {
+        let (row, col) = self.board.cursor();
+        self.board.set_marked(Vec::new());
+        layout::toggle(&mut self.board, row, col, self.symmetry);
+        self.derive_sums();
+    }
+
+    /// Types a digit: into the selected sum on a clue cell, or as the
+    /// solution digit on a playable cell
+    pub fn digit(&mut self, c: char) {
+        let Some(d) = c.to_digit(10) else {
+            return;
+        };
+        self.board.set_marked(Vec::new());
+        let cell = self.board.cur_cell();
+        if !cell.is_clue() {
+            if d != 0 {
+                self.board.number(c);
+                self.derive_sums();
+            }
+            return;
+        }
+        let sum = match self.direction {
+            Direction::Across => cell.across_sum(),
+            Direction::Down => cell.down_sum(),
+        };
+        let max = max_value(cell.operation());
+        // Start over once the sum would be more than any run can make
+        let sum = match sum
+            .unwrap_or(0)
+            .checked_mul(10)
+            .and_then(|s| s.checked_add(d))
+        {
+            Some(s) if s <= max => s,
+            _ => d,
+        };
+        self.set_sum((sum > 0).then_some(sum));
+    }
+
+    /// Removes the last digit of the selected sum, or the playable cell's
+    /// value
+    pub fn backspace(&mut self) {
+        self.board.set_marked(Vec::new());
+        let cell = self.board.cur_cell();
+        if !cell.is_clue() {
+            self.board.backspace();
+            self.derive_sums();
+            return;
+        }
+        let sum = match self.direction {
+            Direction::Across => cell.across_sum(),
+            Direction::Down => cell.down_sum(),
+        };
+        self.set_sum(sum.map(|s| s / 10).filter(|&s| s > 0));
+    }
+
+    /// Sets the selected sum of the clue cell under the cursor
+    fn set_sum(&mut self, sum: Option<u32>) {
+        let cell = self.board.cur_cell();
+        let operation = cell.operation();
+        *cell = match self.direction {
+            Direction::Across => Cell::new_clue(sum, cell.down_sum()),
+            Direction::Down => Cell::new_clue(cell.across_sum(), sum),
+        };
+        cell.set_operation(operation);
+    }
+
+    /// Recomputes clue sums from the solution: a clue with no cells after it
+    /// loses its sum, and a completely filled run sets it
+    pub fn derive_sums(&mut self) {
+        let board = &mut self.board;
+        for r in 0..board.rows() {
+            for c in 0..board.cols() {
+                if !board.cell(r, c).is_clue() {
+                    continue;
+                }
+                let across: Vec<Option<u8>> = (c + 1..board.cols())
+                    .map(|c2| board.cell(r, c2))
+                    .take_while(|cell| !cell.is_clue())
+                    .map(|cell| cell.value())
+                    .collect();
+                let down: Vec<Option<u8>> = (r + 1..board.rows())
+                    .map(|r2| board.cell(r2, c))
+                    .take_while(|cell| !cell.is_clue())
+                    .map(|cell| cell.value())
+                    .collect();
+                let operation = board.cell(r, c).operation();
+                let derive = |run: Vec<Option<u8>>, old: Option<u32>| {
+                    if run.is_empty() {
+                        return None;
+                    }
+                    let filled: Option<Vec<u8>> = run.into_iter().collect();
+                    // Products past what can be typed are left out
+                    filled.map_or(old, |v| {
+                        operation
+                            .apply(v)
+                            .filter(|&p| operation.is_sum() || p <= MAX_PRODUCT)
+                    })
+                };
+                let cell = board.cell(r, c);
+                let across = derive(across, cell.across_sum());
+                let down = derive(down, cell.down_sum());
+                let cell = board.cell_mut(r, c);
+                *cell = Cell::new_clue(across, down);
+                cell.set_operation(operation);
+            }
+        }
+    }
+
+    /// Returns the puzzle: the board with every value cleared
+    pub fn puzzle(&self) -> Board {
+        let mut puzzle = self.board.clone();
+        for r in 0..puzzle.rows() {
+            for c in 0..puzzle.cols() {
+                if !puzzle.cell(r, c).is_clue() {
+                    *puzzle.cell_mut(r, c) = Cell::new_play();
+                }
+            }
+        }
+        puzzle
+    }
+
+    /// Returns the typed solution, if every playable cell has a value
+    pub fn solution(&self) -> Option<Board> {
+        let board = &self.board;
+        let full = (0..board.rows())
+            .flat_map(|r| (0..board.cols()).map(move |c| board.cell(r, c)))
+            .all(|cell| cell.is_clue() || cell.value().is_some());
+        full.then(|| board.clone())
+    }
+
+    /// Reports problems with the pattern of playable cells in the status
+    /// line
+    pub fn check_layout(&mut self) {
+        self.status = layout::analyze(&self.board).to_string();
+    }
+
+    /// Checks the puzzle's structure and whether it has exactly one
+    /// solution, reporting the result in the status line
+    pub fn check_unique(&mut self) {
+        self.board.set_marked(Vec::new());
+        let puzzle = self.puzzle();
+        let errors = puzzle.structure_errors();
+        if let Some(first) = errors.first() {
+            self.status = match errors.len() {
+                1 => first.to_string(),
+                n => format!("{} (and {} more problems)", first, n - 1),
+            };
+            return;
+        }
+        let mut solver = Solver::new(&puzzle);
+        solver.set_budget(SOLVER_BUDGET);
+        let count = solver.count(SOLUTION_CAP);
+        let at_least = if count.capped || solver.exhausted() {
+            "at least "
+        } else {
+            ""
+        };
+        self.status = match count.count {
+            2.. => format!(
+                "{}{} solutions, the first two differing in the marked cells",
+                at_least, count.count
+            ),
+            _ if solver.exhausted() => "too hard to decide".to_string(),
+            0 => "no solution".to_string(),
+            _ => "unique solution".to_string(),
+        };
+        self.board.set_marked(count.ambiguous);
+    }
+
+    /// Saves the puzzle, as JSON with the solution if the path ends in
+    /// `.json` and in the text format otherwise
+    pub fn save(&mut self, path: &str) {
+        let text = if path.ends_with(".json") {
+            let mut puzzle = Puzzle::new(self.puzzle());
+            puzzle.solution = self.solution();
+            json::to_json(&puzzle) + "\n"
+        } else {
+            format::to_text(&self.puzzle())
+        };
+        self.status = match fs::write(path, text) {
+            Ok(()) => format!("saved to {}", path),
+            Err(e) => format!("{}: {}", path, e),
+        };
+    }
+
+    /// Draws the board with the editor's help and status lines
+    fn draw(&self, line: &str) -> Result<()> {
+        let direction = match self.direction {
+            Direction::Across => "across",
+            Direction::Down => "down",
+        };
+        let tag = format!(" [{}, symmetry {}]", direction, self.symmetry);
+        let keys = [
+            ("esc", "home"),
+            ("space", "block"),
+            ("tab", "across/down"),
+            ("m", "symmetry"),
+            ("x", "sum/product"),
+            ("l", "layout"),
+            ("u", "unique?"),
+            ("w", "save"),
+        ];
+        let help = render::help_line(&keys, &tag);
+        self.board.draw_footer(&help, line)
+    }
+
+    /// Reads a line of text typed below the board, None if cancelled
+    fn prompt(&self, label: &str) -> Result<Option<String>> {
+        let mut text = String::new();
+        loop {
+            self.draw(&format!("{}{}", label, text))?;
+            if let Event::Key(key) = term::read()? {
+                match key.code {
+                    KeyCode::Enter if !text.is_empty() => return Ok(Some(text)),
+                    KeyCode::Esc => return Ok(None),
+                    KeyCode::Backspace => {
+                        text.pop();
+                    }
+                    KeyCode::Char(c) => text.push(c),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Runs the editor until escape is pressed
+    pub fn run(&mut self) -> Result<()> {
+        let _guard = term::Guard::new()?;
+        render::invalidate();
+        loop {
+            self.draw(&self.status)?;
+            let read = term::read()?;
+            if let Event::Resize(..) = read {
+                render::invalidate();
+            }
+            if let Event::Key(key) = read {
+                let digits = self.board.sum_digits();
+                match key.code {
+                    KeyCode::Right => self.board.right(),
+                    KeyCode::Left => self.board.left(),
+                    KeyCode::Down => self.board.down(),
+                    KeyCode::Up => self.board.up(),
+                    KeyCode::Backspace => self.backspace(),
+                    KeyCode::Tab => self.switch_direction(),
+                    KeyCode::Char(' ') => self.toggle_cell(),
+                    KeyCode::Char(c) if c.is_ascii_digit() => self.digit(c),
+                    KeyCode::Char('m') => self.cycle_symmetry(),
+                    KeyCode::Char('x') => self.toggle_operation(),
+                    KeyCode::Char('l') => self.check_layout(),
+                    KeyCode::Char('u') => self.check_unique(),
+                    KeyCode::Char('w') => {
+                        if let Some(path) = self.prompt("save as: ")? {
+                            self.save(&path);
+                        }
+                    }
+                    KeyCode::Esc => break,
+                    _ => {}
+                }
+                // Cells widen and narrow with the longest sum, moving them all
+                if self.board.sum_digits() != digits {
+                    render::invalidate();
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Asks for the size of a new puzzle, None if cancelled
+pub fn choose_size() -> Result<Option<(usize, usize)>> {
+    let _guard = term::Guard::new()?;
+    let mut stdout = stdout();
+    let (mut rows, mut cols) = (9, 9);
+    loop {
+        execute!(
+            stdout,
+            terminal::Clear(terminal::ClearType::All),
+            MoveTo(0, 0),
+            Print("New puzzle, including the clue row and column:"),
+            MoveTo(0, 2),
+            Print(format!("  {} x {}", rows, cols).attribute(Attribute::Bold)),
+            MoveTo(0, 4),
+            Print("up/down: rows | left/right: columns | return: edit | esc: home"),
+        )?;
+        if let Event::Key(key) = term::read()? {
+            match key.code {
+                KeyCode::Up => rows = (rows + 1).min(MAX_SIZE),
+                KeyCode::Down => rows = (rows - 1).max(MIN_SIZE),
+                KeyCode::Right => cols = (cols + 1).min(MAX_SIZE),
+                KeyCode::Left => cols = (cols - 1).max(MIN_SIZE),
+                KeyCode::Enter => return Ok(Some((rows, cols))),
+                KeyCode::Esc => return Ok(None),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod editor_tests {
+    use super::*;
+
+    /// Moves the cursor to (row, col)
+    fn goto(editor: &mut Editor, row: usize, col: usize) {
+        while editor.board().cursor().0 != row {
+            editor.board_mut().down();
+        }
+        while editor.board().cursor().1 != col {
+            editor.board_mut().right();
+        }
+    }
+
+    #[test]
+    fn test_new_and_toggle() {
+        let mut editor = Editor::new(3, 4);
+        assert!(editor.board().cell(0, 2).is_clue());
+        assert!(!editor.board().cell(1, 1).is_clue());
+        goto(&mut editor, 1, 3);
+        editor.toggle_cell();
+        assert!(editor.board().cell(1, 3).is_clue());
+        editor.toggle_cell();
+        assert!(!editor.board().cell(1, 3).is_clue());
+        editor.cycle_symmetry();
+        assert_eq!(editor.symmetry(), Symmetry::Rotational);
+        editor.toggle_cell();
+        assert!(editor.board().cell(1, 3).is_clue() && editor.board().cell(2, 1).is_clue());
+        editor.check_layout();
+        assert!(
+            editor
+                .status()
+                .starts_with("down run of 1 at row 1, col 1;")
+        );
+    }
+
+    #[test]
+    fn test_typed_sums() {
+        let mut editor = Editor::new(3, 3);
+        goto(&mut editor, 1, 0);
+        editor.digit('1');
+        editor.digit('7');
+        assert_eq!(editor.board().cell(1, 0).across_sum(), Some(17));
+        editor.digit('8');
+        assert_eq!(editor.board().cell(1, 0).across_sum(), Some(8));
+        editor.switch_direction();
+        editor.digit('4');
+        editor.backspace();
+        assert_eq!(editor.board().cell(1, 0).down_sum(), None);
+        editor.backspace();
+        editor.switch_direction();
+        editor.backspace();
+        assert_eq!(editor.board().cell(1, 0).across_sum(), None);
+    }
+
+    #[test]
+    fn test_ambiguous_cells_marked() {
+        let mut editor = Editor::new(3, 3);
+        for (r, c, d) in [(1, 1, '1'), (1, 2, '2'), (2, 1, '2'), (2, 2, '1')] {
+            goto(&mut editor, r, c);
+            editor.digit(d);
+        }
+        editor.check_unique();
+        assert_eq!(
+            editor.status(),
+            "2 solutions, the first two differing in the marked cells"
+        );
+        assert!(editor.board().is_marked(1, 1) && editor.board().is_marked(2, 2));
+        editor.backspace();
+        assert!(!editor.board().is_marked(1, 1));
+    }
+
+    #[test]
+    fn test_derived_sums_and_uniqueness() {
+        let mut editor = Editor::new(3, 3);
+        for (r, c, d) in [(1, 1, '7'), (1, 2, '9'), (2, 1, '9')] {
+            goto(&mut editor, r, c);
+            editor.digit(d);
+        }
+        assert_eq!(editor.board().cell(1, 0).across_sum(), Some(16));
+        assert_eq!(editor.board().cell(2, 0).across_sum(), None);
+        assert!(editor.solution().is_none());
+        goto(&mut editor, 2, 2);
+        editor.digit('8');
+        assert_eq!(editor.board().cell(2, 0).across_sum(), Some(17));
+        assert_eq!(editor.board().cell(0, 2).down_sum(), Some(17));
+        assert!(editor.solution().is_some());
+        assert_eq!(editor.puzzle().cell(1, 1).value(), None);
+        editor.check_unique();
+        assert_eq!(editor.status(), "unique solution");
+        assert!(!editor.board().is_marked(1, 1));
+
+        goto(&mut editor, 0, 1);
+        editor.toggle_cell();
+        editor.check_unique();
+        assert!(editor.status().contains("has no clue"));
+    }
+
+    #[test]
+    fn test_product_clues() {
+        let mut editor = Editor::new(3, 3);
+        for (r, c, d) in [(1, 1, '3'), (1, 2, '4')] {
+            goto(&mut editor, r, c);
+            editor.digit(d);
+        }
+        goto(&mut editor, 1, 0);
+        editor.toggle_operation();
+        assert_eq!(editor.board().cell(1, 0).operation(), Operation::Product);
+        assert_eq!(editor.board().cell(1, 0).across_sum(), Some(12));
+        for d in ['1', '6', '8'] {
+            editor.digit(d);
+        }
+        assert_eq!(editor.board().cell(1, 0).across_sum(), Some(12_168));
+        assert_eq!(editor.board().cell(1, 0).operation(), Operation::Product);
+    }
+
+    #[test]
+    fn test_long_product_runs() {
+        // Ten 9s multiply to 3_486_784_401, more than a clue can be typed up to
+        let mut editor = Editor::new(2, 11);
+        for c in 1..11 {
+            goto(&mut editor, 1, c);
+            editor.digit('9');
+        }
+        goto(&mut editor, 1, 0);
+        assert_eq!(editor.board().cell(1, 0).across_sum(), Some(90));
+        editor.toggle_operation();
+        assert_eq!(editor.board().cell(1, 0).across_sum(), None);
+        editor.digit('7');
+        assert_eq!(editor.board().cell(1, 0).across_sum(), Some(7));
+        // A clue read from a file can still be too long to type onto
+        *editor.board_mut().cur_cell() = Cell::new_clue(Some(3_486_784_401), None);
+        editor.digit('7');
+        assert_eq!(editor.board().cell(1, 0).across_sum(), Some(7));
+    }
+}
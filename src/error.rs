@@ -0,0 +1,85 @@
+//! Crate error type
+
+use std::{fmt, io};
+
+#[derive(Debug)]
+/// Error raised by the library's fallible operations
+pub enum Error {
+    /// Reading input or writing to the terminal failed
+    Io(io::Error),
+    /// A cell coordinate lies outside the board
+    OutOfBounds {
+        /// 0-based row asked for
+        row: usize,
+        /// 0-based column asked for
+        col: usize,
+        /// Rows on the board
+        rows: usize,
+        /// Columns on the board
+        cols: usize,
+    },
+    /// The grid does not have the number of rows the board was given
+    RowCount {
+        /// Rows expected
+        expected: usize,
+        /// Rows in the grid
+        found: usize,
+    },
+    /// A grid row does not have the number of columns the board was given
+    RowLength {
+        /// 0-based row
+        row: usize,
+        /// Cells expected
+        expected: usize,
+        /// Cells in the row
+        found: usize,
+    },
+}
+
+/// Result with the crate error type
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::OutOfBounds {
+                row,
+                col,
+                rows,
+                cols,
+            } => write!(
+                f,
+                "cell at row {}, col {} is outside the {} x {} board",
+                row, col, rows, cols
+            ),
+            Error::RowCount { expected, found } => {
+                write!(f, "expected {} rows, found {}", expected, found)
+            }
+            Error::RowLength {
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "row {}: expected {} cells, found {}",
+                row, expected, found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
@@ -0,0 +1,367 @@
+//! Plain text puzzle format
+//!
+//! One line per board row, one whitespace separated token per cell:
+//!
+//! - `D\A`: clue cell with down sum `D` and across sum `A`, either may be
+//!   left out (`\` alone is a blank block)
+//! - `D*A`: clue cell whose runs multiply to `D` and `A` instead
+//! - `_`: empty playable cell
+//! - `1`-`9`: filled playable cell
+//!
+//! Blank lines are ignored. Variant rules go on `key: value` lines before
+//! the grid; `digits: 1-6` or `digits: 0-9` sets the digit range and
+//! `repeats: yes` lets digits repeat within a run.
+//!
+//! Each `cage: SUM CELLS...` line adds a cage over the cells given as
+//! 0-based `row,col` pairs. `SUM` is a number, `*N` for a product, or `_`
+//! for a cage that only needs distinct digits; the word `repeats` after it
+//! lets digits repeat within the cage.
+//!
+//! ```text
+//! \    12\  3\
+//! \9   _    _
+//! \6   _    _
+//! ```
+
+use crate::{
+    Board, Cell,
+    cage::Cage,
+    rules::{Operation, Rules},
+};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Error raised when a puzzle text cannot be read
+pub struct ParseError {
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based token number within the line
+    pub column: usize,
+    /// What went wrong
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, cell {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Applies a `key: value` line to the rules
+fn parse_setting(key: &str, value: &str, rules: &mut Rules) -> Result<(), String> {
+    match key.trim() {
+        "digits" => {
+            let digits: Rules = value.parse()?;
+            (rules.min, rules.max) = (digits.min, digits.max);
+        }
+        "repeats" => {
+            rules.repeats = match value.trim() {
+                "yes" => true,
+                "no" => false,
+                v => return Err(format!("expected yes or no for repeats, found '{}'", v)),
+            }
+        }
+        key => return Err(format!("unknown setting '{}'", key)),
+    }
+    Ok(())
+}
+
+/// Reads the value of a `cage:` line
+fn parse_cage(value: &str) -> Result<Cage, String> {
+    let mut words = value.split_whitespace();
+    let mut cage = Cage::default();
+    match words.next() {
+        Some("_") => {}
+        Some(sum) => {
+            let (digits, operation) = match sum.strip_prefix('*') {
+                Some(digits) => (digits, Operation::Product),
+                None => (sum, Operation::Sum),
+            };
+            cage.sum = Some(
+                (digits.parse()).map_err(|_| format!("invalid cage {} '{}'", operation, sum))?,
+            );
+            cage.operation = operation;
+        }
+        None => return Err("cage has no sum".to_string()),
+    }
+    for word in words {
+        if word == "repeats" {
+            cage.repeats = true;
+            continue;
+        }
+        let cell = word
+            .split_once(',')
+            .and_then(|(r, c)| Some((r.parse().ok()?, c.parse().ok()?)));
+        cage.cells
+            .push(cell.ok_or_else(|| format!("invalid cage cell '{}'", word))?);
+    }
+    if cage.cells.is_empty() {
+        return Err("cage has no cells".to_string());
+    }
+    Ok(cage)
+}
+
+/// Parses a single cell token, checking digits against the rules
+fn parse_token(token: &str, rules: Rules) -> Result<Cell, String> {
+    let clue = match (token.split_once('\\'), token.split_once('*')) {
+        (Some(parts), _) => Some((parts, Operation::Sum)),
+        (None, Some(parts)) => Some((parts, Operation::Product)),
+        (None, None) => None,
+    };
+    if let Some(((down, across), operation)) = clue {
+        let sum = |s: &str| -> Result<Option<u32>, String> {
+            if s.is_empty() {
+                Ok(None)
+            } else {
+                s.parse()
+                    .map(Some)
+                    .map_err(|_| format!("invalid clue {} '{}'", operation, s))
+            }
+        };
+        let mut cell = Cell::new_clue(sum(across)?, sum(down)?);
+        cell.set_operation(operation);
+        return Ok(cell);
+    }
+    let mut cell = Cell::new_play();
+    match token {
+        "_" => {}
+        t if t.len() == 1 && t.chars().all(|c| c.is_ascii_digit()) => {
+            let d = t.parse().ok().filter(|&d| rules.contains(d));
+            cell.set_value(Some(
+                d.ok_or_else(|| format!("digit {} is outside {}", t, rules))?,
+            ));
+        }
+        t => return Err(format!("unrecognised cell '{}'", t)),
+    }
+    Ok(cell)
+}
+
+/// Reads a board from the text format
+pub fn parse(text: &str) -> Result<Board, ParseError> {
+    let mut grid: Vec<Vec<Cell>> = Vec::new();
+    let mut rules = Rules::default();
+    let mut cages = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if grid.is_empty()
+            && let Some((key, value)) = line.split_once(':')
+        {
+            let error = |message| ParseError {
+                line: i + 1,
+                column: 1,
+                message,
+            };
+            if key.trim() == "cage" {
+                cages.push((i + 1, parse_cage(value).map_err(error)?));
+            } else {
+                parse_setting(key, value, &mut rules).map_err(error)?;
+            }
+            continue;
+        }
+        let mut row = Vec::new();
+        for (j, token) in line.split_whitespace().enumerate() {
+            row.push(parse_token(token, rules).map_err(|message| ParseError {
+                line: i + 1,
+                column: j + 1,
+                message,
+            })?);
+        }
+        if let Some(first) = grid.first()
+            && first.len() != row.len()
+        {
+            return Err(ParseError {
+                line: i + 1,
+                column: row.len().min(first.len()) + 1,
+                message: format!("expected {} cells, found {}", first.len(), row.len()),
+            });
+        }
+        grid.push(row);
+    }
+    if grid.is_empty() {
+        return Err(ParseError {
+            line: 1,
+            column: 1,
+            message: "empty puzzle".to_string(),
+        });
+    }
+    let (rows, cols) = (grid.len(), grid[0].len());
+    for (line, cage) in &cages {
+        if let Some(&(r, c)) = cage.cells.iter().find(|&&(r, c)| r >= rows || c >= cols) {
+            return Err(ParseError {
+                line: *line,
+                column: 1,
+                message: format!("cage cell {},{} is off the {}x{} grid", r, c, rows, cols),
+            });
+        }
+    }
+    let mut board = Board::new(grid, rows, cols);
+    board.set_rules(rules);
+    board.set_cages(cages.into_iter().map(|(_, cage)| cage).collect());
+    Ok(board)
+}
+
+/// Returns the token for a single cell
+fn token(cell: &Cell) -> String {
+    if cell.is_clue() {
+        let sum = |s: Option<u32>| s.map(|v| v.to_string()).unwrap_or_default();
+        let separator = match cell.operation() {
+            Operation::Sum => '\\',
+            Operation::Product => '*',
+        };
+        format!(
+            "{}{}{}",
+            sum(cell.down_sum()),
+            separator,
+            sum(cell.across_sum())
+        )
+    } else {
+        cell.value()
+            .map(|v| v.to_string())
+            .unwrap_or("_".to_string())
+    }
+}
+
+/// Writes a board in the text format, with columns aligned and any
+/// variant rules on header lines
+pub fn to_text(board: &Board) -> String {
+    let tokens: Vec<Vec<String>> = (0..board.rows())
+        .map(|r| (0..board.cols()).map(|c| token(board.cell(r, c))).collect())
+        .collect();
+    let width = tokens.iter().flatten().map(|t| t.len()).max().unwrap_or(0);
+    let mut out = String::new();
+    let rules = board.rules();
+    if !rules.has_standard_digits() {
+        out.push_str(&format!("digits: {}\n", rules));
+    }
+    if rules.repeats {
+        out.push_str("repeats: yes\n");
+    }
+    for cage in board.cages() {
+        let sum = match (cage.sum, cage.operation) {
+            (None, _) => "_".to_string(),
+            (Some(sum), Operation::Sum) => sum.to_string(),
+            (Some(sum), Operation::Product) => format!("*{}", sum),
+        };
+        out.push_str(&format!("cage: {}", sum));
+        if cage.repeats {
+            out.push_str(" repeats");
+        }
+        for (r, c) in &cage.cells {
+            out.push_str(&format!(" {},{}", r, c));
+        }
+        out.push('\n');
+    }
+    for row in tokens {
+        let line: Vec<String> = row
+            .iter()
+            .map(|t| format!("{:<width$}", t, width = width))
+            .collect();
+        out.push_str(line.join(" ").trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod format_tests {
+    use super::*;
+
+    const SAMPLE: &str = "\\ 12\\ 3\\\n\\9 _ _\n\\6 5 _\n";
+
+    #[test]
+    fn test_parse() {
+        let board = parse(SAMPLE).unwrap();
+        assert_eq!((board.rows(), board.cols()), (3, 3));
+        assert!(board.cell(0, 0).is_clue());
+        assert_eq!(board.cell(0, 0).across_sum(), None);
+        assert_eq!(board.cell(0, 1).down_sum(), Some(12));
+        assert_eq!(board.cell(1, 0).across_sum(), Some(9));
+        assert!(!board.cell(1, 1).is_clue());
+        assert_eq!(board.cell(2, 1).value(), Some(5));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let board = parse(SAMPLE).unwrap();
+        let text = to_text(&board);
+        assert_eq!(text, "\\   12\\ 3\\\n\\9  _   _\n\\6  5   _\n");
+        assert_eq!(to_text(&parse(&text).unwrap()), text);
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = parse("\\ 3\\\n\\3 x\n").err().unwrap();
+        assert_eq!((err.line, err.column), (2, 2));
+        let err = parse("\\ 3\\\n\\3\n").err().unwrap();
+        assert_eq!(err.line, 2);
+        assert!(parse("\n\n").is_err());
+        assert!(parse("a\\b").is_err());
+        let err = parse("\\ 3\\\n\\3 0\n").err().unwrap();
+        assert_eq!(err.to_string(), "line 2, cell 2: digit 0 is outside 1-9");
+        let err = parse("size: 3\n\\ 3\\\n").err().unwrap();
+        assert_eq!(err.to_string(), "line 1, cell 1: unknown setting 'size'");
+    }
+
+    #[test]
+    fn test_rules() {
+        let text = "digits: 0-6\n\\   1\\\n\\1 0\n\\  _\n";
+        let board = parse(text).unwrap();
+        assert_eq!(board.rules().to_string(), "0-6");
+        assert_eq!(board.cell(1, 1).value(), Some(0));
+        assert_eq!(to_text(&board), "digits: 0-6\n\\  1\\\n\\1 0\n\\  _\n");
+        assert!(parse("digits: 1-6\n\\ 7\\\n\\7 7\n").is_err());
+        let board = parse("repeats: yes\ndigits: 1-4\n\\   2\\\n\\2 1\n\\  1\n").unwrap();
+        assert!(board.rules().repeats);
+        assert_eq!(board.rules().to_string(), "1-4");
+        assert_eq!(
+            to_text(&board),
+            "digits: 1-4\nrepeats: yes\n\\  2\\\n\\2 1\n\\  1\n"
+        );
+        let err = parse("repeats: maybe\n").err().unwrap();
+        assert_eq!(err.message, "expected yes or no for repeats, found 'maybe'");
+    }
+
+    #[test]
+    fn test_cages() {
+        let text = "cage: 3 0,0 0,1\ncage: *6 repeats 1,0 1,1\ncage: _ 0,0 1,0\n_ _\n_ _\n";
+        let board = parse(text).unwrap();
+        let cages = board.cages();
+        assert_eq!(cages.len(), 3);
+        assert_eq!(
+            (cages[0].sum, cages[0].cells.clone()),
+            (Some(3), vec![(0, 0), (0, 1)])
+        );
+        assert_eq!(cages[1].operation, Operation::Product);
+        assert!(cages[1].repeats);
+        assert_eq!(cages[2].sum, None);
+        assert_eq!(to_text(&board), text);
+        let err = parse("cage: 3 0,0 2,0\n_ _\n").err().unwrap();
+        assert_eq!(
+            (err.line, err.message.as_str()),
+            (1, "cage cell 2,0 is off the 1x2 grid")
+        );
+        let err = parse("cage: 3 0;0\n_\n").err().unwrap();
+        assert_eq!(err.message, "invalid cage cell '0;0'");
+        assert!(parse("cage: x 0,0\n_\n").is_err());
+        assert!(parse("cage: 3\n_\n").is_err());
+    }
+
+    #[test]
+    fn test_products() {
+        let board = parse("\\ 12* 2*\n*3 _ _\n*8 _ _\n").unwrap();
+        assert_eq!(board.cell(0, 1).operation(), Operation::Product);
+        assert_eq!(board.cell(2, 0).across_sum(), Some(8));
+        assert!(board.cell(0, 0).operation().is_sum());
+        assert_eq!(to_text(&board), "\\   12* 2*\n*3  _   _\n*8  _   _\n");
+        let err = parse("\\ x*\n").err().unwrap();
+        assert_eq!(err.message, "invalid clue product 'x'");
+    }
+}
@@ -0,0 +1,229 @@
+//! Random puzzle generation
+//!
+//! A random layout from [`crate::layout`], with runs of at most 5 cells, is
+//! filled with random distinct digits. The fill's sums become the clues.
+//! While the solver finds a second solution, the digits the two solutions
+//! disagree on are redrawn, or one of those cells is blocked along with its
+//! symmetric image, and the clues recomputed.
+
+use crate::{
+    Board, Cell,
+    layout::{Symmetry, full_enough, pattern, repair, segments, transpose},
+    solver::{Solver, differences},
+};
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
+
+/// Longest run a generated layout may have, keeping puzzles quick to prove
+/// unique
+const MAX_RUN: usize = 5;
+/// Randomness added to the digit order when filling
+const JITTER: i32 = 5;
+/// Chance of redrawing disputed digits rather than blocking a cell
+const REFILL_CHANCE: f64 = 0.8;
+/// Attempts made before settling for a non-unique puzzle
+const MAX_ATTEMPTS: usize = 5_000;
+/// Guesses the solver may make on one fill before it is abandoned
+const SOLVER_BUDGET: usize = 2_000;
+
+/// Gives every run a random lean towards low (-1) or high (+1) digits and
+/// returns, per cell, the sum of the leans of its two runs
+fn leans(play: &[Vec<bool>], rng: &mut StdRng) -> Vec<Vec<i32>> {
+    let mut lean = vec![vec![0; play[0].len()]; play.len()];
+    for (r, row) in play.iter().enumerate() {
+        for (start, len) in segments(row) {
+            let l = if rng.gen_bool(0.5) { 1 } else { -1 };
+            (start..start + len).for_each(|c| lean[r][c] += l);
+        }
+    }
+    for (c, column) in transpose(play).iter().enumerate() {
+        for (start, len) in segments(column) {
+            let l = if rng.gen_bool(0.5) { 1 } else { -1 };
+            (start..start + len).for_each(|r| lean[r][c] += l);
+        }
+    }
+    lean
+}
+
+/// Returns whether `d` already appears in the runs through (r, c)
+fn used(play: &[Vec<bool>], digits: &[Vec<u8>], r: usize, c: usize, d: u8) -> bool {
+    let (rows, cols) = (play.len(), play[0].len());
+    let left = (0..c).rev().take_while(|&c2| play[r][c2]);
+    let right = (c + 1..cols).take_while(|&c2| play[r][c2]);
+    let up = (0..r).rev().take_while(|&r2| play[r2][c]);
+    let down = (r + 1..rows).take_while(|&r2| play[r2][c]);
+    left.chain(right).any(|c2| digits[r][c2] == d) || up.chain(down).any(|r2| digits[r2][c] == d)
+}
+
+/// Fills `cells` from index `i` on, backtracking on dead ends. Digits lean
+/// towards the extremes of each run, where sums have few combinations.
+fn place(
+    i: usize,
+    cells: &[(usize, usize)],
+    play: &[Vec<bool>],
+    lean: &[Vec<i32>],
+    digits: &mut Vec<Vec<u8>>,
+    rng: &mut StdRng,
+) -> bool {
+    let Some(&(r, c)) = cells.get(i) else {
+        return true;
+    };
+    let mut order: Vec<u8> = (1..=9).collect();
+    order.sort_by_cached_key(|&d| rng.gen_range(0..JITTER) - lean[r][c] * i32::from(d));
+    for d in order {
+        if !used(play, digits, r, c, d) {
+            digits[r][c] = d;
+            if place(i + 1, cells, play, lean, digits, rng) {
+                return true;
+            }
+        }
+    }
+    digits[r][c] = 0;
+    false
+}
+
+/// Fills a layout with digits so no run repeats one
+fn fill(play: &[Vec<bool>], rng: &mut StdRng) -> Vec<Vec<u8>> {
+    let (rows, cols) = (play.len(), play[0].len());
+    let cells: Vec<(usize, usize)> = (0..rows)
+        .flat_map(|r| (0..cols).map(move |c| (r, c)))
+        .filter(|&(r, c)| play[r][c])
+        .collect();
+    let mut digits = vec![vec![0u8; cols]; rows];
+    let lean = leans(play, rng);
+    place(0, &cells, play, &lean, &mut digits, rng);
+    digits
+}
+
+/// Redraws the digits of some cells, keeping the rest. Returns false, with
+/// the digits unchanged, if no other valid digits exist.
+fn refill(
+    play: &[Vec<bool>],
+    digits: &mut Vec<Vec<u8>>,
+    cells: &[(usize, usize)],
+    rng: &mut StdRng,
+) -> bool {
+    let old = digits.clone();
+    cells.iter().for_each(|&(r, c)| digits[r][c] = 0);
+    let lean = leans(play, rng);
+    if place(0, cells, play, &lean, digits, rng) && *digits != old {
+        return true;
+    }
+    *digits = old;
+    false
+}
+
+/// Builds the puzzle for a filled layout, with sums as clues and no values
+fn puzzle(play: &[Vec<bool>], digits: &[Vec<u8>]) -> Board {
+    let (rows, cols) = (play.len(), play[0].len());
+    let grid = (0..rows)
+        .map(|r| {
+            (0..cols)
+                .map(|c| {
+                    if play[r][c] {
+                        return Cell::new_play();
+                    }
+                    let across: u32 = (c + 1..cols)
+                        .take_while(|&c2| play[r][c2])
+                        .map(|c2| u32::from(digits[r][c2]))
+                        .sum();
+                    let down: u32 = (r + 1..rows)
+                        .take_while(|&r2| play[r2][c])
+                        .map(|r2| u32::from(digits[r2][c]))
+                        .sum();
+                    Cell::new_clue((across > 0).then_some(across), (down > 0).then_some(down))
+                })
+                .collect()
+        })
+        .collect();
+    Board::new(grid, rows, cols)
+}
+
+/// Generates a puzzle of the given size, including the clue row and
+/// column, from a seed. The same seed always gives the same puzzle. Returns
+/// None if the board is smaller than 3 x 3, or too thin to lay out.
+pub fn generate(rows: usize, cols: usize, seed: u64) -> Option<Board> {
+    generate_symmetric(rows, cols, Symmetry::None, seed)
+}
+
+/// Generates a puzzle like [`generate`] whose layout has the given symmetry
+pub fn generate_symmetric(
+    rows: usize,
+    cols: usize,
+    symmetry: Symmetry,
+    seed: u64,
+) -> Option<Board> {
+    if rows < 3 || cols < 3 {
+        return None;
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut play = pattern(rows, cols, symmetry, MAX_RUN, &mut rng)?;
+    let mut digits = fill(&play, &mut rng);
+    let mut last = None;
+    for _ in 0..MAX_ATTEMPTS {
+        let board = puzzle(&play, &digits);
+        let mut solver = Solver::new(&board);
+        solver.set_budget(SOLVER_BUDGET);
+        let solutions = solver.solutions(2);
+        let cells = (0..rows).flat_map(|r| (0..cols).map(move |c| (r, c)));
+        let candidates: Vec<(usize, usize)> = match solutions.as_slice() {
+            // Too hard to prove either way, so shorten some run
+            _ if solver.exhausted() => cells.filter(|&(r, c)| play[r][c]).collect(),
+            [first, second] => differences(first, second),
+            _ => return Some(board),
+        };
+        last = Some(board);
+        // Redraw the cells in doubt, or block one of them; blocking only
+        // shortens runs, so the remaining digits stay a valid fill
+        if !solver.exhausted()
+            && rng.gen_bool(REFILL_CHANCE)
+            && refill(&play, &mut digits, &candidates, &mut rng)
+        {
+            continue;
+        }
+        let &(r, c) = candidates.choose(&mut rng).expect("layout has cells");
+        let (r2, c2) = symmetry.image(rows, cols, r, c);
+        play[r][c] = false;
+        play[r2][c2] = false;
+        repair(&mut play, symmetry, MAX_RUN, &mut rng);
+        if !full_enough(&play) {
+            let Some(fresh) = pattern(rows, cols, symmetry, MAX_RUN, &mut rng) else {
+                return last;
+            };
+            play = fresh;
+            digits = fill(&play, &mut rng);
+        }
+    }
+    last
+}
+
+#[cfg(test)]
+mod generate_tests {
+    use super::*;
+    use crate::format;
+
+    #[test]
+    fn test_generate_is_deterministic() {
+        let a = generate(6, 6, 42).unwrap();
+        let b = generate(6, 6, 42).unwrap();
+        assert_eq!(format::to_text(&a), format::to_text(&b));
+    }
+
+    #[test]
+    fn test_generated_puzzle_is_solvable() {
+        let board = generate(7, 7, 7).unwrap();
+        assert_eq!((board.rows(), board.cols()), (7, 7));
+        let mut solved = crate::solver::solve(&board).unwrap();
+        assert!(solved.check());
+        assert!(generate(2, 5, 1).is_none());
+    }
+
+    #[test]
+    fn test_generate_symmetric() {
+        let board = generate_symmetric(9, 9, Symmetry::Rotational, 3).unwrap();
+        for (r, c) in (1..9).flat_map(|r| (1..9).map(move |c| (r, c))) {
+            let clue = board.cell(r, c).is_clue();
+            assert_eq!(clue, board.cell(9 - r, 9 - c).is_clue());
+        }
+        assert!(crate::solver::solve(&board).is_some());
+    }
+}
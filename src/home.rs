@@ -0,0 +1,496 @@
+//! Home menu
+//!
+//! Every puzzle from every pack is listed on one scrollable menu. The list
+//! can be narrowed by size, difficulty, completion and a search text, and
+//! the selected puzzle is previewed beside it. The Classic levels keep
+//! their digit shortcuts.
+
+use crate::{
+    Result,
+    menu::Menu,
+    pack::{self, Pack, Progress},
+    solver::{self, Difficulty},
+};
+use crossterm::{
+    cursor::MoveTo,
+    event::KeyCode,
+    execute,
+    style::{Print, Stylize},
+    terminal,
+};
+use std::{fmt, io::stdout, path::PathBuf};
+
+/// Entries shown per menu page
+const PAGE_SIZE: usize = 9;
+/// Difficulties the filter cycles through after "any"
+const DIFFICULTIES: [Difficulty; 4] = [
+    Difficulty::Easy,
+    Difficulty::Medium,
+    Difficulty::Hard,
+    Difficulty::Expert,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Which puzzles to list by whether they are solved
+pub enum Completion {
+    /// Every puzzle
+    Any,
+    /// Solved puzzles only
+    Solved,
+    /// Unsolved puzzles only
+    Unsolved,
+}
+
+impl Completion {
+    /// Returns the next setting, wrapping around
+    pub fn next(self) -> Self {
+        match self {
+            Completion::Any => Completion::Solved,
+            Completion::Solved => Completion::Unsolved,
+            Completion::Unsolved => Completion::Any,
+        }
+    }
+}
+
+impl fmt::Display for Completion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Completion::Any => "any",
+            Completion::Solved => "solved",
+            Completion::Unsolved => "unsolved",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Puzzle listed on the home menu
+struct Entry {
+    /// Index of its pack
+    pack: usize,
+    /// Index within the pack
+    level: usize,
+    /// Rated difficulty, or None if the puzzle has no solution
+    difficulty: Option<Difficulty>,
+}
+
+/// What the home loop should do after a key
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Redraw the menu
+    Stay,
+    /// Play the puzzle at this index into the full list
+    Play(usize),
+    /// Open the daily puzzle
+    Daily,
+    /// Browse the puzzle packs
+    Packs,
+    /// Open the editor
+    Editor,
+    /// Leave the program
+    Quit,
+}
+
+/// State of the home menu
+pub struct Home {
+    /// Packs whose puzzles are listed
+    packs: Vec<Pack>,
+    /// Every puzzle, in pack order
+    entries: Vec<Entry>,
+    /// Solved puzzles
+    progress: Progress,
+    /// Where progress is saved
+    path: Option<PathBuf>,
+    /// Only list puzzles of this size
+    size: Option<(usize, usize)>,
+    /// Only list puzzles of this difficulty
+    difficulty: Option<Difficulty>,
+    /// Only list solved or unsolved puzzles
+    completion: Completion,
+    /// Only list puzzles whose label contains this, ignoring case
+    search: String,
+    /// Whether keys are typed into the search
+    searching: bool,
+    /// Indices into `entries` of the listed puzzles
+    shown: Vec<usize>,
+    /// Menu over the listed puzzles
+    menu: Menu,
+    /// Message shown under the menu
+    status: String,
+}
+
+impl Home {
+    /// Lists the puzzles in the given packs, rating each one
+    pub fn new(packs: Vec<Pack>, progress: Progress, path: Option<PathBuf>) -> Self {
+        let mut entries = Vec::new();
+        for (p, pack) in packs.iter().enumerate() {
+            for (l, level) in pack.levels.iter().enumerate() {
+                entries.push(Entry {
+                    pack: p,
+                    level: l,
+                    difficulty: solver::rate(&level.board).map(|r| r.difficulty),
+                });
+            }
+        }
+        let mut home = Self {
+            packs,
+            entries,
+            progress,
+            path,
+            size: None,
+            difficulty: None,
+            completion: Completion::Any,
+            search: String::new(),
+            searching: false,
+            shown: Vec::new(),
+            menu: Menu::new(Vec::new(), PAGE_SIZE),
+            status: String::new(),
+        };
+        home.refresh();
+        home
+    }
+
+    /// Lists the built-in packs and those in the packs directory, with
+    /// progress read from the progress file
+    pub fn load() -> Self {
+        let (packs, errors) = pack::load_all();
+        let path = pack::progress_path();
+        let progress = pack::load_progress(path.as_deref());
+        let mut home = Self::new(packs, progress, path);
+        home.status = errors.into_iter().next().unwrap_or_default();
+        home
+    }
+
+    /// Returns the puzzle an entry refers to
+    fn level(&self, entry: &Entry) -> &pack::Level {
+        &self.packs[entry.pack].levels[entry.level]
+    }
+
+    /// Returns the digit that opens an entry, if it has one: the Classic
+    /// levels are numbered 1 to 9
+    fn shortcut(&self, index: usize) -> Option<char> {
+        let entry = &self.entries[index];
+        (entry.pack == 0 && entry.level < 9)
+            .then(|| char::from_digit(entry.level as u32 + 1, 10))
+            .flatten()
+    }
+
+    /// Returns whether an entry is solved
+    fn is_solved(&self, entry: &Entry) -> bool {
+        let pack = &self.packs[entry.pack];
+        self.progress
+            .is_solved(&pack.name, &pack.levels[entry.level].title)
+    }
+
+    /// Returns whether an entry passes every filter
+    fn matches(&self, entry: &Entry) -> bool {
+        let board = &self.level(entry).board;
+        let label = format!(
+            "{} {}",
+            self.packs[entry.pack].name,
+            self.level(entry).title
+        );
+        self.size.is_none_or(|s| s == (board.rows(), board.cols()))
+            && self.difficulty.is_none_or(|d| entry.difficulty == Some(d))
+            && match self.completion {
+                Completion::Any => true,
+                Completion::Solved => self.is_solved(entry),
+                Completion::Unsolved => !self.is_solved(entry),
+            }
+            && label.to_lowercase().contains(&self.search.to_lowercase())
+    }
+
+    /// Reapplies the filters and relabels the menu, keeping the selected
+    /// puzzle selected if it is still listed
+    fn refresh(&mut self) {
+        let selected = self.selected();
+        self.shown = (0..self.entries.len())
+            .filter(|&i| self.matches(&self.entries[i]))
+            .collect();
+        let labels = self.shown.iter().map(|&i| self.label(i)).collect();
+        self.menu.set_items(labels);
+        if let Some(pos) = selected.and_then(|s| self.shown.iter().position(|&i| i == s)) {
+            self.menu.select(pos);
+        }
+    }
+
+    /// Returns the menu label for an entry: its shortcut, completion mark,
+    /// title, pack and difficulty
+    fn label(&self, index: usize) -> String {
+        let entry = &self.entries[index];
+        let pack = &self.packs[entry.pack];
+        let width = self
+            .entries
+            .iter()
+            .map(|e| self.level(e).title.len())
+            .max()
+            .unwrap_or(0);
+        let key = self
+            .shortcut(index)
+            .map(|c| format!("{})", c))
+            .unwrap_or_default();
+        let mark = if self.is_solved(entry) {
+            "[x]"
+        } else if pack.is_unlocked(entry.level, &self.progress) {
+            "[ ]"
+        } else {
+            "[-]"
+        };
+        let difficulty = entry
+            .difficulty
+            .map(|d| d.to_string())
+            .unwrap_or("unsolvable".to_string());
+        format!(
+            "{:<2} {} {:<width$}  {:<10} {}",
+            key,
+            mark,
+            self.level(entry).title,
+            pack.name,
+            difficulty,
+            width = width
+        )
+    }
+
+    /// Returns the index of the selected puzzle in the full list
+    pub fn selected(&self) -> Option<usize> {
+        self.shown.get(self.menu.selected()).copied()
+    }
+
+    /// Returns the titles of the listed puzzles
+    pub fn shown(&self) -> Vec<&str> {
+        self.shown
+            .iter()
+            .map(|&i| self.level(&self.entries[i]).title.as_str())
+            .collect()
+    }
+
+    /// Returns the line describing the filters
+    pub fn filters(&self) -> String {
+        let size = self
+            .size
+            .map(|(r, c)| format!("{} x {}", r, c))
+            .unwrap_or("any".to_string());
+        let difficulty = self
+            .difficulty
+            .map(|d| d.to_string())
+            .unwrap_or("any".to_string());
+        let cursor = if self.searching { "_" } else { "" };
+        format!(
+            "size: {} | difficulty: {} | completion: {} | search: {}{}",
+            size, difficulty, self.completion, self.search, cursor
+        )
+    }
+
+    /// Shows the next size found among the puzzles, then every size again
+    pub fn cycle_size(&mut self) {
+        let mut sizes: Vec<(usize, usize)> = self
+            .entries
+            .iter()
+            .map(|e| {
+                let board = &self.level(e).board;
+                (board.rows(), board.cols())
+            })
+            .collect();
+        sizes.sort();
+        sizes.dedup();
+        self.size = match self.size {
+            None => sizes.first().copied(),
+            Some(size) => sizes.into_iter().find(|&s| s > size),
+        };
+        self.refresh();
+    }
+
+    /// Shows the next difficulty, then every difficulty again
+    pub fn cycle_difficulty(&mut self) {
+        self.difficulty = match self.difficulty {
+            None => Some(DIFFICULTIES[0]),
+            Some(d) => DIFFICULTIES.into_iter().skip_while(|&x| x != d).nth(1),
+        };
+        self.refresh();
+    }
+
+    /// Shows the next completion setting
+    pub fn cycle_completion(&mut self) {
+        self.completion = self.completion.next();
+        self.refresh();
+    }
+
+    /// Handles a key, returning what the home loop should do. While
+    /// searching, characters are typed into the search text.
+    pub fn key(&mut self, code: KeyCode) -> Action {
+        self.status.clear();
+        if self.searching {
+            match code {
+                KeyCode::Char(c) => self.search.push(c),
+                KeyCode::Backspace => {
+                    self.search.pop();
+                }
+                KeyCode::Esc => {
+                    self.search.clear();
+                    self.searching = false;
+                }
+                KeyCode::Enter => self.searching = false,
+                code => {
+                    self.menu.key(code);
+                }
+            }
+            self.refresh();
+            return Action::Stay;
+        }
+        match code {
+            KeyCode::Enter => {
+                if let Some(index) = self.selected() {
+                    return Action::Play(index);
+                }
+            }
+            KeyCode::Char(c) if c.is_ascii_digit() => {
+                if let Some(index) = (0..self.entries.len()).find(|&i| self.shortcut(i) == Some(c))
+                {
+                    return Action::Play(index);
+                }
+            }
+            KeyCode::Char('/') => self.searching = true,
+            KeyCode::Char('s') => self.cycle_size(),
+            KeyCode::Char('r') => self.cycle_difficulty(),
+            KeyCode::Char('c') => self.cycle_completion(),
+            KeyCode::Char('d') => return Action::Daily,
+            KeyCode::Char('p') => return Action::Packs,
+            KeyCode::Char('e') => return Action::Editor,
+            KeyCode::Char('q') | KeyCode::Esc => return Action::Quit,
+            code => {
+                self.menu.key(code);
+            }
+        }
+        Action::Stay
+    }
+
+    /// Plays a puzzle from the full list if it is unlocked
+    pub fn play(&mut self, index: usize) -> Result<()> {
+        let entry = &self.entries[index];
+        let pack = &self.packs[entry.pack];
+        self.status = pack::play(pack, entry.level, &mut self.progress, self.path.as_deref())?;
+        self.refresh();
+        Ok(())
+    }
+
+    /// Rereads progress, which may have changed in the pack browser
+    pub fn reload(&mut self) {
+        self.progress = pack::load_progress(self.path.as_deref());
+        self.refresh();
+    }
+
+    /// Draws the menu with the selected puzzle's preview beside it. `extra`
+    /// lists the other home screen choices.
+    pub fn draw(&self, extra: &[String]) -> Result<()> {
+        let mut stdout = stdout();
+        execute!(
+            stdout,
+            terminal::Clear(terminal::ClearType::All),
+            MoveTo(0, 0),
+            Print("Welcome to Kakuro".dark_magenta()),
+            MoveTo(0, 2),
+            Print(self.filters()),
+        )?;
+        let mut lines = self.menu.lines();
+        if lines.is_empty() {
+            lines.push("  No puzzles match".to_string());
+        }
+        let width = lines.iter().map(|l| l.len()).max().unwrap_or(0) as u16;
+        for (i, line) in lines.iter().enumerate() {
+            execute!(stdout, MoveTo(0, i as u16 + 4), Print(line))?;
+        }
+        if let Some(index) = self.selected() {
+            let board = &self.level(&self.entries[index]).board;
+            for (i, line) in board.thumbnail().lines().enumerate() {
+                execute!(stdout, MoveTo(width + 4, i as u16 + 4), Print(line))?;
+            }
+        }
+        let mut y = lines.len().max(PAGE_SIZE + 1) as u16 + 5;
+        for line in extra {
+            execute!(stdout, MoveTo(0, y), Print(line))?;
+            y += 1;
+        }
+        let help = "up/down: select | left/right: page | return: play | /: search | \
+                    s: size | r: difficulty | c: completion";
+        execute!(
+            stdout,
+            MoveTo(0, y + 1),
+            Print(help),
+            MoveTo(0, y + 2),
+            Print(&self.status)
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod home_tests {
+    use super::*;
+
+    /// Returns a home menu over the built-in packs with nothing solved
+    fn home() -> Home {
+        Home::new(pack::builtin(), Progress::default(), None)
+    }
+
+    #[test]
+    fn test_filters() {
+        let mut home = home();
+        assert_eq!(home.shown().len(), 15);
+        home.cycle_size();
+        assert_eq!(home.shown(), ["Level 1 (3 x 3)"]);
+        home.cycle_size();
+        assert_eq!(home.shown().len(), 3);
+        assert!(home.filters().starts_with("size: 5 x 5 |"));
+        while home.size.is_some() {
+            home.cycle_size();
+        }
+        home.cycle_difficulty();
+        home.cycle_difficulty();
+        assert_eq!(home.shown(), ["Level 1 (3 x 3)", "Practice 12 (9 x 9)"]);
+        home.cycle_difficulty();
+        home.cycle_difficulty();
+        home.cycle_difficulty();
+        assert_eq!(home.difficulty, None);
+        home.cycle_completion();
+        assert!(home.shown().is_empty());
+        home.progress.record("Practice", "Practice 1 (5 x 5)", 60);
+        home.refresh();
+        assert_eq!(home.shown(), ["Practice 1 (5 x 5)"]);
+        home.cycle_completion();
+        assert_eq!(home.shown().len(), 14);
+    }
+
+    #[test]
+    fn test_keys() {
+        let mut home = home();
+        assert_eq!(home.key(KeyCode::Char('2')), Action::Play(1));
+        assert_eq!(home.key(KeyCode::Char('9')), Action::Stay);
+        assert_eq!(home.key(KeyCode::Char('q')), Action::Quit);
+        home.key(KeyCode::Down);
+        home.key(KeyCode::Down);
+        home.key(KeyCode::Down);
+        assert_eq!(home.key(KeyCode::Enter), Action::Play(3));
+
+        // Searching keeps the selection when it is still listed
+        home.key(KeyCode::Char('/'));
+        for c in "PRACTICE 1".chars() {
+            assert_eq!(home.key(KeyCode::Char(c)), Action::Stay);
+        }
+        assert_eq!(home.shown().len(), 4);
+        assert_eq!(home.selected(), Some(3));
+        assert!(home.filters().ends_with("search: PRACTICE 1_"));
+        home.key(KeyCode::Enter);
+        assert_eq!(home.key(KeyCode::Char('q')), Action::Quit);
+        home.key(KeyCode::Char('/'));
+        assert_eq!(home.key(KeyCode::Esc), Action::Stay);
+        assert_eq!(home.shown().len(), 15);
+        assert_eq!(home.key(KeyCode::Esc), Action::Quit);
+    }
+
+    #[test]
+    fn test_labels() {
+        let home = home();
+        assert!(home.label(0).starts_with("1) [ ] Level 1 (3 x 3)"));
+        assert!(home.label(1).ends_with("Classic    easy"));
+        assert!(home.label(4).starts_with("   [-] Practice 2 (5 x 5)"));
+    }
+}
@@ -0,0 +1,85 @@
+//! Importers for third-party puzzle formats
+//!
+//! Each importer reads its format into a [`Board`] and then runs the board's
+//! structural checks, so an imported puzzle is known to be playable. Parts of
+//! a format this crate cannot represent are reported as
+//! [`ImportError::Unsupported`] rather than silently dropped.
+
+pub mod csv;
+pub mod tatham;
+
+use crate::{Board, StructureError};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Error raised when an imported puzzle cannot be read
+pub enum ImportError {
+    /// Malformed input
+    Syntax {
+        /// 1-based line number
+        line: usize,
+        /// 1-based field or character number within the line
+        column: usize,
+        /// What went wrong
+        message: String,
+    },
+    /// Well formed, but using a feature this crate does not support
+    Unsupported {
+        /// 1-based line number
+        line: usize,
+        /// 1-based field or character number within the line
+        column: usize,
+        /// The unsupported feature
+        feature: String,
+    },
+    /// Read successfully, but the board is not playable
+    Structure(Vec<StructureError>),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Syntax {
+                line,
+                column,
+                message,
+            } => write!(f, "line {}, column {}: {}", line, column, message),
+            ImportError::Unsupported {
+                line,
+                column,
+                feature,
+            } => write!(
+                f,
+                "line {}, column {}: unsupported {}",
+                line, column, feature
+            ),
+            ImportError::Structure(errors) => {
+                let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                f.write_str(&errors.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// Parses an optional clue sum
+fn sum(s: &str) -> Result<Option<u32>, String> {
+    if s.is_empty() {
+        Ok(None)
+    } else {
+        s.parse()
+            .map(Some)
+            .map_err(|_| format!("invalid clue sum '{}'", s))
+    }
+}
+
+/// Returns the board if it passes the structural checks
+fn validate(board: Board) -> Result<Board, ImportError> {
+    let errors = board.structure_errors();
+    if errors.is_empty() {
+        Ok(board)
+    } else {
+        Err(ImportError::Structure(errors))
+    }
+}
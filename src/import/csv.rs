@@ -0,0 +1,124 @@
+//! CSV grids
+//!
+//! One line per board row, one comma separated field per cell. Fields may be
+//! quoted and padded with spaces:
+//!
+//! - `D\A`: clue cell with down sum `D` and across sum `A`, either may be
+//!   left out
+//! - `#`, `X`, `-` or `\`: blank block
+//! - empty, `0`, `.` or `_`: empty playable cell
+//! - `1`-`9`: filled playable cell
+//!
+//! ```text
+//! #,12\,3\
+//! \9,,
+//! \6,,
+//! ```
+
+use super::{ImportError, sum, validate};
+use crate::{Board, Cell};
+
+/// Reads a single field
+fn field(text: &str, line: usize, column: usize) -> Result<Cell, ImportError> {
+    let text = text.trim().trim_matches('"').trim();
+    if let Some((down, across)) = text.split_once('\\') {
+        let sums = sum(down).and_then(|d| Ok((d, sum(across)?)));
+        let (down, across) = sums.map_err(|message| ImportError::Syntax {
+            line,
+            column,
+            message,
+        })?;
+        return Ok(Cell::new_clue(across, down));
+    }
+    let mut cell = Cell::new_play();
+    match text {
+        "#" | "X" | "x" | "-" => return Ok(Cell::new_clue(None, None)),
+        "" | "0" | "." | "_" => {}
+        t if t.len() == 1 && t.chars().all(|c| c.is_ascii_digit()) => {
+            cell.set_value(t.parse().ok());
+        }
+        t => {
+            return Err(ImportError::Unsupported {
+                line,
+                column,
+                feature: format!("cell '{}'", t),
+            });
+        }
+    }
+    Ok(cell)
+}
+
+/// Reads a board from a CSV grid
+pub fn parse(text: &str) -> Result<Board, ImportError> {
+    let mut grid: Vec<Vec<Cell>> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let row = line
+            .split(',')
+            .enumerate()
+            .map(|(j, f)| field(f, i + 1, j + 1))
+            .collect::<Result<Vec<Cell>, ImportError>>()?;
+        if let Some(first) = grid.first()
+            && first.len() != row.len()
+        {
+            return Err(ImportError::Syntax {
+                line: i + 1,
+                column: row.len().min(first.len()) + 1,
+                message: format!("expected {} fields, found {}", first.len(), row.len()),
+            });
+        }
+        grid.push(row);
+    }
+    if grid.is_empty() {
+        return Err(ImportError::Syntax {
+            line: 1,
+            column: 1,
+            message: "empty puzzle".to_string(),
+        });
+    }
+    let (rows, cols) = (grid.len(), grid[0].len());
+    validate(Board::new(grid, rows, cols))
+}
+
+#[cfg(test)]
+mod csv_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let board = parse("#, 12\\ ,\"3\\\"\n\\9,,_\n\\6,5,0\n").unwrap();
+        assert_eq!((board.rows(), board.cols()), (3, 3));
+        assert!(board.cell(0, 0).is_clue());
+        assert_eq!(board.cell(0, 1).down_sum(), Some(12));
+        assert_eq!(board.cell(1, 0).across_sum(), Some(9));
+        assert!(!board.cell(1, 1).is_clue());
+        assert_eq!(board.cell(2, 1).value(), Some(5));
+        assert_eq!(board.cell(2, 2).value(), None);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(
+            parse("#,3\\\n\\3,?\n"),
+            Err(ImportError::Unsupported {
+                line: 2,
+                column: 2,
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("#,a\\\n\\3,_\n"),
+            Err(ImportError::Syntax { line: 1, .. })
+        ));
+        assert!(matches!(
+            parse("#,3\\\n\\3\n"),
+            Err(ImportError::Syntax { line: 2, .. })
+        ));
+        assert!(matches!(
+            parse("#,#,#\n#,_,_\n"),
+            Err(ImportError::Structure(_))
+        ));
+    }
+}
@@ -0,0 +1,164 @@
+//! Tatham-style game descriptions
+//!
+//! A single line in the style of Simon Tatham's puzzle collection: a
+//! `<cols>x<rows>` header, a colon, then the cells in reading order.
+//!
+//! - `a`-`z`: 1-26 empty playable cells
+//! - `A`-`Z`: 1-26 blank blocks
+//! - `D/A`: clue cell with down sum `D` and across sum `A`, either may be
+//!   left out
+//! - `_`: separator between items that would otherwise run together
+//!
+//! ```text
+//! 3x3:A12/_3//9b/6b
+//! ```
+//!
+//! Extra parameters after the size, such as a difficulty letter, are
+//! reported as unsupported.
+
+use super::{ImportError, sum, validate};
+use crate::{Board, Cell};
+
+/// Returns the 1-based column of a byte offset
+fn column(text: &str, offset: usize) -> usize {
+    text[..offset].chars().count() + 1
+}
+
+/// Returns a syntax error on line 1
+fn syntax(column: usize, message: String) -> ImportError {
+    ImportError::Syntax {
+        line: 1,
+        column,
+        message,
+    }
+}
+
+/// Reads the `<cols>x<rows>` header
+fn header(params: &str) -> Result<(usize, usize), ImportError> {
+    let digits = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let cols_end = digits(params);
+    let rest = &params[cols_end..];
+    let Some(rest) = rest.strip_prefix('x').filter(|_| cols_end > 0) else {
+        return Err(syntax(
+            1,
+            format!("invalid size '{}', expected <cols>x<rows>", params),
+        ));
+    };
+    let rows_end = digits(rest);
+    let size = (rest[..rows_end].parse(), params[..cols_end].parse());
+    let (Ok(rows), Ok(cols)) = size else {
+        return Err(syntax(
+            1,
+            format!("invalid size '{}', expected <cols>x<rows>", params),
+        ));
+    };
+    if rows_end < rest.len() {
+        return Err(ImportError::Unsupported {
+            line: 1,
+            column: column(params, cols_end + 1 + rows_end),
+            feature: format!("parameters '{}'", &rest[rows_end..]),
+        });
+    }
+    if rows == 0 || cols == 0 {
+        return Err(syntax(1, "board has no cells".to_string()));
+    }
+    Ok((rows, cols))
+}
+
+/// Reads a board from a game description
+pub fn parse(text: &str) -> Result<Board, ImportError> {
+    let text = text.trim();
+    let Some((params, desc)) = text.split_once(':') else {
+        return Err(syntax(1, "missing ':' after the size".to_string()));
+    };
+    let (rows, cols) = header(params)?;
+    let start = params.len() + 1;
+    let mut cells = Vec::new();
+    let mut chars = desc.char_indices().peekable();
+    while let Some((i, ch)) = chars.next() {
+        let col = column(text, start + i);
+        match ch {
+            '_' => {}
+            'a'..='z' => {
+                let n = usize::from(ch as u8 - b'a') + 1;
+                cells.extend(std::iter::repeat_n(Cell::new_play(), n));
+            }
+            'A'..='Z' => {
+                let n = usize::from(ch as u8 - b'A') + 1;
+                cells.extend(std::iter::repeat_n(Cell::new_clue(None, None), n));
+            }
+            '0'..='9' | '/' => {
+                let mut end = i;
+                while let Some(&(j, c)) = chars.peek()
+                    && (c.is_ascii_digit() || (c == '/' && !desc[i..j].contains('/')))
+                {
+                    end = j;
+                    chars.next();
+                }
+                let item = &desc[i..end + 1];
+                let Some((down, across)) = item.split_once('/') else {
+                    return Err(syntax(col, format!("expected '/' in clue '{}'", item)));
+                };
+                let sums = sum(down).and_then(|d| Ok((d, sum(across)?)));
+                let (down, across) = sums.map_err(|message| syntax(col, message))?;
+                cells.push(Cell::new_clue(across, down));
+            }
+            _ => {
+                return Err(ImportError::Unsupported {
+                    line: 1,
+                    column: col,
+                    feature: format!("character '{}'", ch),
+                });
+            }
+        }
+        if cells.len() > rows * cols {
+            return Err(syntax(
+                col,
+                format!("description is longer than {} cells", rows * cols),
+            ));
+        }
+    }
+    if cells.len() < rows * cols {
+        return Err(syntax(
+            column(text, text.len()),
+            format!("expected {} cells, found {}", rows * cols, cells.len()),
+        ));
+    }
+    let grid = cells.chunks(cols).map(|row| row.to_vec()).collect();
+    validate(Board::new(grid, rows, cols))
+}
+
+#[cfg(test)]
+mod tatham_tests {
+    use super::*;
+    use crate::format;
+
+    #[test]
+    fn test_parse() {
+        let board = parse("3x3:A12/_3//9b_/6b").unwrap();
+        let expected = format::parse("\\ 12\\ 3\\\n\\9 _ _\n\\6 _ _\n").unwrap();
+        assert_eq!(format::to_text(&board), format::to_text(&expected));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(
+            parse("3x3dh:A12/_3//9b/6b"),
+            Err(ImportError::Unsupported { column: 4, .. })
+        ));
+        assert!(matches!(
+            parse("3x3:A12/_3//9b/6b?"),
+            Err(ImportError::Unsupported { column: 18, .. })
+        ));
+        assert!(matches!(
+            parse("3x3:A12/3/9b/6b"),
+            Err(ImportError::Syntax { .. })
+        ));
+        assert!(matches!(parse("4x2:B/3b"), Err(ImportError::Syntax { .. })));
+        assert!(matches!(parse("3x3"), Err(ImportError::Syntax { .. })));
+        assert!(matches!(
+            parse("3x2:A/3/3bA"),
+            Err(ImportError::Structure(_))
+        ));
+    }
+}
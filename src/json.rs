@@ -0,0 +1,464 @@
+//! JSON puzzle and saved game format
+//!
+//! Only clue and playable cells are listed; any cell left out is a blank
+//! block. Playable cells may carry the solution digit and the player's value
+//! and pencil marks, so the same schema holds both puzzles and saved games.
+//! Variant puzzles carry their rules, such as
+//! `"rules": { "min": 1, "max": 6, "repeats": true }`; standard puzzles leave
+//! them out.
+//! Product clues add `"operation": "product"` to their clue.
+//! Cages are listed under `"cages"`, each with its `"cells"` as
+//! `[row, col]` pairs and an optional `"sum"`.
+//! Files using any of these are written as version 2, so that readers
+//! from before them refuse the file rather than play a different puzzle;
+//! standard puzzles are still written as version 1.
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "title": "Tiny",
+//!   "difficulty": "easy",
+//!   "rows": 2,
+//!   "cols": 3,
+//!   "cells": [
+//!     { "row": 0, "col": 0, "clue": { "across": 4 } },
+//!     { "row": 0, "col": 1, "solution": 1, "value": 1 },
+//!     { "row": 0, "col": 2, "solution": 3, "notes": [2, 3] }
+//!   ]
+//! }
+//! ```
+
+use crate::{
+    Board, Cell,
+    cage::Cage,
+    rules::{Operation, Rules},
+    solver::Difficulty,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Schema version written by this crate for puzzles with rules, product
+/// clues or cages
+pub const SCHEMA_VERSION: u32 = 2;
+/// Schema version written for standard puzzles, which every reader accepts
+const BASE_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// Descriptive information about a puzzle
+pub struct Metadata {
+    /// Puzzle title
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Puzzle author
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// Rated difficulty
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub difficulty: Option<Difficulty>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// Sums of a clue cell
+pub struct ClueRecord {
+    /// Across sum
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub across: Option<u32>,
+    /// Down sum
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub down: Option<u32>,
+    /// How the sums combine digits, left out for ordinary sums
+    #[serde(default, skip_serializing_if = "Operation::is_sum")]
+    pub operation: Operation,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// A clue or playable cell
+pub struct CellRecord {
+    /// Row index
+    pub row: usize,
+    /// Column index
+    pub col: usize,
+    /// Sums, present only on clue cells
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clue: Option<ClueRecord>,
+    /// Solution digit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub solution: Option<u8>,
+    /// Player's digit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<u8>,
+    /// Player's pencil marks
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// Top level JSON document
+pub struct PuzzleFile {
+    /// Schema version
+    pub version: u32,
+    /// Title, author and difficulty
+    #[serde(flatten)]
+    pub meta: Metadata,
+    /// Rows in grid
+    pub rows: usize,
+    /// Columns in grid
+    pub cols: usize,
+    /// Variant rules, left out for standard puzzles
+    #[serde(default, skip_serializing_if = "Rules::is_standard")]
+    pub rules: Rules,
+    /// Clue and playable cells
+    pub cells: Vec<CellRecord>,
+    /// Cages laid over the grid
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cages: Vec<Cage>,
+}
+
+#[derive(Clone)]
+/// A board with its metadata and optional solution
+pub struct Puzzle {
+    /// Title, author and difficulty
+    pub meta: Metadata,
+    /// The board, including any player values and notes
+    pub board: Board,
+    /// The solved board, if known
+    pub solution: Option<Board>,
+}
+
+#[derive(Debug)]
+/// Error raised when a JSON puzzle cannot be read
+pub enum JsonError {
+    /// Not valid JSON, or not matching the schema
+    Syntax(serde_json::Error),
+    /// Written by a newer version of the schema
+    Version(u32),
+    /// Well formed, but not a valid board
+    Invalid(String),
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonError::Syntax(e) => write!(f, "{}", e),
+            JsonError::Version(v) => write!(
+                f,
+                "schema version {} is newer than supported version {}",
+                v, SCHEMA_VERSION
+            ),
+            JsonError::Invalid(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+impl From<serde_json::Error> for JsonError {
+    fn from(e: serde_json::Error) -> Self {
+        JsonError::Syntax(e)
+    }
+}
+
+/// Checks a digit read from a file against the rules
+fn digit(d: u8, rules: Rules, row: usize, col: usize) -> Result<u8, JsonError> {
+    if rules.contains(d) {
+        Ok(d)
+    } else {
+        Err(JsonError::Invalid(format!(
+            "digit {} at row {}, col {} is out of range",
+            d, row, col
+        )))
+    }
+}
+
+impl Puzzle {
+    /// Wraps a board with no metadata or solution
+    pub fn new(board: Board) -> Self {
+        Self {
+            meta: Metadata::default(),
+            board,
+            solution: None,
+        }
+    }
+
+    /// Builds a puzzle from its JSON document
+    pub fn from_file(file: PuzzleFile) -> Result<Self, JsonError> {
+        if file.version > SCHEMA_VERSION {
+            return Err(JsonError::Version(file.version));
+        }
+        if file.rows == 0 || file.cols == 0 {
+            return Err(JsonError::Invalid("board has no cells".to_string()));
+        }
+        let rules = Rules {
+            repeats: file.rules.repeats,
+            ..Rules::new(file.rules.min, file.rules.max).map_err(JsonError::Invalid)?
+        };
+        let mut grid = vec![vec![Cell::new_clue(None, None); file.cols]; file.rows];
+        let mut solution = grid.clone();
+        let mut seen = vec![vec![false; file.cols]; file.rows];
+        let mut has_solution = false;
+        for rec in &file.cells {
+            let (r, c) = (rec.row, rec.col);
+            if r >= file.rows || c >= file.cols {
+                return Err(JsonError::Invalid(format!(
+                    "cell at row {}, col {} is outside the board",
+                    r, c
+                )));
+            }
+            if std::mem::replace(&mut seen[r][c], true) {
+                return Err(JsonError::Invalid(format!(
+                    "cell at row {}, col {} is listed twice",
+                    r, c
+                )));
+            }
+            if let Some(clue) = &rec.clue {
+                grid[r][c] = Cell::new_clue(clue.across, clue.down);
+                grid[r][c].set_operation(clue.operation);
+                solution[r][c] = grid[r][c].clone();
+                continue;
+            }
+            let cell = &mut grid[r][c];
+            *cell = Cell::new_play();
+            cell.set_value(rec.value.map(|d| digit(d, rules, r, c)).transpose()?);
+            for &d in &rec.notes {
+                cell.toggle_note(digit(d, rules, r, c)?);
+            }
+            solution[r][c] = Cell::new_play();
+            if let Some(d) = rec.solution {
+                solution[r][c].set_value(Some(digit(d, rules, r, c)?));
+                has_solution = true;
+            }
+        }
+        for (i, cage) in file.cages.iter().enumerate() {
+            if let Some((r, c)) =
+                (cage.cells.iter()).find(|&&(r, c)| r >= file.rows || c >= file.cols)
+            {
+                return Err(JsonError::Invalid(format!(
+                    "cage {} takes in row {}, col {}, which is outside the board",
+                    i, r, c
+                )));
+            }
+        }
+        let mut board = Board::new(grid, file.rows, file.cols);
+        board.set_rules(rules);
+        board.set_cages(file.cages.clone());
+        let solution = has_solution.then(|| {
+            let mut solution = Board::new(solution, file.rows, file.cols);
+            solution.set_rules(rules);
+            solution.set_cages(file.cages);
+            solution
+        });
+        Ok(Self {
+            meta: file.meta,
+            board,
+            solution,
+        })
+    }
+
+    /// Returns the JSON document for the puzzle
+    pub fn to_file(&self) -> PuzzleFile {
+        let board = &self.board;
+        let mut cells = Vec::new();
+        for r in 0..board.rows() {
+            for c in 0..board.cols() {
+                let cell = board.cell(r, c);
+                let mut rec = CellRecord {
+                    row: r,
+                    col: c,
+                    ..CellRecord::default()
+                };
+                if cell.is_clue() {
+                    if cell.across_sum().is_none() && cell.down_sum().is_none() {
+                        continue;
+                    }
+                    rec.clue = Some(ClueRecord {
+                        across: cell.across_sum(),
+                        down: cell.down_sum(),
+                        operation: cell.operation(),
+                    });
+                } else {
+                    rec.value = cell.value();
+                    rec.notes = (0..16).filter(|&d| cell.has_note(d)).collect();
+                    rec.solution = self.solution.as_ref().and_then(|s| s.cell(r, c).value());
+                }
+                cells.push(rec);
+            }
+        }
+        let standard = board.rules().is_standard()
+            && board.cages().is_empty()
+            && cells
+                .iter()
+                .all(|rec| rec.clue.as_ref().is_none_or(|clue| clue.operation.is_sum()));
+        PuzzleFile {
+            version: if standard {
+                BASE_VERSION
+            } else {
+                SCHEMA_VERSION
+            },
+            meta: self.meta.clone(),
+            rows: board.rows(),
+            cols: board.cols(),
+            rules: board.rules(),
+            cells,
+            cages: board.cages().to_vec(),
+        }
+    }
+}
+
+/// Reads a puzzle from JSON
+pub fn from_json(text: &str) -> Result<Puzzle, JsonError> {
+    Puzzle::from_file(serde_json::from_str(text)?)
+}
+
+/// Writes a puzzle as pretty printed JSON
+pub fn to_json(puzzle: &Puzzle) -> String {
+    serde_json::to_string_pretty(&puzzle.to_file()).expect("puzzle serializes")
+}
+
+#[cfg(test)]
+mod json_tests {
+    use super::*;
+    use crate::format;
+
+    #[test]
+    fn test_doc_example() {
+        let text = r#"{
+          "version": 1,
+          "title": "Tiny",
+          "difficulty": "easy",
+          "rows": 2,
+          "cols": 3,
+          "cells": [
+            { "row": 0, "col": 0, "clue": { "across": 4 } },
+            { "row": 0, "col": 1, "solution": 1, "value": 1 },
+            { "row": 0, "col": 2, "solution": 3, "notes": [2, 3] }
+          ]
+        }"#;
+        let puzzle = from_json(text).unwrap();
+        assert_eq!(puzzle.meta.title.as_deref(), Some("Tiny"));
+        assert_eq!(puzzle.meta.difficulty, Some(Difficulty::Easy));
+        let board = &puzzle.board;
+        assert_eq!(board.cell(0, 0).across_sum(), Some(4));
+        assert_eq!(board.cell(0, 1).value(), Some(1));
+        assert!(board.cell(0, 2).has_note(2) && board.cell(0, 2).has_note(3));
+        assert!(board.cell(1, 1).is_clue());
+        let solution = puzzle.solution.as_ref().unwrap();
+        assert_eq!(solution.cell(0, 2).value(), Some(3));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut board = format::parse("\\ 16\\ 17\\\n\\16 7 _\n\\17 _ _\n").unwrap();
+        board.cell_mut(2, 2).toggle_note(8);
+        let mut puzzle = Puzzle::new(board);
+        puzzle.meta.author = Some("Ada".to_string());
+        puzzle.solution = crate::solver::solve(&puzzle.board);
+        let text = to_json(&puzzle);
+        let back = from_json(&text).unwrap();
+        assert_eq!(back.to_file(), puzzle.to_file());
+        assert_eq!(format::to_text(&back.board), format::to_text(&puzzle.board));
+        assert!(!text.contains("\"row\": 0,\n      \"col\": 0"));
+        assert!(!text.contains("rules"));
+    }
+
+    #[test]
+    fn test_cages() {
+        let board = format::parse("\\ 16\\ 17\\\n\\16 _ _\n\\17 _ _\n").unwrap();
+        let mut puzzle = Puzzle::new(board);
+        let mut cage = Cage::new(vec![(1, 1), (1, 2)], Some(6));
+        cage.operation = Operation::Product;
+        puzzle
+            .board
+            .set_cages(vec![cage, Cage::new(vec![(1, 1)], None)]);
+        let text = to_json(&puzzle);
+        assert!(text.contains("\"cages\": ["));
+        let back = from_json(&text).unwrap();
+        assert_eq!(back.board.cages(), puzzle.board.cages());
+        let bad = r#"{"version": 1, "rows": 1, "cols": 1, "cells": [],
+            "cages": [{"cells": [[0, 1]], "sum": 3}]}"#;
+        assert!(matches!(from_json(bad), Err(JsonError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_rules() {
+        let board = format::parse("digits: 1-4\n\\ 4\\ 6\\\n\\3 _ _\n\\7 _ _\n").unwrap();
+        let mut puzzle = Puzzle::new(board);
+        puzzle.solution = crate::solver::solve(&puzzle.board);
+        let text = to_json(&puzzle);
+        assert!(text.contains("\"rules\": {\n    \"min\": 1,\n    \"max\": 4\n  }"));
+        let back = from_json(&text).unwrap();
+        assert_eq!(back.board.rules(), puzzle.board.rules());
+        assert_eq!(back.solution.unwrap().cell(2, 2).value(), Some(4));
+        let bad = r#"{"version": 1, "rows": 1, "cols": 2, "rules": {"max": 4},
+            "cells": [{"row": 0, "col": 1, "value": 5}]}"#;
+        assert!(matches!(from_json(bad), Err(JsonError::Invalid(_))));
+        let text = to_json(
+            &from_json(
+                r#"{"version": 1, "rows": 1, "cols": 1,
+            "rules": {"repeats": true}, "cells": []}"#,
+            )
+            .unwrap(),
+        );
+        assert!(text.contains(
+            "\"rules\": {\n    \"min\": 1,\n    \"max\": 9,\n    \"repeats\": true\n  }"
+        ));
+    }
+
+    #[test]
+    fn test_products() {
+        let board = format::parse("\\ 12* 2*\n*3 _ _\n*8 _ _\n").unwrap();
+        let text = to_json(&Puzzle::new(board));
+        assert!(text.contains("\"down\": 12,\n        \"operation\": \"product\""));
+        let back = from_json(&text).unwrap();
+        assert_eq!(back.board.cell(2, 0).operation(), Operation::Product);
+        assert!(back.board.cell(0, 0).operation().is_sum());
+    }
+
+    #[test]
+    fn test_versions() {
+        // Only puzzles using variant rules are written as the newer version,
+        // which readers that do not know it turn away
+        for text in [
+            "\\ 12* 2*\n*3 _ _\n*8 _ _\n",
+            "digits: 1-6\n\\ 3\\ 4\\\n\\3 _ _\n\\4 _ _\n",
+            "cage: 3 1,1 1,2\n\\ 3\\ 4\\\n\\3 _ _\n\\4 _ _\n",
+        ] {
+            let mut file = Puzzle::new(format::parse(text).unwrap()).to_file();
+            assert_eq!(file.version, SCHEMA_VERSION);
+            assert!(Puzzle::from_file(file.clone()).is_ok());
+            file.version = SCHEMA_VERSION + 1;
+            assert!(matches!(
+                Puzzle::from_file(file),
+                Err(JsonError::Version(v)) if v == SCHEMA_VERSION + 1
+            ));
+        }
+        let plain = format::parse("\\ 3\\ 4\\\n\\3 _ _\n\\4 _ _\n").unwrap();
+        let file = Puzzle::new(plain).to_file();
+        assert_eq!(file.version, BASE_VERSION);
+        assert!(Puzzle::from_file(file).is_ok());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(from_json("{"), Err(JsonError::Syntax(_))));
+        let file = |version: u32, cells: &str| {
+            format!(
+                r#"{{"version": {}, "rows": 1, "cols": 2, "cells": [{}]}}"#,
+                version, cells
+            )
+        };
+        assert!(matches!(
+            from_json(&file(3, "")),
+            Err(JsonError::Version(3))
+        ));
+        for cells in [
+            r#"{"row": 1, "col": 0}"#,
+            r#"{"row": 0, "col": 1, "value": 0}"#,
+            r#"{"row": 0, "col": 1}, {"row": 0, "col": 1}"#,
+        ] {
+            assert!(matches!(
+                from_json(&file(1, cells)),
+                Err(JsonError::Invalid(_))
+            ));
+        }
+    }
+}
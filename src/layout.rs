@@ -0,0 +1,514 @@
+//! Black and white cell patterns
+//!
+//! A layout marks which cells are playable. A valid layout keeps the top row
+//! and left column for clues, has every playable cell in both an across and a
+//! down run of 2-9 cells, and connects all of its playable cells. Layouts may
+//! be symmetric like published puzzles, the cells below the clue row and right
+//! of the clue column mapping onto themselves.
+
+use crate::{Board, Cell, Direction};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use std::{fmt, str::FromStr};
+
+/// Longest run distinct digits can fill
+pub const MAX_RUN: usize = 9;
+/// Share of interior cells that start out playable
+const DENSITY: f64 = 0.75;
+/// Smallest share of interior cells a layout may keep playable
+const MIN_FILL: f64 = 0.4;
+/// Random layouts tried before giving up, which thin boards may need
+const MAX_PATTERNS: usize = 2_000;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Symmetry of a layout's pattern
+pub enum Symmetry {
+    /// No symmetry
+    #[default]
+    None,
+    /// Unchanged by a half turn
+    Rotational,
+    /// Left half mirrors the right half
+    LeftRight,
+    /// Top half mirrors the bottom half
+    TopBottom,
+}
+
+impl Symmetry {
+    /// Every symmetry, in the order the editor cycles through them
+    pub const ALL: [Symmetry; 4] = [
+        Symmetry::None,
+        Symmetry::Rotational,
+        Symmetry::LeftRight,
+        Symmetry::TopBottom,
+    ];
+
+    /// Returns the cell that must match (row, col) on a board of the given
+    /// size. Cells in the clue row and column are their own image.
+    pub fn image(self, rows: usize, cols: usize, row: usize, col: usize) -> (usize, usize) {
+        if row == 0 || col == 0 {
+            return (row, col);
+        }
+        match self {
+            Symmetry::None => (row, col),
+            Symmetry::Rotational => (rows - row, cols - col),
+            Symmetry::LeftRight => (row, cols - col),
+            Symmetry::TopBottom => (rows - row, col),
+        }
+    }
+
+    /// Returns the symmetry after this one in [`Symmetry::ALL`]
+    pub fn next(self) -> Symmetry {
+        let i = Symmetry::ALL.iter().position(|&s| s == self).unwrap_or(0);
+        Symmetry::ALL[(i + 1) % Symmetry::ALL.len()]
+    }
+}
+
+impl fmt::Display for Symmetry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Symmetry::None => "none",
+            Symmetry::Rotational => "rotational",
+            Symmetry::LeftRight => "left-right",
+            Symmetry::TopBottom => "top-bottom",
+        })
+    }
+}
+
+impl FromStr for Symmetry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Symmetry::ALL
+            .into_iter()
+            .find(|sym| sym.to_string() == s)
+            .ok_or(format!(
+                "unknown symmetry '{}', expected none, rotational, left-right or top-bottom",
+                s
+            ))
+    }
+}
+
+/// Returns the starts and lengths of the playable segments in a line
+pub(crate) fn segments(line: &[bool]) -> Vec<(usize, usize)> {
+    let mut found = Vec::new();
+    let mut start = None;
+    for (i, &play) in line.iter().chain([false].iter()).enumerate() {
+        match (play, start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                found.push((s, i - s));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    found
+}
+
+/// Fixes run lengths in every row, returning true if anything changed
+fn repair_rows(play: &mut [Vec<bool>], max_run: usize, rng: &mut StdRng) -> bool {
+    let mut changed = false;
+    for row in play.iter_mut() {
+        for (start, len) in segments(row) {
+            if len == 1 {
+                row[start] = false;
+                changed = true;
+            } else if len > max_run {
+                row[start + rng.gen_range(2..=len - 3)] = false;
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+/// Returns the transpose of a layout
+pub(crate) fn transpose(play: &[Vec<bool>]) -> Vec<Vec<bool>> {
+    (0..play[0].len())
+        .map(|c| play.iter().map(|row| row[c]).collect())
+        .collect()
+}
+
+/// Returns the connected groups of playable cells, in reading order of
+/// their first cell
+fn regions(play: &[Vec<bool>]) -> Vec<Vec<(usize, usize)>> {
+    let (rows, cols) = (play.len(), play[0].len());
+    let mut seen = vec![vec![false; cols]; rows];
+    let mut found = Vec::new();
+    for r in 0..rows {
+        for c in 0..cols {
+            if !play[r][c] || seen[r][c] {
+                continue;
+            }
+            let mut region = Vec::new();
+            let mut stack = vec![(r, c)];
+            seen[r][c] = true;
+            while let Some((r2, c2)) = stack.pop() {
+                region.push((r2, c2));
+                let near = [
+                    (r2.wrapping_sub(1), c2),
+                    (r2 + 1, c2),
+                    (r2, c2.wrapping_sub(1)),
+                    (r2, c2 + 1),
+                ];
+                for (r3, c3) in near {
+                    if r3 < rows && c3 < cols && play[r3][c3] && !seen[r3][c3] {
+                        seen[r3][c3] = true;
+                        stack.push((r3, c3));
+                    }
+                }
+            }
+            region.sort_unstable();
+            found.push(region);
+        }
+    }
+    found
+}
+
+/// Blocks every playable cell outside the largest connected region,
+/// returning true if anything changed
+fn keep_largest_region(play: &mut [Vec<bool>]) -> bool {
+    let mut found = regions(play);
+    let Some(largest) = (0..found.len()).max_by_key(|&i| found[i].len()) else {
+        return false;
+    };
+    found.swap_remove(largest);
+    for &(r, c) in found.iter().flatten() {
+        play[r][c] = false;
+    }
+    !found.is_empty()
+}
+
+/// Blocks every playable cell whose image is blocked, returning true if
+/// anything changed
+fn symmetrize(play: &mut [Vec<bool>], symmetry: Symmetry) -> bool {
+    let (rows, cols) = (play.len(), play[0].len());
+    let mut changed = false;
+    for r in 0..rows {
+        for c in 0..cols {
+            let (r2, c2) = symmetry.image(rows, cols, r, c);
+            if play[r][c] && !play[r2][c2] {
+                play[r][c] = false;
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+/// Repairs a layout until its runs, connectivity and symmetry are valid
+pub(crate) fn repair(
+    play: &mut Vec<Vec<bool>>,
+    symmetry: Symmetry,
+    max_run: usize,
+    rng: &mut StdRng,
+) {
+    loop {
+        let mut changed = repair_rows(play, max_run, rng);
+        let mut columns = transpose(play);
+        changed |= repair_rows(&mut columns, max_run, rng);
+        *play = transpose(&columns);
+        changed |= keep_largest_region(play);
+        changed |= symmetrize(play, symmetry);
+        if !changed {
+            break;
+        }
+    }
+}
+
+/// Returns whether enough of the interior is still playable
+pub(crate) fn full_enough(play: &[Vec<bool>]) -> bool {
+    let interior = (play.len() - 1) * (play[0].len() - 1);
+    let playable = play.iter().flatten().filter(|&&p| p).count();
+    playable > 0 && playable as f64 >= interior as f64 * MIN_FILL
+}
+
+/// Creates a random valid layout, true marking playable cells, or None if
+/// none of the layouts tried kept enough cells playable
+pub(crate) fn pattern(
+    rows: usize,
+    cols: usize,
+    symmetry: Symmetry,
+    max_run: usize,
+    rng: &mut StdRng,
+) -> Option<Vec<Vec<bool>>> {
+    for _ in 0..MAX_PATTERNS {
+        let mut play = vec![vec![false; cols]; rows];
+        for r in 0..rows {
+            for c in 0..cols {
+                // Draw each pair of images once, from its first cell
+                let (r2, c2) = symmetry.image(rows, cols, r, c);
+                if (r2, c2) >= (r, c) {
+                    let p = r > 0 && c > 0 && rng.gen_bool(DENSITY);
+                    play[r][c] = p;
+                    play[r2][c2] = p;
+                }
+            }
+        }
+        repair(&mut play, symmetry, max_run, rng);
+        if full_enough(&play) {
+            return Some(play);
+        }
+    }
+    None
+}
+
+/// Builds a board of empty playable cells and unsummed clue cells
+pub fn to_board(play: &[Vec<bool>]) -> Board {
+    let grid = play
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|&p| {
+                    if p {
+                        Cell::new_play()
+                    } else {
+                        Cell::new_clue(None, None)
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    Board::new(grid, play.len(), play[0].len())
+}
+
+/// Returns the playable cells of a board
+fn playable(board: &Board) -> Vec<Vec<bool>> {
+    (0..board.rows())
+        .map(|r| {
+            (0..board.cols())
+                .map(|c| !board.cell(r, c).is_clue())
+                .collect()
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A stretch of consecutive playable cells in one direction, clued or not
+pub struct Segment {
+    /// Row of the first cell
+    pub row: usize,
+    /// Column of the first cell
+    pub col: usize,
+    /// Direction of the stretch
+    pub direction: Direction,
+    /// Number of cells
+    pub len: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// Problems with a board's pattern of playable cells, found before any sums
+/// are set
+pub struct Analysis {
+    /// Connected groups of playable cells; a valid layout has one
+    pub components: Vec<Vec<(usize, usize)>>,
+    /// Runs of a single cell
+    pub short_runs: Vec<Segment>,
+    /// Runs longer than distinct digits can fill
+    pub long_runs: Vec<Segment>,
+    /// Playable cells not in both an across and a down run
+    pub single_direction: Vec<(usize, usize)>,
+}
+
+impl Analysis {
+    /// Returns whether the layout has no problems
+    pub fn is_valid(&self) -> bool {
+        self.components.len() <= 1
+            && self.short_runs.is_empty()
+            && self.long_runs.is_empty()
+            && self.single_direction.is_empty()
+    }
+}
+
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = |d: &Direction| match d {
+            Direction::Across => "across",
+            Direction::Down => "down",
+        };
+        let mut problems = Vec::new();
+        if self.components.len() > 1 {
+            problems.push(format!(
+                "{} separate groups of playable cells",
+                self.components.len()
+            ));
+        }
+        for run in self.short_runs.iter().chain(&self.long_runs) {
+            problems.push(format!(
+                "{} run of {} at row {}, col {}",
+                name(&run.direction),
+                run.len,
+                run.row,
+                run.col
+            ));
+        }
+        for (r, c) in &self.single_direction {
+            problems.push(format!(
+                "cell at row {}, col {} is not in both directions",
+                r, c
+            ));
+        }
+        if problems.is_empty() {
+            f.write_str("layout ok")
+        } else {
+            f.write_str(&problems.join("; "))
+        }
+    }
+}
+
+/// Reports the connected groups, bad run lengths and single direction
+/// cells of a board's layout
+pub fn analyze(board: &Board) -> Analysis {
+    let play = playable(board);
+    let mut analysis = Analysis {
+        components: regions(&play),
+        ..Analysis::default()
+    };
+    let mut across = vec![vec![0; board.cols()]; board.rows()];
+    for (r, row) in play.iter().enumerate() {
+        for (start, len) in segments(row) {
+            (start..start + len).for_each(|c| across[r][c] = len);
+            let run = Segment {
+                row: r,
+                col: start,
+                direction: Direction::Across,
+                len,
+            };
+            match len {
+                1 => analysis.short_runs.push(run),
+                l if l > MAX_RUN => analysis.long_runs.push(run),
+                _ => {}
+            }
+        }
+    }
+    let mut down = vec![vec![0; board.cols()]; board.rows()];
+    for (c, column) in transpose(&play).iter().enumerate() {
+        for (start, len) in segments(column) {
+            (start..start + len).for_each(|r| down[r][c] = len);
+            let run = Segment {
+                row: start,
+                col: c,
+                direction: Direction::Down,
+                len,
+            };
+            match len {
+                1 => analysis.short_runs.push(run),
+                l if l > MAX_RUN => analysis.long_runs.push(run),
+                _ => {}
+            }
+        }
+    }
+    for r in 0..board.rows() {
+        for c in 0..board.cols() {
+            if play[r][c] && (across[r][c] < 2 || down[r][c] < 2) {
+                analysis.single_direction.push((r, c));
+            }
+        }
+    }
+    analysis
+}
+
+/// Generates a valid layout of the given size, including the clue row and
+/// column, from a seed. Returns None if the board is smaller than 3 x 3, or
+/// too thin for a random layout to fill enough of it.
+pub fn generate(rows: usize, cols: usize, symmetry: Symmetry, seed: u64) -> Option<Board> {
+    if rows < 3 || cols < 3 {
+        return None;
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    pattern(rows, cols, symmetry, MAX_RUN, &mut rng).map(|play| to_board(&play))
+}
+
+/// Turns (row, col) into a blank block or a playable cell, and its image
+/// under `symmetry` to match
+pub fn toggle(board: &mut Board, row: usize, col: usize, symmetry: Symmetry) {
+    let play = board.cell(row, col).is_clue();
+    let (r2, c2) = symmetry.image(board.rows(), board.cols(), row, col);
+    for (r, c) in [(row, col), (r2, c2)] {
+        if board.cell(r, c).is_clue() == play {
+            *board.cell_mut(r, c) = if play {
+                Cell::new_play()
+            } else {
+                Cell::new_clue(None, None)
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod layout_tests {
+    use super::*;
+
+    #[test]
+    fn test_segments() {
+        assert_eq!(
+            segments(&[false, true, true, false, true]),
+            vec![(1, 2), (4, 1)]
+        );
+        assert!(segments(&[false, false]).is_empty());
+    }
+
+    #[test]
+    fn test_symmetric_layouts_are_valid() {
+        for symmetry in Symmetry::ALL {
+            for seed in 0..5 {
+                let board = generate(12, 11, symmetry, seed).unwrap();
+                let play = playable(&board);
+                for (r, c) in (0..12).flat_map(|r| (0..11).map(move |c| (r, c))) {
+                    let (r2, c2) = symmetry.image(12, 11, r, c);
+                    assert_eq!(play[r][c], play[r2][c2], "{} at {},{}", symmetry, r, c);
+                }
+                for line in play.iter().chain(transpose(&play).iter()) {
+                    assert!(
+                        segments(line)
+                            .iter()
+                            .all(|&(_, len)| (2..=9).contains(&len))
+                    );
+                }
+                let mut connected = play.clone();
+                assert!(!keep_largest_region(&mut connected));
+                assert!(board.cell(3, 3).across_sum().is_none());
+            }
+        }
+        assert!(generate(2, 9, Symmetry::None, 0).is_none());
+    }
+
+    #[test]
+    fn test_analyze() {
+        let board = crate::format::parse(
+            "\\ \\ \\ \\ \\ \\ \\ \\ \\ \\ \\ \\\n\\ _ _ _ _ _ _ _ _ _ _ \\\n\\ _ \\ \\ \\ \\ \\ \\ \\ \\ \\ _\n",
+        )
+        .unwrap();
+        let analysis = analyze(&board);
+        assert_eq!(analysis.components.len(), 2);
+        assert_eq!(analysis.components[1], vec![(2, 11)]);
+        assert_eq!(
+            analysis.long_runs,
+            vec![Segment {
+                row: 1,
+                col: 1,
+                direction: Direction::Across,
+                len: 10
+            }]
+        );
+        assert_eq!(analysis.short_runs.len(), 12);
+        assert_eq!(analysis.single_direction.len(), 11);
+        assert!(!analysis.is_valid());
+        assert!(analysis.to_string().starts_with("2 separate groups"));
+        let board = generate(6, 6, Symmetry::None, 1).unwrap();
+        assert_eq!(analyze(&board).to_string(), "layout ok");
+    }
+
+    #[test]
+    fn test_toggle_and_parse() {
+        let mut board = to_board(&vec![vec![false; 5]; 5]);
+        toggle(&mut board, 1, 2, Symmetry::Rotational);
+        assert!(!board.cell(1, 2).is_clue() && !board.cell(4, 3).is_clue());
+        toggle(&mut board, 4, 3, Symmetry::LeftRight);
+        assert!(!board.cell(1, 2).is_clue());
+        assert!(board.cell(4, 3).is_clue() && board.cell(4, 2).is_clue());
+        assert_eq!("top-bottom".parse(), Ok(Symmetry::TopBottom));
+        assert!("diagonal".parse::<Symmetry>().is_err());
+        assert_eq!(Symmetry::TopBottom.next(), Symmetry::None);
+    }
+}
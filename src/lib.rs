@@ -2,8 +2,41 @@
 #![doc = include_str!("../README.md")]
 #![warn(missing_docs)]
 
+/// Width inside a boxed cell, unless the board's sums need more
 const CELL_WIDTH: u16 = 5;
 const CELL_DEPTH: u16 = 3;
+/// Columns per cell in the compact view, the last one left blank, unless
+/// the board's sums need more
+const COMPACT_WIDTH: u16 = 4;
+/// Lines per cell in the compact view
+const COMPACT_DEPTH: u16 = 2;
+
+pub mod cage;
+pub mod check;
+pub mod coop;
+pub mod daily;
+pub mod editor;
+pub mod error;
+pub mod format;
+pub mod generate;
+pub mod home;
+pub mod import;
+pub mod json;
+pub mod layout;
+pub mod menu;
+mod net;
+pub mod pack;
+pub mod race;
+pub mod render;
+pub mod rules;
+pub mod share;
+pub mod solver;
+mod store;
+pub mod term;
+pub mod walkthrough;
+pub mod win;
+
+pub use error::{Error, Result};
 
 #[derive(Clone)]
 /// Puzzle grid cell
@@ -11,15 +44,19 @@ pub struct Cell {
     /// Value of cell
     value: Option<u8>,
     /// Across sum, or None
-    across_sum: Option<u16>,
+    across_sum: Option<u32>,
     /// Down sum, or None
-    down_sum: Option<u16>,
+    down_sum: Option<u32>,
+    /// How the clue's sums combine digits
+    operation: Operation,
     /// Is clue or playable
     is_clue: bool,
     /// Across sum is flagged incorrect
     a_incorrect: bool,
     /// Down sum is flagged incorrect
     d_incorrect: bool,
+    /// Pencil marks, one bit per digit
+    notes: u16,
 }
 
 impl Cell {
@@ -29,30 +66,32 @@ impl Cell {
             value: None,
             across_sum: None,
             down_sum: None,
+            operation: Operation::Sum,
             is_clue: false,
             a_incorrect: false,
             d_incorrect: false,
+            notes: 0,
         }
     }
 
     /// Creates a clue cell
-    pub fn new_clue(across: Option<u16>, down: Option<u16>) -> Self {
+    pub fn new_clue(across: Option<u32>, down: Option<u32>) -> Self {
         Cell {
             value: None,
             across_sum: across,
             down_sum: down,
+            operation: Operation::Sum,
             is_clue: true,
             a_incorrect: false,
             d_incorrect: false,
+            notes: 0,
         }
     }
 
     // Print methods
-    /// Returns strings to be printed from a clue cell
-    pub fn clue_string(&self) -> (String, String) {
-        let left_w: usize = ((CELL_WIDTH - 1) / 2) as usize;
-        let right_w: usize = CELL_WIDTH as usize - 1 - left_w;
-
+    /// Returns the down sum, the operation's symbol and the across sum to be
+    /// printed from a clue cell, each sum padded to `width` characters
+    pub fn clue_string(&self, width: usize) -> (String, char, String) {
         let down = self
             .down_sum()
             .map(|d| d.to_string())
@@ -62,27 +101,70 @@ impl Cell {
             .map(|a| a.to_string())
             .unwrap_or("".to_string());
         if down.is_empty() && across.is_empty() {
-            return (String::new(), String::new());
+            return (String::new(), ' ', String::new());
         }
         (
-            format!("{:>width$}", down, width = left_w),
-            format!("{:<width$}", across, width = right_w),
+            format!("{:>width$}", down),
+            self.operation.symbol(),
+            format!("{:<width$}", across),
+        )
+    }
+
+    /// Returns strings to be printed from a play cell `width` characters
+    /// wide inside its box
+    pub fn play_strings(&self, width: usize) -> (String, String, String) {
+        let val = match self.value() {
+            Some(v) => v.to_string(),
+            None if self.notes != 0 => self.notes_string(),
+            None => "_".to_string(),
+        };
+        (
+            format!("┌{}┐", &"─".repeat(width)),
+            format!("|{:^width$}|", val),
+            format!("└{}┘", &"─".repeat(width)),
         )
     }
 
-    /// Returns strings to be printed from a play cell
-    pub fn play_strings(&self) -> (String, String, String) {
-        let val = self
-            .value()
-            .map(|v| v.to_string())
-            .unwrap_or("_".to_string());
+    /// Returns the pencil marks as a string, shortened to fit the cell
+    pub fn notes_string(&self) -> String {
+        let digits: String = (0..16u8)
+            .filter(|d| self.has_note(*d))
+            .map(|d| d.to_string())
+            .collect();
+        if digits.len() > CELL_WIDTH as usize {
+            format!("{}+", &digits[..CELL_WIDTH as usize - 1])
+        } else {
+            digits
+        }
+    }
+
+    /// Returns the down and across sums of a clue cell padded to `width`
+    /// characters each for the compact view, blank if there is no sum
+    pub fn compact_clue_strings(&self, width: usize) -> (String, String) {
+        let sum = |s: Option<u32>| s.map(|s| s.to_string()).unwrap_or_default();
         (
-            format!("┌{}┐", &"─".repeat(CELL_WIDTH as usize)),
-            format!("|{:^width$}|", val, width = CELL_WIDTH as usize),
-            format!("└{}┘", &"─".repeat(CELL_WIDTH as usize)),
+            format!("{:>width$}", sum(self.down_sum())),
+            format!("{:<width$}", sum(self.across_sum())),
         )
     }
 
+    /// Returns the two lines of a play cell in the compact view, `width`
+    /// characters each: the value and as many pencil marks as fit
+    pub fn compact_play_strings(&self, width: usize) -> (String, String) {
+        let value = self.value().map_or('_', |v| char::from(b'0' + v));
+        let notes = if self.value().is_some() {
+            String::new()
+        } else {
+            self.notes_string()
+        };
+        let notes = if notes.len() > width {
+            format!("{}+", &notes[..width - 1])
+        } else {
+            notes
+        };
+        (format!("{:^width$}", value), format!("{:<width$}", notes))
+    }
+
     // Accessor Methods
     /// Returns cell is_clue
     pub fn is_clue(&self) -> bool {
@@ -95,15 +177,20 @@ impl Cell {
     }
 
     /// Returns cell across_sum
-    pub fn across_sum(&self) -> Option<u16> {
+    pub fn across_sum(&self) -> Option<u32> {
         self.across_sum
     }
 
     /// Returns cell down_sum
-    pub fn down_sum(&self) -> Option<u16> {
+    pub fn down_sum(&self) -> Option<u32> {
         self.down_sum
     }
 
+    /// Returns how the clue combines the digits of its runs
+    pub fn operation(&self) -> Operation {
+        self.operation
+    }
+
     /// Returns cell a_incorrect
     pub fn is_a_incorrect(&self) -> bool {
         self.a_incorrect
@@ -114,6 +201,16 @@ impl Cell {
         self.d_incorrect
     }
 
+    /// Returns cell notes as a bit set, bit `d` marking digit `d`
+    pub fn notes(&self) -> u16 {
+        self.notes
+    }
+
+    /// Returns whether digit `d` is pencilled into the cell
+    pub fn has_note(&self, d: u8) -> bool {
+        d < 16 && self.notes & (1 << d) != 0
+    }
+
     // Modifying Methods
 
     /// Sets cell value
@@ -121,6 +218,11 @@ impl Cell {
         self.value = v;
     }
 
+    /// Sets how the clue combines the digits of its runs
+    pub fn set_operation(&mut self, operation: Operation) {
+        self.operation = operation;
+    }
+
     /// Sets cell a_incorrect
     pub fn set_a_incorrect(&mut self, incorrect: bool) {
         self.a_incorrect = incorrect;
@@ -130,6 +232,18 @@ impl Cell {
     pub fn set_d_incorrect(&mut self, incorrect: bool) {
         self.d_incorrect = incorrect;
     }
+
+    /// Adds or removes pencil mark `d`
+    pub fn toggle_note(&mut self, d: u8) {
+        if d < 16 {
+            self.notes ^= 1 << d;
+        }
+    }
+
+    /// Sets all pencil marks from a bit set
+    pub fn set_notes(&mut self, notes: u16) {
+        self.notes = notes;
+    }
 }
 
 #[cfg(test)]
@@ -175,28 +289,64 @@ mod cell_tests {
         assert!(!cell.is_d_incorrect());
     }
 
+    #[test]
+    fn test_compact_strings() {
+        let clue = Cell::new_clue(Some(23), Some(4));
+        assert_eq!(
+            clue.compact_clue_strings(2),
+            (" 4".to_string(), "23".to_string())
+        );
+        let across = Cell::new_clue(Some(7), None);
+        assert_eq!(
+            across.compact_clue_strings(2),
+            ("  ".to_string(), "7 ".to_string())
+        );
+        let mut play = Cell::new_play();
+        assert_eq!(
+            play.compact_play_strings(3),
+            (" _ ".to_string(), "   ".to_string())
+        );
+        for d in [1, 2, 3, 4] {
+            play.toggle_note(d);
+        }
+        assert_eq!(play.compact_play_strings(3).1, "12+");
+        play.set_value(Some(8));
+        assert_eq!(
+            play.compact_play_strings(3),
+            (" 8 ".to_string(), "   ".to_string())
+        );
+    }
+
     #[test]
     fn test_string_creation() {
         let empty_clue = Cell::new_clue(None, None);
-        assert_eq!(empty_clue.clue_string(), (String::new(), String::new()));
+        assert_eq!(
+            empty_clue.clue_string(2),
+            (String::new(), ' ', String::new())
+        );
         let down_clue = Cell::new_clue(None, Some(20));
         assert_eq!(
-            down_clue.clue_string(),
-            ("20".to_string(), "  ".to_string())
+            down_clue.clue_string(2),
+            ("20".to_string(), '\\', "  ".to_string())
         );
         let across_clue = Cell::new_clue(Some(20), None);
         assert_eq!(
-            across_clue.clue_string(),
-            ("  ".to_string(), 20.to_string())
+            across_clue.clue_string(2),
+            ("  ".to_string(), '\\', 20.to_string())
+        );
+        let mut both_clue = Cell::new_clue(Some(10), Some(20));
+        assert_eq!(
+            both_clue.clue_string(2),
+            ("20".to_string(), '\\', "10".to_string())
         );
-        let both_clue = Cell::new_clue(Some(10), Some(20));
+        both_clue.set_operation(Operation::Product);
         assert_eq!(
-            both_clue.clue_string(),
-            ("20".to_string(), "10".to_string())
+            both_clue.clue_string(2),
+            ("20".to_string(), '×', "10".to_string())
         );
         let mut play_cell = Cell::new_play();
         assert_eq!(
-            play_cell.play_strings(),
+            play_cell.play_strings(5),
             (
                 "┌─────┐".to_string(),
                 "|  _  |".to_string(),
@@ -205,7 +355,7 @@ mod cell_tests {
         );
         play_cell.set_value(Some(5));
         assert_eq!(
-            play_cell.play_strings(),
+            play_cell.play_strings(5),
             (
                 "┌─────┐".to_string(),
                 "|  5  |".to_string(),
@@ -213,21 +363,210 @@ mod cell_tests {
             )
         );
     }
+
+    #[test]
+    fn test_notes() {
+        let mut cell = Cell::new_play();
+        cell.toggle_note(3);
+        cell.toggle_note(7);
+        assert!(cell.has_note(3));
+        assert!(!cell.has_note(4));
+        assert_eq!(cell.play_strings(5).1, "| 37  |");
+        cell.toggle_note(3);
+        assert!(!cell.has_note(3));
+        cell.set_notes(0b11_1111_1110);
+        assert_eq!(cell.notes_string(), "1234+");
+        cell.set_value(Some(9));
+        assert_eq!(cell.play_strings(5).1, "|  9  |");
+    }
 }
 
+use cage::{Cage, Constraint};
+use check::{CheckReport, RunStatus};
 use crossterm::{
     cursor::MoveTo,
-    event::{self, Event, KeyCode},
-    execute, queue,
-    style::{Attribute, Color, ResetColor, SetBackgroundColor, Stylize},
-    terminal::{self},
+    event::{Event, KeyCode, KeyEvent},
+    queue,
+    style::{Color, ResetColor, SetBackgroundColor},
+    terminal,
 };
+use render::{Frame, Region, View};
+use rules::{Operation, Rules};
+use serde::Serialize;
 use std::{
-    collections::HashSet,
-    io::{Stdout, Write, stdout},
+    io::{Write, stdout},
     ops::Not,
+    time::{Duration, Instant},
 };
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// What the digit keys do during play
+pub enum InputMode {
+    /// Digits fill in the cell value
+    Normal,
+    /// Digits toggle pencil marks
+    Notes,
+    /// Digits choose which digit is highlighted across the board
+    Highlight,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// What a key press during play leads to
+pub enum Turn {
+    /// Play goes on
+    Playing,
+    /// A check found every run correct
+    Solved,
+    /// The player left the board
+    Quit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+/// Direction of a run of playable cells
+pub enum Direction {
+    /// Left to right, checked against the clue's across sum
+    Across,
+    /// Top to bottom, checked against the clue's down sum
+    Down,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A clued run of playable cells
+pub struct Run {
+    /// Row of the clue cell
+    pub clue_row: usize,
+    /// Column of the clue cell
+    pub clue_col: usize,
+    /// Direction of the run
+    pub direction: Direction,
+    /// Expected sum, or product for a product clue
+    pub sum: u32,
+    /// How the digits combine to the expected value
+    pub operation: Operation,
+    /// Coordinates of the playable cells, in order
+    pub cells: Vec<(usize, usize)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A structural problem that makes a board unplayable
+pub enum StructureError {
+    /// A run of two or more playable cells has no clue giving its sum
+    MissingClue {
+        /// Row of the first cell of the run
+        row: usize,
+        /// Column of the first cell of the run
+        col: usize,
+        /// Direction of the run
+        direction: Direction,
+    },
+    /// A clue sum has no playable cells after it
+    EmptyRun {
+        /// Row of the clue cell
+        row: usize,
+        /// Column of the clue cell
+        col: usize,
+        /// Direction of the sum
+        direction: Direction,
+    },
+    /// No set of distinct digits allowed by the rules fills the run with its sum
+    ImpossibleSum {
+        /// Row of the clue cell
+        row: usize,
+        /// Column of the clue cell
+        col: usize,
+        /// Direction of the sum
+        direction: Direction,
+        /// Clue sum, or product
+        sum: u32,
+        /// How the digits combine
+        operation: Operation,
+        /// Cells in the run
+        len: usize,
+        /// Whether the digits must be distinct
+        distinct: bool,
+    },
+    /// A cage takes in a cell that is not playable or not on the board
+    CageCell {
+        /// Index of the cage
+        cage: usize,
+        /// Row of the cell
+        row: usize,
+        /// Column of the cell
+        col: usize,
+    },
+    /// No digits allowed by the rules fill a cage
+    ImpossibleCage {
+        /// Index of the cage
+        cage: usize,
+        /// Cells in the cage
+        len: usize,
+    },
+}
+
+impl std::fmt::Display for StructureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = |d: &Direction| match d {
+            Direction::Across => "across",
+            Direction::Down => "down",
+        };
+        match self {
+            StructureError::MissingClue {
+                row,
+                col,
+                direction,
+            } => write!(
+                f,
+                "{} run starting at row {}, col {} has no clue",
+                name(direction),
+                row,
+                col
+            ),
+            StructureError::EmptyRun {
+                row,
+                col,
+                direction,
+            } => write!(
+                f,
+                "{} clue at row {}, col {} has no cells",
+                name(direction),
+                row,
+                col
+            ),
+            StructureError::ImpossibleSum {
+                row,
+                col,
+                direction,
+                sum,
+                operation,
+                len,
+                distinct,
+            } => write!(
+                f,
+                "{} clue at row {}, col {}: no {} {}digits {} to {}",
+                name(direction),
+                row,
+                col,
+                len,
+                if *distinct { "distinct " } else { "" },
+                match operation {
+                    Operation::Sum => "sum",
+                    Operation::Product => "multiply",
+                },
+                sum
+            ),
+            StructureError::CageCell { cage, row, col } => write!(
+                f,
+                "cage {} takes in row {}, col {}, which is not a playable cell",
+                cage, row, col
+            ),
+            StructureError::ImpossibleCage { cage, len } => {
+                write!(f, "cage {}: no {} digits can fill it", cage, len)
+            }
+        }
+    }
+}
+
 #[derive(Clone)]
 /// Playable Kakuro Board
 pub struct Board {
@@ -241,6 +580,24 @@ pub struct Board {
     cursor_row: usize,
     /// Cursor column index
     cursor_col: usize,
+    /// Current input mode
+    mode: InputMode,
+    /// Highlight cells sharing the value under the cursor
+    highlight_same: bool,
+    /// Digit chosen in highlight mode
+    highlight_digit: Option<u8>,
+    /// Cells drawn marked, such as those two solutions disagree on
+    marked: Vec<(usize, usize)>,
+    /// Other players' cursors, as cells drawn in their colours
+    cursors: Vec<(usize, usize, Color)>,
+    /// Report of the last check made with `check`
+    report: CheckReport,
+    /// Number of checks made during play
+    checks: usize,
+    /// Digits the board is played with
+    rules: Rules,
+    /// Cages laid over the grid
+    cages: Vec<Cage>,
 }
 
 impl Board {
@@ -252,181 +609,809 @@ impl Board {
             cols,
             cursor_row: 0,
             cursor_col: 0,
+            mode: InputMode::Normal,
+            highlight_same: true,
+            highlight_digit: None,
+            marked: Vec::new(),
+            cursors: Vec::new(),
+            report: CheckReport::default(),
+            checks: 0,
+            rules: Rules::default(),
+            cages: Vec::new(),
         }
     }
 
+    /// Board constructor that checks the grid has `rows` rows of `cols`
+    /// cells each
+    pub fn try_new(grid: Vec<Vec<Cell>>, rows: usize, cols: usize) -> Result<Self> {
+        if grid.len() != rows {
+            return Err(Error::RowCount {
+                expected: rows,
+                found: grid.len(),
+            });
+        }
+        if let Some((row, line)) = grid.iter().enumerate().find(|(_, l)| l.len() != cols) {
+            return Err(Error::RowLength {
+                row,
+                expected: cols,
+                found: line.len(),
+            });
+        }
+        Ok(Self::new(grid, rows, cols))
+    }
+
     // Accessor Methods
 
-    /// Gets a reference to a cell in the board
+    /// Returns the number of rows
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Returns the number of columns
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// Returns every clued run on the board, across runs before down runs
+    /// of the same clue
+    pub fn runs(&self) -> Vec<Run> {
+        let mut runs = Vec::new();
+        for r in 0..self.rows {
+            for c in 0..self.cols {
+                let cell = self.cell(r, c);
+                if !cell.is_clue() {
+                    continue;
+                }
+                if let Some(sum) = cell.across_sum() {
+                    let cells = (c + 1..self.cols)
+                        .take_while(|&c2| !self.cell(r, c2).is_clue())
+                        .map(|c2| (r, c2))
+                        .collect();
+                    runs.push(Run {
+                        clue_row: r,
+                        clue_col: c,
+                        direction: Direction::Across,
+                        sum,
+                        operation: cell.operation(),
+                        cells,
+                    });
+                }
+                if let Some(sum) = cell.down_sum() {
+                    let cells = (r + 1..self.rows)
+                        .take_while(|&r2| !self.cell(r2, c).is_clue())
+                        .map(|r2| (r2, c))
+                        .collect();
+                    runs.push(Run {
+                        clue_row: r,
+                        clue_col: c,
+                        direction: Direction::Down,
+                        sum,
+                        operation: cell.operation(),
+                        cells,
+                    });
+                }
+            }
+        }
+        runs
+    }
+
+    /// Gets a reference to a cell in the board, panicking if it is outside
     pub fn cell(&self, row: usize, col: usize) -> &Cell {
         &self.grid[row][col]
     }
 
-    /// Gets a mutable reference to a cell in the board
+    /// Gets a mutable reference to a cell in the board, panicking if it is
+    /// outside
     pub fn cell_mut(&mut self, row: usize, col: usize) -> &mut Cell {
         &mut self.grid[row][col]
     }
 
+    /// Gets a reference to a cell, None if it is outside the board
+    pub fn get(&self, row: usize, col: usize) -> Option<&Cell> {
+        self.grid.get(row)?.get(col)
+    }
+
+    /// Gets a mutable reference to a cell, None if it is outside the board
+    pub fn get_mut(&mut self, row: usize, col: usize) -> Option<&mut Cell> {
+        self.grid.get_mut(row)?.get_mut(col)
+    }
+
+    /// Returns the rules the board is played by
+    pub fn rules(&self) -> Rules {
+        self.rules
+    }
+
+    /// Sets the rules the board is played by
+    pub fn set_rules(&mut self, rules: Rules) {
+        self.rules = rules;
+    }
+
+    /// Returns the cages laid over the grid
+    pub fn cages(&self) -> &[Cage] {
+        &self.cages
+    }
+
+    /// Sets the cages laid over the grid
+    pub fn set_cages(&mut self, cages: Vec<Cage>) {
+        self.cages = cages;
+    }
+
+    /// Returns the index of the first cage with a sum holding a cell, the
+    /// one whose outline is drawn around it
+    pub fn cage_of(&self, row: usize, col: usize) -> Option<usize> {
+        self.cages
+            .iter()
+            .position(|cage| cage.sum.is_some() && cage.cells.contains(&(row, col)))
+    }
+
+    /// Returns every run and cage as a constraint, runs first
+    pub fn constraints(&self) -> Vec<Constraint> {
+        let distinct = !self.rules.repeats;
+        let runs = self.runs();
+        let runs = runs.iter().map(|run| Constraint::from_run(run, distinct));
+        let cages = (self.cages.iter().enumerate()).map(|(i, cage)| Constraint::from_cage(i, cage));
+        runs.chain(cages).collect()
+    }
+
+    /// Returns the cursor's row and column
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.cursor_row, self.cursor_col)
+    }
+
+    /// Moves the cursor to a cell
+    pub fn set_cursor(&mut self, row: usize, col: usize) -> Result<()> {
+        if row >= self.rows || col >= self.cols {
+            return Err(Error::OutOfBounds {
+                row,
+                col,
+                rows: self.rows,
+                cols: self.cols,
+            });
+        }
+        (self.cursor_row, self.cursor_col) = (row, col);
+        Ok(())
+    }
+
     /// Returns a reference to the cell the cursor is at
     pub fn cur_cell(&mut self) -> &mut Cell {
         &mut self.grid[self.cursor_row][self.cursor_col]
     }
 
+    /// Returns the current input mode
+    pub fn mode(&self) -> InputMode {
+        self.mode
+    }
+
+    /// Returns whether same-value highlighting is enabled
+    pub fn highlight_same(&self) -> bool {
+        self.highlight_same
+    }
+
+    /// Enables or disables same-value highlighting
+    pub fn set_highlight_same(&mut self, on: bool) {
+        self.highlight_same = on;
+    }
+
+    /// Returns the digit chosen in highlight mode
+    pub fn highlight_digit(&self) -> Option<u8> {
+        self.highlight_digit
+    }
+
+    /// Returns whether a cell should be drawn highlighted
+    pub fn is_highlighted(&self, row: usize, col: usize) -> bool {
+        let cell = self.cell(row, col);
+        if cell.is_clue() {
+            return false;
+        }
+        if let Some(d) = self.highlight_digit {
+            // Placed digit, or still possible according to pencil marks
+            return match cell.value() {
+                Some(v) => v == d,
+                None => cell.has_note(d),
+            };
+        }
+        if self.highlight_same && (row, col) != (self.cursor_row, self.cursor_col) {
+            let cur = self.cell(self.cursor_row, self.cursor_col);
+            if let (false, Some(v)) = (cur.is_clue(), cur.value()) {
+                return cell.value() == Some(v);
+            }
+        }
+        false
+    }
+
+    /// Marks cells to be drawn in a warning colour, replacing any earlier
+    /// marks
+    pub fn set_marked(&mut self, cells: Vec<(usize, usize)>) {
+        self.marked = cells;
+    }
+
+    /// Returns whether a cell is marked
+    pub fn is_marked(&self, row: usize, col: usize) -> bool {
+        self.marked.contains(&(row, col))
+    }
+
+    /// Sets the other players' cursors, replacing any earlier ones
+    pub fn set_cursors(&mut self, cursors: Vec<(usize, usize, Color)>) {
+        self.cursors = cursors;
+    }
+
+    /// Returns the colour of another player's cursor on a cell
+    pub fn cursor_color(&self, row: usize, col: usize) -> Option<Color> {
+        (self.cursors.iter())
+            .find(|&&(r, c, _)| (r, c) == (row, col))
+            .map(|&(_, _, color)| color)
+    }
+
+    /// Returns the report of the last check made with `check`
+    pub fn last_check(&self) -> &CheckReport {
+        &self.report
+    }
+
+    /// Returns the number of checks made during play
+    pub fn checks(&self) -> usize {
+        self.checks
+    }
+
+    /// Checks every run against its clue without changing the board
+    pub fn check_report(&self) -> CheckReport {
+        check::check(self)
+    }
+
+    /// Sets the incorrect flags of every clue from a report
+    pub fn apply_report(&mut self, report: &CheckReport) {
+        for run in &report.runs {
+            let wrong = run.status != RunStatus::Correct;
+            let clue = self.cell_mut(run.row, run.col);
+            match run.direction {
+                Direction::Across => clue.set_a_incorrect(wrong),
+                Direction::Down => clue.set_d_incorrect(wrong),
+            }
+        }
+    }
+
+    /// Returns the structural problems that make the board unplayable,
+    /// such as runs without clues or sums no digits can make. Cells in a
+    /// cage do not need clued runs.
+    pub fn structure_errors(&self) -> Vec<StructureError> {
+        let mut errors = Vec::new();
+        for r in 0..self.rows {
+            for c in 0..self.cols {
+                let caged = self.cages.iter().any(|cage| cage.cells.contains(&(r, c)));
+                if self.cell(r, c).is_clue() || caged {
+                    continue;
+                }
+                let across_start = c == 0 || self.cell(r, c - 1).is_clue();
+                let across_long = c + 1 < self.cols && !self.cell(r, c + 1).is_clue();
+                if across_start
+                    && across_long
+                    && (c == 0 || self.cell(r, c - 1).across_sum().is_none())
+                {
+                    errors.push(StructureError::MissingClue {
+                        row: r,
+                        col: c,
+                        direction: Direction::Across,
+                    });
+                }
+                let down_start = r == 0 || self.cell(r - 1, c).is_clue();
+                let down_long = r + 1 < self.rows && !self.cell(r + 1, c).is_clue();
+                if down_start && down_long && (r == 0 || self.cell(r - 1, c).down_sum().is_none()) {
+                    errors.push(StructureError::MissingClue {
+                        row: r,
+                        col: c,
+                        direction: Direction::Down,
+                    });
+                }
+            }
+        }
+        for run in self.runs() {
+            let (row, col, direction) = (run.clue_row, run.clue_col, run.direction);
+            if run.cells.is_empty() {
+                errors.push(StructureError::EmptyRun {
+                    row,
+                    col,
+                    direction,
+                });
+            } else if !solver::can_fill(self.rules, run.operation, run.cells.len(), run.sum) {
+                errors.push(StructureError::ImpossibleSum {
+                    row,
+                    col,
+                    direction,
+                    sum: run.sum,
+                    operation: run.operation,
+                    len: run.cells.len(),
+                    distinct: !self.rules.repeats,
+                });
+            }
+        }
+        for (i, cage) in self.cages.iter().enumerate() {
+            let outside = cage
+                .cells
+                .iter()
+                .find(|&&(r, c)| r >= self.rows || c >= self.cols || self.cell(r, c).is_clue());
+            if let Some(&(row, col)) = outside {
+                errors.push(StructureError::CageCell { cage: i, row, col });
+                continue;
+            }
+            let mut rules = self.rules;
+            rules.repeats = cage.repeats;
+            let len = cage.cells.len();
+            let fits = match cage.sum {
+                Some(sum) => solver::can_fill(rules, cage.operation, len, sum),
+                None => cage.repeats || len <= rules.max_run(),
+            };
+            if !fits {
+                errors.push(StructureError::ImpossibleCage { cage: i, len });
+            }
+        }
+        errors
+    }
+
     // Drawing Methods
-    /// Draw a clue cell
-    pub fn draw_clue(&self, stdout: &mut Stdout, row: u16, col: u16, cell: &Cell) {
-        let x: u16 = col * (CELL_WIDTH + 2);
+    /// Returns the background colour of a clue sum: that of its run's status
+    /// at the last check, or red if it is flagged incorrect
+    fn clue_color(&self, row: u16, col: u16, direction: Direction, cell: &Cell) -> Option<Color> {
+        let flagged = match direction {
+            Direction::Across => cell.is_a_incorrect(),
+            Direction::Down => cell.is_d_incorrect(),
+        };
+        match self.report.get(row as usize, col as usize, direction) {
+            Some(run) => run.status.color(),
+            None => flagged.then_some(Color::Red),
+        }
+    }
+
+    /// Draw a clue cell `width` characters wide inside
+    pub fn draw_clue(
+        &self,
+        stdout: &mut impl Write,
+        row: u16,
+        col: u16,
+        cell: &Cell,
+        width: u16,
+    ) -> Result<()> {
+        let x: u16 = col * (width + 2);
         let y: u16 = row * (CELL_DEPTH);
 
         for line in 0..CELL_DEPTH {
             // Move to top line
-            queue!(stdout, MoveTo(x, y + line)).unwrap();
+            queue!(stdout, MoveTo(x, y + line))?;
             if line == 1 {
                 // Write content line
-                let (left, right) = cell.clue_string();
+                let (left, symbol, right) = cell.clue_string((width as usize - 1) / 2);
                 if left.is_empty() && right.is_empty() {
                     // No down or across sum
                     queue!(
                         stdout,
-                        crossterm::style::Print(&" ".repeat(CELL_WIDTH as usize + 2))
-                    )
-                    .unwrap();
+                        crossterm::style::Print(&" ".repeat(width as usize + 2))
+                    )?;
                 } else {
-                    queue!(stdout, crossterm::style::Print(" ")).unwrap();
-                    // Print down sum
-                    if cell.is_d_incorrect() {
-                        // If incorrect, set to red
-                        queue!(stdout, SetBackgroundColor(Color::Red)).unwrap();
-                        queue!(stdout, crossterm::style::Print(&left)).unwrap();
-                        queue!(stdout, ResetColor).unwrap();
+                    queue!(stdout, crossterm::style::Print(" "))?;
+                    // Print down sum, coloured by the run's status
+                    if let Some(color) = self.clue_color(row, col, Direction::Down, cell) {
+                        queue!(stdout, SetBackgroundColor(color))?;
+                        queue!(stdout, crossterm::style::Print(&left))?;
+                        queue!(stdout, ResetColor)?;
                     } else {
-                        queue!(stdout, crossterm::style::Print(&left)).unwrap();
+                        queue!(stdout, crossterm::style::Print(&left))?;
                     }
-                    queue!(stdout, crossterm::style::Print('\\')).unwrap();
+                    queue!(stdout, crossterm::style::Print(symbol))?;
 
                     // Print across sum
-                    if cell.is_a_incorrect() {
-                        // If incorrect, set to red
-                        queue!(stdout, SetBackgroundColor(Color::Red)).unwrap();
-                        queue!(stdout, crossterm::style::Print(&right)).unwrap();
-                        queue!(stdout, ResetColor).unwrap();
+                    if let Some(color) = self.clue_color(row, col, Direction::Across, cell) {
+                        queue!(stdout, SetBackgroundColor(color))?;
+                        queue!(stdout, crossterm::style::Print(&right))?;
+                        queue!(stdout, ResetColor)?;
                     } else {
-                        queue!(stdout, crossterm::style::Print(&right)).unwrap();
+                        queue!(stdout, crossterm::style::Print(&right))?;
                     }
 
-                    queue!(stdout, crossterm::style::Print(' ')).unwrap();
+                    queue!(stdout, crossterm::style::Print(' '))?;
                 }
             } else {
                 // Leave other lines blank
-                queue!(
-                    stdout,
-                    crossterm::style::Print(" ".repeat(CELL_WIDTH as usize))
-                )
-                .unwrap();
+                queue!(stdout, crossterm::style::Print(" ".repeat(width as usize)))?;
             }
 
-            queue!(stdout, ResetColor).unwrap(); // Reset color
+            queue!(stdout, ResetColor)?; // Reset color
         }
+        Ok(())
+    }
+
+    /// Returns the three lines of a play cell `width` characters wide
+    /// inside, its box drawn heavy on the sides where it borders another
+    /// cage and the cage's label set into the top of its first cell
+    pub fn play_lines(
+        &self,
+        row: usize,
+        col: usize,
+        cell: &Cell,
+        width: usize,
+    ) -> (String, String, String) {
+        let (top, mid, bottom) = cell.play_strings(width);
+        let Some(i) = self.cage_of(row, col) else {
+            return (top, mid, bottom);
+        };
+        let other = |r: Option<usize>, c: Option<usize>| match (r, c) {
+            (Some(r), Some(c)) if r < self.rows && c < self.cols => self.cage_of(r, c) != Some(i),
+            _ => true,
+        };
+        let up = other(row.checked_sub(1), Some(col));
+        let down = other(Some(row + 1), Some(col));
+        let left = other(Some(row), col.checked_sub(1));
+        let right = other(Some(row), Some(col + 1));
+        // Corners pick the heavy, light or mixed glyph from their two sides
+        let corner = |vertical: bool, horizontal: bool, glyphs: [char; 4]| {
+            glyphs[usize::from(vertical) * 2 + usize::from(horizontal)]
+        };
+        let line = |heavy: bool| if heavy { '━' } else { '─' };
+        let side = |heavy: bool| if heavy { '┃' } else { '|' };
+        let label = self.cage_label(row, col, width).unwrap_or_default();
+        let fill = width - label.chars().count();
+        let top = format!(
+            "{}{}{}{}",
+            corner(left, up, ['┌', '┍', '┎', '┏']),
+            label,
+            line(up).to_string().repeat(fill),
+            corner(right, up, ['┐', '┑', '┒', '┓'])
+        );
+        let inner: String = mid.chars().skip(1).take(width).collect();
+        let mid = format!("{}{}{}", side(left), inner, side(right));
+        let bottom = format!(
+            "{}{}{}",
+            corner(left, down, ['└', '┕', '┖', '┗']),
+            line(down).to_string().repeat(width),
+            corner(right, down, ['┘', '┙', '┚', '┛'])
+        );
+        (top, mid, bottom)
+    }
+
+    /// Returns the label of the cage whose first cell this is, cut to fit
+    /// `width` characters
+    fn cage_label(&self, row: usize, col: usize, width: usize) -> Option<String> {
+        let cage = &self.cages[self.cage_of(row, col)?];
+        let label = cage.label().filter(|_| cage.anchor() == Some((row, col)))?;
+        Some(label.chars().take(width).collect())
     }
 
     /// Draw a play cell
-    pub fn draw_play(&self, stdout: &mut Stdout, row: u16, col: u16, cell: &Cell) {
-        let x: u16 = col * (CELL_WIDTH + 2);
+    pub fn draw_play(
+        &self,
+        stdout: &mut impl Write,
+        row: u16,
+        col: u16,
+        cell: &Cell,
+        width: u16,
+    ) -> Result<()> {
+        let x: u16 = col * (width + 2);
         let y: u16 = row * (CELL_DEPTH);
+        let (r, c) = (row as usize, col as usize);
 
-        queue!(stdout, MoveTo(x, y)).unwrap();
+        queue!(stdout, MoveTo(x, y))?;
         // Print content line
-        let (line1, line2, line3) = cell.play_strings();
-        queue!(
-            stdout,
-            crossterm::style::Print(&line1),
-            crossterm::cursor::MoveTo(x, y + 1)
-        )
-        .unwrap();
+        let (line1, line2, line3) = self.play_lines(r, c, cell, width as usize);
+        // A cage's label is coloured by the cage's status at the last check
+        let label = self.cage_label(r, c, width as usize).and_then(|label| {
+            let i = self.cage_of(r, c)?;
+            let check = self.report.cages.iter().find(|check| check.index == i)?;
+            Some((label, check.status.color()?))
+        });
+        match label {
+            Some((label, color)) => {
+                let mut chars = line1.chars();
+                let corner = chars.next().unwrap_or(' ');
+                let rest: String = chars.skip(label.chars().count()).collect();
+                queue!(
+                    stdout,
+                    crossterm::style::Print(corner),
+                    SetBackgroundColor(color),
+                    crossterm::style::Print(label),
+                    ResetColor,
+                    crossterm::style::Print(rest)
+                )?;
+            }
+            None => queue!(stdout, crossterm::style::Print(&line1))?,
+        }
+        queue!(stdout, crossterm::cursor::MoveTo(x, y + 1))?;
+        if let Some(color) = self.cursor_color(r, c) {
+            queue!(stdout, SetBackgroundColor(color))?;
+        } else if self.is_marked(row as usize, col as usize) {
+            queue!(stdout, SetBackgroundColor(Color::DarkYellow))?;
+        } else if self.is_highlighted(row as usize, col as usize) {
+            queue!(stdout, SetBackgroundColor(Color::DarkCyan))?;
+        }
         queue!(
             stdout,
             crossterm::style::Print(&line2),
+            ResetColor,
             crossterm::cursor::MoveTo(x, y + 2)
-        )
-        .unwrap();
+        )?;
         queue!(
             stdout,
             crossterm::style::Print(&line3),
             crossterm::cursor::MoveTo(x, y + CELL_DEPTH)
-        )
-        .unwrap();
-        //
+        )?;
+        Ok(())
+    }
+
+    /// Draw a clue cell `width` columns wide in the compact view
+    fn draw_compact_clue(
+        &self,
+        out: &mut impl Write,
+        row: u16,
+        col: u16,
+        cell: &Cell,
+        width: u16,
+    ) -> Result<()> {
+        let x = col * width;
+        let y = row * COMPACT_DEPTH;
+        if cell.across_sum().is_none() && cell.down_sum().is_none() {
+            let blank = " ".repeat(width as usize);
+            queue!(
+                out,
+                MoveTo(x, y),
+                crossterm::style::Print(&blank),
+                MoveTo(x, y + 1),
+                crossterm::style::Print(&blank)
+            )?;
+            return Ok(());
+        }
+        let (down, across) = cell.compact_clue_strings(width as usize - 2);
+        let symbol = cell.operation().symbol();
+        // Print the down sum over the across sum, each coloured by its run
+        queue!(out, MoveTo(x, y))?;
+        if let Some(color) = self.clue_color(row, col, Direction::Down, cell) {
+            queue!(out, SetBackgroundColor(color))?;
+        }
+        queue!(
+            out,
+            crossterm::style::Print(&down),
+            ResetColor,
+            crossterm::style::Print(format!("{} ", symbol)),
+            MoveTo(x, y + 1),
+            crossterm::style::Print(format!(" {}", symbol))
+        )?;
+        if let Some(color) = self.clue_color(row, col, Direction::Across, cell) {
+            queue!(out, SetBackgroundColor(color))?;
+        }
+        queue!(out, crossterm::style::Print(&across), ResetColor)?;
+        Ok(())
     }
 
-    /// Draw the Kakuro board to the terminal
-    pub fn draw(&self) {
-        let mut stdout = stdout();
+    /// Draw a play cell `width` columns wide in the compact view
+    fn draw_compact_play(
+        &self,
+        out: &mut impl Write,
+        row: u16,
+        col: u16,
+        cell: &Cell,
+        width: u16,
+    ) -> Result<()> {
+        let x = col * width;
+        let y = row * COMPACT_DEPTH;
+        let (value, notes) = cell.compact_play_strings(width as usize - 1);
+        queue!(out, MoveTo(x, y))?;
+        if let Some(color) = self.cursor_color(row as usize, col as usize) {
+            queue!(out, SetBackgroundColor(color))?;
+        } else if self.is_marked(row as usize, col as usize) {
+            queue!(out, SetBackgroundColor(Color::DarkYellow))?;
+        } else if self.is_highlighted(row as usize, col as usize) {
+            queue!(out, SetBackgroundColor(Color::DarkCyan))?;
+        }
+        queue!(
+            out,
+            crossterm::style::Print(&value),
+            ResetColor,
+            crossterm::style::Print(' '),
+            MoveTo(x, y + 1),
+            crossterm::style::Print(&notes),
+            crossterm::style::Print(' ')
+        )?;
+        Ok(())
+    }
 
-        // Clear terminal
-        execute!(
-            stdout,
-            terminal::Clear(terminal::ClearType::Purge),
-            MoveTo(0, 0)
-        )
-        .unwrap();
+    /// Returns the number of digits in the board's longest sum or product
+    pub(crate) fn sum_digits(&self) -> usize {
+        self.grid
+            .iter()
+            .flatten()
+            .flat_map(|cell| [cell.down_sum(), cell.across_sum()])
+            .flatten()
+            .map(|sum| sum.checked_ilog10().map_or(1, |n| n as usize + 1))
+            .max()
+            .unwrap_or(1)
+    }
+
+    /// Returns the width inside a boxed cell, wider when products need
+    /// more than two digits
+    fn cell_width(&self) -> u16 {
+        CELL_WIDTH.max(self.sum_digits() as u16 * 2 + 1)
+    }
+
+    /// Returns the columns per cell in the compact view, wider when
+    /// products need more than two digits
+    fn compact_width(&self) -> u16 {
+        COMPACT_WIDTH.max(self.sum_digits() as u16 + 2)
+    }
 
+    /// Returns whether the board is drawn compact: always or never if the
+    /// view says so, otherwise when the boxed board does not fit the
+    /// terminal
+    pub fn is_compact(&self) -> bool {
+        match render::view() {
+            View::Boxed => false,
+            View::Compact => true,
+            View::Auto => terminal::size().is_ok_and(|(width, height)| {
+                let boxed = (
+                    self.cols as u16 * (self.cell_width() + 2),
+                    self.rows as u16 * CELL_DEPTH + 3,
+                );
+                width > 0 && height > 0 && (boxed.0 > width || boxed.1 > height)
+            }),
+        }
+    }
+
+    /// Draw the Kakuro board to the terminal, writing only what changed
+    /// since the last frame
+    pub fn draw(&self) -> Result<()> {
+        let compact = self.is_compact();
+        self.draw_to(&mut stdout(), &mut render::frame(), compact, None)
+    }
+
+    /// Draws the board as `draw` does, with `help` in place of the help
+    /// line and a status line under it
+    pub fn draw_footer(&self, help: &str, status: &str) -> Result<()> {
+        let compact = self.is_compact();
+        let footer = Some((help, status));
+        self.draw_to(&mut stdout(), &mut render::frame(), compact, footer)
+    }
+
+    /// Draws the board into a frame, boxed or compact, with an optional
+    /// help and status line replacing the game's own
+    fn draw_to(
+        &self,
+        out: &mut impl Write,
+        frame: &mut Frame,
+        compact: bool,
+        footer: Option<(&str, &str)>,
+    ) -> Result<()> {
+        let width = if compact {
+            self.compact_width()
+        } else {
+            self.cell_width()
+        };
         // Draw each cell
         for r in 0..self.rows {
             for c in 0..self.cols {
                 let cell = self.cell(r, c);
-                if cell.is_clue() {
-                    self.draw_clue(
-                        &mut stdout,
-                        r.try_into().unwrap(),
-                        c.try_into().unwrap(),
-                        cell,
-                    );
-                } else {
-                    self.draw_play(
-                        &mut stdout,
-                        r.try_into().unwrap(),
-                        c.try_into().unwrap(),
-                        cell,
-                    );
+                let (row, col) = (r as u16, c as u16);
+                let mut bytes = Vec::new();
+                match (cell.is_clue(), compact) {
+                    (true, false) => self.draw_clue(&mut bytes, row, col, cell, width)?,
+                    (false, false) => self.draw_play(&mut bytes, row, col, cell, width)?,
+                    (true, true) => self.draw_compact_clue(&mut bytes, row, col, cell, width)?,
+                    (false, true) => self.draw_compact_play(&mut bytes, row, col, cell, width)?,
                 }
+                frame.update(out, Region::Cell(r, c), bytes)?;
             }
         }
 
         // Print help text one line below the grid
-        let help_y = (self.rows as u16) * CELL_DEPTH + 1;
-        queue!(stdout, MoveTo(0, help_y)).unwrap();
-        queue!(
-            stdout,
-            crossterm::style::Print(format!(
-                "{}{}{}{}",
-                "esc".attribute(Attribute::Bold),
-                ": home | ",
-                "return".attribute(Attribute::Bold),
-                ": check"
-            ))
-        )
-        .unwrap();
-
-        stdout.flush().unwrap();
+        let depth = if compact { COMPACT_DEPTH } else { CELL_DEPTH };
+        let help_y = (self.rows as u16) * depth + 1;
+        match footer {
+            Some((help, status)) => {
+                frame.line(out, help_y, help)?;
+                frame.line(out, help_y + 1, status)?;
+            }
+            None => {
+                frame.line(out, help_y, &self.help())?;
+                if !self.report.is_empty() {
+                    let check = format!("check: {}", self.report.summary());
+                    frame.line(out, help_y + 1, &check)?;
+                }
+            }
+        }
 
-        // Set playable cells to blinking
-        if self.grid[self.cursor_row][self.cursor_col].is_clue() {
-            execute!(stdout, crossterm::cursor::DisableBlinking).unwrap();
+        // Playable cells blink, and the cursor sits on the selected cell
+        let blinking = !self.grid[self.cursor_row][self.cursor_col].is_clue();
+        let cursor = if compact {
+            (
+                self.cursor_col as u16 * width + (width - 1) / 2,
+                self.cursor_row as u16 * COMPACT_DEPTH,
+            )
         } else {
-            execute!(stdout, crossterm::cursor::EnableBlinking).unwrap();
+            (
+                self.cursor_col as u16 * (width + 2) + 1 + width / 2,
+                self.cursor_row as u16 * CELL_DEPTH + 1,
+            )
+        };
+        frame.finish(out, cursor, blinking)?;
+        Ok(())
+    }
+
+    /// Returns the input mode as shown after the help line, such as
+    /// " [notes]", or nothing in normal input
+    pub fn mode_tag(&self) -> String {
+        match self.mode {
+            InputMode::Normal => String::new(),
+            InputMode::Notes => " [notes]".to_string(),
+            InputMode::Highlight => match self.highlight_digit {
+                Some(d) => format!(" [highlight {}]", d),
+                None => " [highlight]".to_string(),
+            },
         }
+    }
 
-        // Move cursor to the cell
-        execute!(
-            stdout,
-            MoveTo(
-                self.cursor_col as u16 * (CELL_WIDTH + 2) + 3,
-                self.cursor_row as u16 * CELL_DEPTH + 1
-            )
-        )
-        .unwrap();
+    /// Returns the help line shown during play, with the input mode
+    fn help(&self) -> String {
+        let view = format!("view ({})", render::view());
+        let keys = [
+            ("esc", "home"),
+            ("return", "check"),
+            ("n", "notes"),
+            ("h", "highlight"),
+            ("s", "same digits"),
+            ("w", "watch solver"),
+            ("c", "share"),
+            ("v", view.as_str()),
+        ];
+        render::help_line(&keys, &self.mode_tag())
+    }
 
-        terminal::enable_raw_mode().unwrap();
+    /// Renders the board as plain text in the same layout as `draw`
+    pub fn render(&self) -> String {
+        let width = self.cell_width() as usize;
+        let mut out = String::new();
+        for r in 0..self.rows {
+            let mut lines = [String::new(), String::new(), String::new()];
+            for c in 0..self.cols {
+                let cell = self.cell(r, c);
+                if cell.is_clue() {
+                    let (left, symbol, right) = cell.clue_string((width - 1) / 2);
+                    let blank = " ".repeat(width + 2);
+                    lines[0].push_str(&blank);
+                    if left.is_empty() && right.is_empty() {
+                        lines[1].push_str(&blank);
+                    } else {
+                        lines[1].push_str(&format!(" {}{}{} ", left, symbol, right));
+                    }
+                    lines[2].push_str(&blank);
+                } else {
+                    let (top, mid, bottom) = self.play_lines(r, c, cell, width);
+                    lines[0].push_str(&top);
+                    lines[1].push_str(&mid);
+                    lines[2].push_str(&bottom);
+                }
+            }
+            for line in lines {
+                out.push_str(line.trim_end());
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    /// Returns a small preview of the board, one character per cell: `\`
+    /// for a clue, `#` for a blank block and `.` or the digit for a playable
+    /// cell
+    pub fn thumbnail(&self) -> String {
+        let mut out = String::new();
+        for row in &self.grid {
+            let line: Vec<String> = row
+                .iter()
+                .map(|cell| match (cell.is_clue(), cell.value()) {
+                    (true, _) if cell.across_sum().is_some() || cell.down_sum().is_some() => {
+                        "\\".to_string()
+                    }
+                    (true, _) => "#".to_string(),
+                    (false, Some(v)) => v.to_string(),
+                    (false, None) => ".".to_string(),
+                })
+                .collect();
+            out.push_str(&line.join(" "));
+            out.push('\n');
+        }
+        out
     }
 
     // Play Methods
@@ -476,127 +1461,125 @@ impl Board {
 
     /// Adds a value to the current cell
     pub fn number(&mut self, c: char) {
-        if !self.cur_cell().is_clue() {
-            self.cur_cell()
-                .set_value(Some(c.to_digit(10).unwrap() as u8));
+        if let Some(d) = self.allowed(c)
+            && !self.cur_cell().is_clue()
+        {
+            self.cur_cell().set_value(Some(d));
         }
     }
 
-    /// Begin game play and control input
-    pub fn run(&mut self) {
-        terminal::enable_raw_mode().unwrap();
-        let mut stdout: std::io::Stdout = stdout();
-        execute!(stdout, terminal::Clear(terminal::ClearType::All)).unwrap();
-        let y = self.rows * 3 + 2;
+    /// Toggles a pencil mark in the current cell
+    pub fn note(&mut self, c: char) {
+        if let Some(d) = self.allowed(c)
+            && !self.cur_cell().is_clue()
+        {
+            self.cur_cell().toggle_note(d);
+        }
+    }
 
-        let mut win = false;
-        loop {
-            if win {
-                execute!(stdout, MoveTo(0, y.try_into().unwrap())).unwrap();
-                print!("You win!");
-            }
-            self.draw();
+    /// Returns the digit a key stands for if the rules allow it
+    fn allowed(&self, c: char) -> Option<u8> {
+        let d = c.to_digit(10)? as u8;
+        self.rules.contains(d).then_some(d)
+    }
 
-            // Handle user input
-            if let Event::Key(even) = event::read().unwrap() {
-                match even.code {
-                    KeyCode::Right => {
-                        self.right();
-                    }
-                    KeyCode::Left => {
-                        self.left();
-                    }
-                    KeyCode::Down => {
-                        self.down();
-                    }
-                    KeyCode::Up => {
-                        self.up();
-                    }
-                    KeyCode::Backspace => {
-                        self.backspace();
-                    }
-                    KeyCode::Enter => {
-                        win = self.check();
-                    }
-                    KeyCode::Char(c) if c.is_ascii_digit() && c != '0' => {
-                        self.number(c);
-                    }
-                    KeyCode::Esc => break,
-                    _ => {}
+    /// Switches to `mode`, or back to normal input if already in it
+    pub fn toggle_mode(&mut self, mode: InputMode) {
+        self.mode = if self.mode == mode {
+            InputMode::Normal
+        } else {
+            mode
+        };
+        if self.mode != InputMode::Highlight {
+            self.highlight_digit = None;
+        }
+    }
+
+    /// Handles a digit key according to the input mode
+    pub fn digit(&mut self, c: char) {
+        match self.mode {
+            InputMode::Normal => self.number(c),
+            InputMode::Notes => self.note(c),
+            InputMode::Highlight => {
+                let Some(d) = self.allowed(c) else {
+                    return;
                 };
-            };
+                self.highlight_digit = if self.highlight_digit == Some(d) {
+                    None
+                } else {
+                    Some(d)
+                };
+            }
         }
-        terminal::disable_raw_mode().unwrap();
-        execute!(stdout, terminal::Clear(terminal::ClearType::All)).unwrap();
     }
 
-    /// Check Kakuro board
-    pub fn check(&mut self) -> bool {
-        let mut correct: bool = true;
-        for r in 0..self.rows {
-            for c in 0..self.cols {
-                if self.cell(r, c).is_clue() {
-                    // Check each clue cell
-                    if self.cell(r, c).across_sum().is_some() {
-                        // Calculate across sum
-                        let mut c2 = c + 1;
-                        let mut sum = 0;
-                        let mut items: HashSet<u8> = HashSet::new();
-
-                        while c2 < self.cols {
-                            // Move to the end of the board
-                            if self.cell(r, c2).is_clue() {
-                                // Stop at next clue cell
-                                break;
-                            }
-                            if !items.insert(self.cell(r, c2).value().unwrap_or(0)) {
-                                // Duplicate item found
-                                self.cell_mut(r, c).set_a_incorrect(true);
-                                correct = false;
-                                break;
-                            }
-                            sum += self.cell(r, c2).value().unwrap_or(0);
-                            c2 += 1;
-                        }
-                        // Check sum
-                        if u16::from(sum) != self.cell(r, c).across_sum().unwrap() {
-                            self.cell_mut(r, c).set_a_incorrect(true);
-                            correct = false;
-                        } else {
-                            self.cell_mut(r, c).set_a_incorrect(false);
-                        }
-                    }
-                    if self.cell(r, c).down_sum().is_some() {
-                        let mut r2 = r + 1;
-                        let mut sum2 = 0;
-                        let mut items2: HashSet<u8> = HashSet::new();
-
-                        while r2 < self.rows {
-                            // Move to the end of the board
-                            if self.cell(r2, c).is_clue() {
-                                // Stop at the next clue
-                                break;
-                            }
-                            if !items2.insert(self.cell(r2, c).value().unwrap_or(0)) {
-                                // Duplicate item found
-                                self.cell_mut(r, c).set_d_incorrect(true);
-                                correct = false;
-                                break;
-                            }
-                            sum2 += self.cell(r2, c).value().unwrap_or(0);
-                            r2 += 1;
-                        }
-                        // Check sum
-                        if u16::from(sum2) != self.cell(r, c).down_sum().unwrap() {
-                            self.cell_mut(r, c).set_d_incorrect(true);
-                            correct = false;
-                        } else {
-                            self.cell_mut(r, c).set_d_incorrect(false);
-                        }
-                    }
+    /// Handles a key pressed during play, counting checks and opening the
+    /// solver walkthrough when asked. Keys held with a modifier open no
+    /// screens.
+    pub fn key(&mut self, key: KeyEvent) -> Result<Turn> {
+        match key.code {
+            KeyCode::Right => self.right(),
+            KeyCode::Left => self.left(),
+            KeyCode::Down => self.down(),
+            KeyCode::Up => self.up(),
+            KeyCode::Backspace => self.backspace(),
+            KeyCode::Enter => {
+                self.checks += 1;
+                if self.check() {
+                    return Ok(Turn::Solved);
+                }
+            }
+            KeyCode::Char(c) if self.allowed(c).is_some() => self.digit(c),
+            KeyCode::Char('n') => self.toggle_mode(InputMode::Notes),
+            KeyCode::Char('h') => self.toggle_mode(InputMode::Highlight),
+            KeyCode::Char('s') => self.highlight_same = !self.highlight_same,
+            KeyCode::Char('w') => walkthrough::Walkthrough::new(self).run()?,
+            KeyCode::Char('v') => render::set_view(render::view().next()),
+            KeyCode::Char('c') if key.modifiers.is_empty() => {
+                share::show(self)?;
+                render::invalidate();
+            }
+            KeyCode::Esc => return Ok(Turn::Quit),
+            _ => {}
+        }
+        Ok(Turn::Playing)
+    }
+
+    /// Begin game play and control input. Returns how long the player took
+    /// to solve the board once a check finds every run correct, or None if
+    /// they left before solving it. The terminal is restored even if
+    /// drawing fails.
+    pub fn run(&mut self) -> Result<Option<Duration>> {
+        let start = Instant::now();
+        self.checks = 0;
+        let _guard = term::Guard::new()?;
+        render::invalidate();
+
+        loop {
+            self.draw()?;
+
+            // Handle user input, redrawing everything after a resize
+            let read = term::read()?;
+            if let Event::Resize(..) = read {
+                render::invalidate();
+            }
+            if let Event::Key(key) = read {
+                match self.key(key)? {
+                    Turn::Playing => {}
+                    Turn::Solved => return Ok(Some(start.elapsed())),
+                    Turn::Quit => return Ok(None),
                 }
             }
         }
+    }
+
+    /// Checks every run, flagging the clues of runs that are not correct
+    /// and keeping the report for drawing. Returns whether all are correct.
+    pub fn check(&mut self) -> bool {
+        let report = self.check_report();
+        self.apply_report(&report);
+        let correct = report.is_solved();
+        self.report = report;
         correct
     }
 }
@@ -642,6 +1625,111 @@ mod board_tests {
         assert_eq!(val, None);
     }
 
+    #[test]
+    fn test_checked_access() {
+        let mut board = sample_board();
+        assert!(board.get(1, 2).is_some_and(|c| !c.is_clue()));
+        assert!(board.get(2, 0).is_none() && board.get(0, 3).is_none());
+        board.get_mut(0, 1).unwrap().set_value(Some(3));
+        assert_eq!(board.cell(0, 1).value(), Some(3));
+        assert!(board.get_mut(5, 5).is_none());
+
+        board.set_cursor(1, 1).unwrap();
+        assert_eq!(board.cursor(), (1, 1));
+        let err = board.set_cursor(2, 1).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "cell at row 2, col 1 is outside the 2 x 3 board"
+        );
+        assert_eq!(board.cursor(), (1, 1));
+    }
+
+    #[test]
+    fn test_draw_writes_changes_only() {
+        let mut board = sample_board();
+        let mut frame = Frame::new();
+        let mut out = Vec::new();
+        board.draw_to(&mut out, &mut frame, false, None).unwrap();
+        let first = String::from_utf8(out).unwrap();
+        assert!(first.contains("|  _  |") && first.contains("watch solver"));
+
+        board.cell_mut(1, 2).set_value(Some(5));
+        let mut out = Vec::new();
+        board.draw_to(&mut out, &mut frame, false, None).unwrap();
+        let second = String::from_utf8(out).unwrap();
+        assert!(second.contains("|  5  |"));
+        assert_eq!(second.matches('|').count(), 2);
+        assert!(!second.contains("watch solver"));
+
+        let mut out = Vec::new();
+        board
+            .draw_to(&mut out, &mut frame, false, Some(("help", "status")))
+            .unwrap();
+        let third = String::from_utf8(out).unwrap();
+        assert!(!third.contains('|') && third.contains("help") && third.contains("status"));
+    }
+
+    #[test]
+    fn test_modified_keys_open_no_screens() {
+        use crossterm::event::KeyModifiers;
+        let mut board = sample_board();
+        // Ctrl-C arrives as c in raw mode, and must not open the share screen
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert_eq!(board.key(ctrl_c).unwrap(), Turn::Playing);
+        let escape = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
+        assert_eq!(board.key(escape).unwrap(), Turn::Quit);
+    }
+
+    #[test]
+    fn test_draw_compact() {
+        let mut board = sample_board();
+        board.cell_mut(0, 1).set_value(Some(3));
+        let mut out = Vec::new();
+        board
+            .draw_to(&mut out, &mut Frame::new(), true, None)
+            .unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(!text.contains('┌'));
+        // Across sum 4 under the clue's backslash, then the value at column 4
+        assert!(text.contains("\x1b[2;1H \\4 "));
+        assert!(text.contains("\x1b[1;5H 3 "));
+        // The help line sits one line below the two-line rows
+        assert!(text.contains("\x1b[6;1H\x1b[K"));
+    }
+
+    #[test]
+    fn test_render_long_products() {
+        // Every cell widens to fit the longest product, keeping boxes aligned
+        let board = format::parse("\\ 1680* 2*\n*1680 _ _\n*2 _ _\n").unwrap();
+        let text = board.render();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[1], "            1680×         2×");
+        assert_eq!(lines[3], "           ┌─────────┐┌─────────┐");
+        assert_eq!(lines[4], "     ×1680 |    _    ||    _    |");
+        assert_eq!(lines[7], "     ×2    |    _    ||    _    |");
+
+        let mut out = Vec::new();
+        board
+            .draw_to(&mut out, &mut Frame::new(), true, None)
+            .unwrap();
+        let text = String::from_utf8(out).unwrap();
+        // Compact cells are six columns, so the product stays in its own
+        assert!(text.contains("\x1b[4;1H ×1680"));
+        assert!(text.contains("\x1b[3;7H  _  "));
+    }
+
+    #[test]
+    fn test_try_new() {
+        let grid = || vec![vec![Cell::new_play(); 3], vec![Cell::new_play(); 3]];
+        assert!(Board::try_new(grid(), 2, 3).is_ok());
+        let err = Board::try_new(grid(), 3, 3).err().unwrap();
+        assert_eq!(err.to_string(), "expected 3 rows, found 2");
+        let mut short = grid();
+        short[1].pop();
+        let err = Board::try_new(short, 2, 3).err().unwrap();
+        assert_eq!(err.to_string(), "row 1: expected 3 cells, found 2");
+    }
+
     #[test]
     fn test_cursor_wraps() {
         let mut board = sample_board();
@@ -817,6 +1905,181 @@ mod board_tests {
         assert_eq!(board.cur_cell().value(), None);
     }
 
+    #[test]
+    fn test_same_value_highlight() {
+        let mut board = sample_board();
+        board.cell_mut(0, 1).set_value(Some(3));
+        board.cell_mut(1, 2).set_value(Some(3));
+        board.cell_mut(1, 1).set_value(Some(1));
+        board.cursor_col = 1;
+        assert!(board.is_highlighted(1, 2));
+        assert!(!board.is_highlighted(0, 1));
+        assert!(!board.is_highlighted(1, 1));
+        board.set_highlight_same(false);
+        assert!(!board.is_highlighted(1, 2));
+    }
+
+    #[test]
+    fn test_highlight_mode_uses_notes() {
+        let mut board = sample_board();
+        board.cursor_col = 1;
+        board.toggle_mode(InputMode::Notes);
+        board.digit('4');
+        assert!(board.cur_cell().has_note(4));
+        assert_eq!(board.cur_cell().value(), None);
+        board.cell_mut(1, 2).set_value(Some(4));
+        board.toggle_mode(InputMode::Highlight);
+        board.digit('4');
+        assert_eq!(board.highlight_digit(), Some(4));
+        assert!(board.is_highlighted(0, 1));
+        assert!(board.is_highlighted(1, 2));
+        assert!(!board.is_highlighted(0, 2));
+        board.toggle_mode(InputMode::Highlight);
+        assert_eq!(board.mode(), InputMode::Normal);
+        assert_eq!(board.highlight_digit(), None);
+    }
+
+    #[test]
+    fn test_runs() {
+        let board = sample_board();
+        let runs = board.runs();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].direction, Direction::Across);
+        assert_eq!(runs[0].sum, 4);
+        assert_eq!(runs[0].cells, vec![(0, 1), (0, 2)]);
+        assert_eq!(runs[1].direction, Direction::Down);
+        assert_eq!(runs[1].cells, Vec::<(usize, usize)>::new());
+    }
+
+    #[test]
+    fn test_render() {
+        let mut board = sample_board();
+        board.cell_mut(0, 1).set_value(Some(1));
+        let text = board.render();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[1], "   \\4  |  1  ||  _  |");
+        assert_eq!(lines[4], "  7\\   |  _  ||  _  |");
+    }
+
+    #[test]
+    fn test_check_keeps_report() {
+        let mut board = sample_board();
+        assert!(board.last_check().runs.is_empty());
+        board.cell_mut(0, 1).set_value(Some(2));
+        board.cell_mut(0, 2).set_value(Some(2));
+        assert!(!board.check());
+        let across = board.last_check().get(0, 0, Direction::Across).unwrap();
+        assert_eq!(across.status, RunStatus::Duplicate);
+        assert_eq!(board.last_check().summary(), "1 wrong sum, 1 duplicate");
+        board.cell_mut(0, 1).set_value(Some(1));
+        board.cell_mut(0, 2).set_value(Some(3));
+        assert!(!board.check());
+        assert!(!board.cell(0, 0).is_a_incorrect());
+        assert!(board.cell(1, 0).is_d_incorrect());
+        assert_eq!(board.last_check().summary(), "1 correct, 1 wrong sum");
+    }
+
+    #[test]
+    fn test_thumbnail() {
+        let mut board = sample_board();
+        board.cell_mut(0, 1).set_value(Some(1));
+        assert_eq!(board.thumbnail(), "\\ 1 .\n\\ . .\n");
+    }
+
+    #[test]
+    fn test_structure_errors() {
+        let board = sample_board();
+        let errors = board.structure_errors();
+        assert!(errors.contains(&StructureError::EmptyRun {
+            row: 1,
+            col: 0,
+            direction: Direction::Down
+        }));
+        assert!(errors.contains(&StructureError::MissingClue {
+            row: 1,
+            col: 1,
+            direction: Direction::Across
+        }));
+        assert!(errors.contains(&StructureError::MissingClue {
+            row: 0,
+            col: 1,
+            direction: Direction::Down
+        }));
+        let grid = vec![
+            vec![Cell::new_clue(None, None), Cell::new_clue(None, Some(18))],
+            vec![Cell::new_clue(None, None), Cell::new_play()],
+            vec![Cell::new_clue(None, None), Cell::new_play()],
+        ];
+        let board = Board::new(grid, 3, 2);
+        assert_eq!(
+            board.structure_errors(),
+            vec![StructureError::ImpossibleSum {
+                row: 0,
+                col: 1,
+                direction: Direction::Down,
+                sum: 18,
+                operation: Operation::Sum,
+                len: 2,
+                distinct: true
+            }]
+        );
+        assert_eq!(
+            board.structure_errors()[0].to_string(),
+            "down clue at row 0, col 1: no 2 distinct digits sum to 18"
+        );
+        let mut board = board;
+        board.cell_mut(0, 1).set_operation(Operation::Product);
+        assert!(board.structure_errors().is_empty());
+        *board.cell_mut(0, 1) = Cell::new_clue(None, Some(17));
+        board.cell_mut(0, 1).set_operation(Operation::Product);
+        assert_eq!(
+            board.structure_errors()[0].to_string(),
+            "down clue at row 0, col 1: no 2 distinct digits multiply to 17"
+        );
+    }
+
+    #[test]
+    fn test_cage_structure_errors() {
+        let mut board = sample_board();
+        board.set_cages(vec![
+            Cage::new(vec![(0, 1), (1, 1)], Some(3)),
+            Cage::new(vec![(0, 0), (0, 2)], None),
+            Cage::new(vec![(0, 2), (1, 2)], Some(18)),
+        ]);
+        let errors = board.structure_errors();
+        assert!(
+            !errors
+                .iter()
+                .any(|e| matches!(e, StructureError::MissingClue { row: 0, col: 1, .. }))
+        );
+        assert!(errors.contains(&StructureError::CageCell {
+            cage: 1,
+            row: 0,
+            col: 0
+        }));
+        assert!(errors.contains(&StructureError::ImpossibleCage { cage: 2, len: 2 }));
+        assert_eq!(
+            StructureError::ImpossibleCage { cage: 2, len: 2 }.to_string(),
+            "cage 2: no 2 digits can fill it"
+        );
+    }
+
+    #[test]
+    fn test_cage_outline() {
+        let mut board = sample_board();
+        board.set_cages(vec![Cage::new(vec![(0, 1), (0, 2)], Some(12))]);
+        let (top, mid, bottom) = board.play_lines(0, 1, board.cell(0, 1), 5);
+        assert_eq!(top, "┏12━━━┑");
+        assert_eq!(mid, "┃  _  |");
+        assert_eq!(bottom, "┗━━━━━┙");
+        let (top, _, _) = board.play_lines(0, 2, board.cell(0, 2), 5);
+        assert_eq!(top, "┍━━━━━┓");
+        let (top, mid, _) = board.play_lines(1, 1, board.cell(1, 1), 5);
+        assert_eq!((top.as_str(), mid.as_str()), ("┌─────┐", "|  _  |"));
+        assert!(board.render().lines().next().unwrap().contains("┏12━━━"));
+    }
+
     #[test]
     fn test_check_with_empty_cells() {
         let mut board = sample_board();
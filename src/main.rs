@@ -1,209 +1,163 @@
-use kakuro::{Board, Cell};
-
-use crossterm::{
-    cursor::MoveTo,
-    event::{self, Event, KeyCode},
-    execute,
-    style::{Print, Stylize},
-    terminal,
+use kakuro::{
+    coop::{self, Client},
+    daily,
+    editor::{self, Editor},
+    home::{Action, Home},
+    json::Puzzle,
+    pack,
+    race::{self, Link},
+    render::{self, View},
+    share, term,
+    win::{self, Choice},
 };
-use std::io::stdout;
-
-fn main() {
-    home()
-}
 
-/// Returns a list of predefined levels & board constructors.
-fn levels() -> Vec<(&'static str, Board)> {
-    let board1 = vec![
-        vec![
-            Cell::new_clue(None, None),
-            Cell::new_clue(None, None),
-            Cell::new_clue(None, Some(30)),
-            Cell::new_clue(None, Some(10)),
-            Cell::new_clue(None, None),
-            Cell::new_clue(None, None),
-            Cell::new_clue(None, Some(3)),
-            Cell::new_clue(None, Some(13)),
-        ],
-        vec![
-            Cell::new_clue(None, None),
-            Cell::new_clue(Some(7), Some(17)),
-            Cell::new_play(),
-            Cell::new_play(),
-            Cell::new_clue(None, None),
-            Cell::new_clue(Some(10), Some(10)),
-            Cell::new_play(),
-            Cell::new_play(),
-        ],
-        vec![
-            Cell::new_clue(Some(24), None),
-            Cell::new_play(),
-            Cell::new_play(),
-            Cell::new_play(),
-            Cell::new_clue(Some(7), Some(23)),
-            Cell::new_play(),
-            Cell::new_play(),
-            Cell::new_play(),
-        ],
-        vec![
-            Cell::new_clue(Some(17), None),
-            Cell::new_play(),
-            Cell::new_play(),
-            Cell::new_clue(Some(10), Some(8)),
-            Cell::new_play(),
-            Cell::new_play(),
-            Cell::new_clue(None, Some(30)),
-            Cell::new_clue(None, None),
-        ],
-        vec![
-            Cell::new_clue(None, None),
-            Cell::new_clue(Some(35), None),
-            Cell::new_play(),
-            Cell::new_play(),
-            Cell::new_play(),
-            Cell::new_play(),
-            Cell::new_play(),
-            Cell::new_clue(None, Some(10)),
-        ],
-        vec![
-            Cell::new_clue(None, None),
-            Cell::new_clue(None, Some(4)),
-            Cell::new_clue(Some(10), Some(13)),
-            Cell::new_play(),
-            Cell::new_play(),
-            Cell::new_clue(Some(7), Some(15)),
-            Cell::new_play(),
-            Cell::new_play(),
-        ],
-        vec![
-            Cell::new_clue(Some(7), None),
-            Cell::new_play(),
-            Cell::new_play(),
-            Cell::new_play(),
-            Cell::new_clue(Some(24), None),
-            Cell::new_play(),
-            Cell::new_play(),
-            Cell::new_play(),
-        ],
-        vec![
-            Cell::new_clue(Some(12), None),
-            Cell::new_play(),
-            Cell::new_play(),
-            Cell::new_clue(None, None),
-            Cell::new_clue(Some(16), None),
-            Cell::new_play(),
-            Cell::new_play(),
-            Cell::new_clue(None, None),
-        ],
-    ];
+use crossterm::event::Event;
+use std::{net::TcpListener, process::ExitCode};
 
-    let board2 = vec![
-        vec![
-            Cell::new_clue(None, None),
-            Cell::new_clue(None, Some(3)),
-            Cell::new_clue(None, Some(14)),
-            Cell::new_clue(None, None),
-            Cell::new_clue(None, None),
-        ],
-        vec![
-            Cell::new_clue(Some(9), None),
-            Cell::new_play(),
-            Cell::new_play(),
-            Cell::new_clue(None, Some(13)),
-            Cell::new_clue(None, None),
-        ],
-        vec![
-            Cell::new_clue(Some(7), None),
-            Cell::new_play(),
-            Cell::new_play(),
-            Cell::new_play(),
-            Cell::new_clue(None, Some(9)),
-        ],
-        vec![
-            Cell::new_clue(None, None),
-            Cell::new_clue(Some(6), None),
-            Cell::new_play(),
-            Cell::new_play(),
-            Cell::new_play(),
-        ],
-        vec![
-            Cell::new_clue(None, None),
-            Cell::new_clue(None, None),
-            Cell::new_clue(Some(17), None),
-            Cell::new_play(),
-            Cell::new_play(),
-        ],
-    ];
+mod cli;
 
-    let board3 = vec![
-        vec![
-            Cell::new_clue(None, None),
-            Cell::new_clue(None, Some(12)),
-            Cell::new_clue(None, Some(3)),
-        ],
-        vec![
-            Cell::new_clue(Some(9), None),
-            Cell::new_play(),
-            Cell::new_play(),
-        ],
-        vec![
-            Cell::new_clue(Some(6), None),
-            Cell::new_play(),
-            Cell::new_play(),
-        ],
-    ];
+fn main() -> ExitCode {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    // A leading view flag picks how the interactive game draws boards
+    let view = match args.first().map(String::as_str) {
+        Some("--compact") => Some(View::Compact),
+        Some("--boxed") => Some(View::Boxed),
+        _ => None,
+    };
+    if let Some(view) = view {
+        render::set_view(view);
+        args.remove(0);
+    }
+    if args.is_empty() {
+        return match home() {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("kakuro: {}", e);
+                ExitCode::FAILURE
+            }
+        };
+    }
+    let interactive = match args[0].as_str() {
+        "play" => Some(play(&args[1..])),
+        "race" => Some(race(&args[1..])),
+        "coop" => Some(coop(&args[1..])),
+        _ => None,
+    };
+    if let Some(result) = interactive {
+        return match result {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("kakuro: {}", e);
+                ExitCode::from(2)
+            }
+        };
+    }
+    cli::run(&args)
+}
 
-    vec![
-        ("Level 1 (3 x 3)", Board::new(board3, 3, 3)),
-        ("Level 2 (5 x 5)", Board::new(board2, 5, 5)),
-        ("Level 3 (8 x 8)", Board::new(board1, 8, 8)),
-    ]
+/// Plays a puzzle from a share code or a file, offering a replay once it
+/// is solved
+fn play(args: &[String]) -> Result<(), String> {
+    let board = match args {
+        [flag, code] if flag == "--code" => share::decode(code).map_err(|e| e.to_string())?,
+        [path] => cli::load(path)?.board,
+        _ => return Err("play takes FILE or --code CODE".to_string()),
+    };
+    loop {
+        let mut game = board.clone();
+        let Some(time) = game.run().map_err(|e| e.to_string())? else {
+            return Ok(());
+        };
+        let lines = win::summary(time, game.checks());
+        if win::show("Shared puzzle", &lines, false).map_err(|e| e.to_string())? != Choice::Replay {
+            return Ok(());
+        }
+    }
 }
 
-/// Displays the home menu and handles level selection.
-fn home() {
-    let mut stdout = stdout();
+/// Reads the `FILE [--port N]` arguments of a host, returning the puzzle
+/// and a listener on the port
+fn listen(command: &str, args: &[String], port: u16) -> Result<(Puzzle, TcpListener), String> {
+    let (path, port) = match args {
+        [path] => (path, port),
+        [path, flag, n] if flag == "--port" => (
+            path,
+            n.parse().map_err(|_| format!("invalid port '{}'", n))?,
+        ),
+        _ => {
+            return Err(format!(
+                "{} host takes FILE and an optional --port N",
+                command
+            ));
+        }
+    };
+    let puzzle = cli::load(path)?;
+    let listener = TcpListener::bind(("0.0.0.0", port)).map_err(|e| e.to_string())?;
+    Ok((puzzle, listener))
+}
 
-    loop {
-        execute!(
-            stdout,
-            terminal::Clear(terminal::ClearType::All),
-            MoveTo(0, 0)
-        )
-        .unwrap();
-        println!("{}\n", "Welcome to Kakuro".dark_magenta());
-        execute!(
-            stdout,
-            Print("Select a level by pressing its number and entering (q to quit):\n\n")
-        )
-        .unwrap();
+/// Hosts a race on a puzzle file or joins one by address
+fn race(args: &[String]) -> Result<(), String> {
+    let (link, puzzle) = match args {
+        [role, rest @ ..] if role == "host" => {
+            let (puzzle, listener) = listen("race", rest, race::DEFAULT_PORT)?;
+            let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+            println!("waiting for a player on port {}", port);
+            let link = Link::host(&listener, &puzzle).map_err(|e| e.to_string())?;
+            (link, puzzle)
+        }
+        [role, addr] if role == "join" => Link::join(addr.as_str()).map_err(|e| e.to_string())?,
+        _ => return Err("race takes host FILE [--port N] or join ADDR".to_string()),
+    };
+    race::play(link, puzzle.board).map_err(|e| e.to_string())
+}
 
-        let mut lvls = levels();
-        for (i, (label, _ctor)) in lvls.iter().enumerate() {
-            execute!(stdout, Print(format!("  {}) {}\n", i + 1, label))).unwrap();
+/// Hosts a shared board for cooperative play, or joins one by address
+fn coop(args: &[String]) -> Result<(), String> {
+    match args {
+        [role, rest @ ..] if role == "host" => {
+            let (puzzle, listener) = listen("coop", rest, coop::DEFAULT_PORT)?;
+            let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+            println!("sharing the board on port {}, ctrl-c to stop", port);
+            coop::serve(listener, puzzle.board).map_err(|e| e.to_string())
+        }
+        [role, addr] if role == "join" => {
+            let (client, puzzle) = Client::join(addr.as_str()).map_err(|e| e.to_string())?;
+            coop::play(client, puzzle.board).map_err(|e| e.to_string())
         }
+        _ => Err("coop takes host FILE [--port N] or join ADDR".to_string()),
+    }
+}
 
-        if let Event::Key(key) = event::read().unwrap() {
-            match key.code {
-                KeyCode::Char('q') => {
-                    execute!(
-                        stdout,
-                        terminal::Clear(terminal::ClearType::All),
-                        MoveTo(0, 0)
-                    )
-                    .unwrap();
-                    return;
-                }
-                KeyCode::Char(c) if c.is_ascii_digit() && c != '0' => {
-                    let idx = (c.to_digit(10).unwrap() as usize) - 1;
-                    if idx < lvls.len() {
-                        let board = &mut lvls[idx].1;
-                        board.run();
-                    }
+/// Displays the home menu and handles level selection.
+fn home() -> kakuro::Result<()> {
+    let mut home = Home::load();
+    let extra = [
+        format!("d) Daily Kakuro ({})", daily::Date::today()),
+        "p) Puzzle packs".to_string(),
+        "e) Puzzle editor".to_string(),
+        "q) Quit".to_string(),
+    ];
+    let _guard = term::Guard::new()?;
+    loop {
+        home.draw(&extra)?;
+        let Event::Key(key) = term::read()? else {
+            continue;
+        };
+        match home.key(key.code) {
+            Action::Play(index) => home.play(index)?,
+            Action::Daily => daily::play()?,
+            Action::Packs => {
+                pack::browse()?;
+                home.reload();
+            }
+            Action::Editor => {
+                if let Some((rows, cols)) = editor::choose_size()? {
+                    Editor::new(rows, cols).run()?;
                 }
-                _ => {}
             }
+            Action::Quit => return Ok(()),
+            Action::Stay => {}
         }
     }
 }
@@ -0,0 +1,124 @@
+//! Scrollable list of entries shown a page at a time
+
+use crossterm::event::KeyCode;
+use std::ops::Range;
+
+/// List of labels with a selected entry
+pub struct Menu {
+    /// Entry labels
+    items: Vec<String>,
+    /// Index of the selected entry
+    selected: usize,
+    /// Entries shown per page
+    page_size: usize,
+}
+
+impl Menu {
+    /// Creates a menu with the first entry selected
+    pub fn new(items: Vec<String>, page_size: usize) -> Self {
+        Self {
+            items,
+            selected: 0,
+            page_size: page_size.max(1),
+        }
+    }
+
+    /// Returns the number of entries
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Returns whether there are no entries
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Returns the index of the selected entry
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Selects an entry, clamped to the list
+    pub fn select(&mut self, index: usize) {
+        self.selected = index.min(self.len().saturating_sub(1));
+    }
+
+    /// Replaces the labels, keeping the selection where possible
+    pub fn set_items(&mut self, items: Vec<String>) {
+        self.items = items;
+        self.select(self.selected);
+    }
+
+    /// Returns the 0-based page holding the selection
+    pub fn page(&self) -> usize {
+        self.selected / self.page_size
+    }
+
+    /// Returns the number of pages, at least one
+    pub fn pages(&self) -> usize {
+        self.len().div_ceil(self.page_size).max(1)
+    }
+
+    /// Returns the indices of the entries on the current page
+    pub fn visible(&self) -> Range<usize> {
+        let start = self.page() * self.page_size;
+        start..(start + self.page_size).min(self.len())
+    }
+
+    /// Moves the selection for an arrow, page or home/end key, returning
+    /// false for any other key
+    pub fn key(&mut self, code: KeyCode) -> bool {
+        let last = self.len().saturating_sub(1);
+        self.selected = match code {
+            KeyCode::Up => self.selected.saturating_sub(1),
+            KeyCode::Down => (self.selected + 1).min(last),
+            KeyCode::PageUp | KeyCode::Left => self.selected.saturating_sub(self.page_size),
+            KeyCode::PageDown | KeyCode::Right => (self.selected + self.page_size).min(last),
+            KeyCode::Home => 0,
+            KeyCode::End => last,
+            _ => return false,
+        };
+        true
+    }
+
+    /// Returns the lines for the current page, the selection marked with
+    /// `>`, followed by a page count if there is more than one page
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .visible()
+            .map(|i| {
+                let marker = if i == self.selected { '>' } else { ' ' };
+                format!("{} {}", marker, self.items[i])
+            })
+            .collect();
+        if self.pages() > 1 {
+            lines.push(format!("  page {}/{}", self.page() + 1, self.pages()));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod menu_tests {
+    use super::*;
+
+    #[test]
+    fn test_paging() {
+        let items = (1..=12).map(|i| format!("item {}", i)).collect();
+        let mut menu = Menu::new(items, 5);
+        assert_eq!(menu.pages(), 3);
+        assert_eq!(menu.lines()[0], "> item 1");
+        assert!(menu.key(KeyCode::Up));
+        assert_eq!(menu.selected(), 0);
+        menu.key(KeyCode::PageDown);
+        assert_eq!((menu.selected(), menu.visible()), (5, 5..10));
+        assert_eq!(menu.lines()[5], "  page 2/3");
+        menu.key(KeyCode::End);
+        assert_eq!(menu.visible(), 10..12);
+        menu.key(KeyCode::PageDown);
+        assert_eq!(menu.selected(), 11);
+        assert!(!menu.key(KeyCode::Enter));
+        menu.set_items(vec!["only".to_string()]);
+        assert_eq!((menu.selected(), menu.lines().len()), (0, 1));
+    }
+}
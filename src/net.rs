@@ -0,0 +1,106 @@
+//! Connections carrying one JSON message per line
+//!
+//! Race and coop talk over TCP the same way: each message is written as a
+//! single line of JSON, and a background thread reads the other side's
+//! lines into a channel the game loop checks between keys.
+
+use crate::Result;
+use serde::{Serialize, de::DeserializeOwned};
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{Shutdown, TcpStream, ToSocketAddrs},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::Duration,
+};
+
+/// How often a networked game looks for messages between keys
+pub(crate) const TICK: Duration = Duration::from_millis(100);
+
+/// Returns an error for a line the other side should not have sent
+pub(crate) fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Writes a message as a single line of JSON
+pub(crate) fn write_line(stream: &mut TcpStream, message: &impl Serialize) -> Result<()> {
+    let mut line = serde_json::to_string(message).map_err(|e| invalid(e.to_string()))?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// Reads messages on a background thread until the connection closes,
+/// when the returned receiver hangs up
+pub(crate) fn spawn_reader<T>(reader: BufReader<TcpStream>) -> Receiver<T>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    let (sender, incoming) = mpsc::channel();
+    thread::spawn(move || {
+        for line in reader.lines() {
+            let Ok(line) = line else {
+                return;
+            };
+            // A line that cannot be read is skipped, not fatal
+            if let Ok(message) = serde_json::from_str(&line)
+                && sender.send(message).is_err()
+            {
+                return;
+            }
+        }
+    });
+    incoming
+}
+
+/// Connects to a host, returning the stream, a reader for what follows and
+/// the first message the host sends
+pub(crate) fn connect<T: DeserializeOwned>(
+    addr: impl ToSocketAddrs,
+) -> Result<(TcpStream, BufReader<TcpStream>, T)> {
+    let stream = TcpStream::connect(addr)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(invalid("host closed the connection").into());
+    }
+    let first = serde_json::from_str(&line).map_err(|e| invalid(e.to_string()))?;
+    Ok((stream, reader, first))
+}
+
+/// The other end of a connection
+pub(crate) struct Peer<T> {
+    /// Stream messages are written to
+    stream: TcpStream,
+    /// Messages read by a background thread, which hangs up when the
+    /// connection closes
+    incoming: Receiver<T>,
+}
+
+impl<T: DeserializeOwned + Send + 'static> Peer<T> {
+    /// Starts reading the other end's messages in the background
+    pub(crate) fn new(stream: TcpStream, reader: BufReader<TcpStream>) -> Self {
+        Self {
+            stream,
+            incoming: spawn_reader(reader),
+        }
+    }
+
+    /// Sends a message
+    pub(crate) fn send(&mut self, message: &impl Serialize) -> Result<()> {
+        write_line(&mut self.stream, message)
+    }
+
+    /// Returns the next message already read, if any
+    pub(crate) fn try_recv(&self) -> std::result::Result<T, TryRecvError> {
+        self.incoming.try_recv()
+    }
+}
+
+impl<T> Drop for Peer<T> {
+    /// Closes the connection, which the reading thread's handle would
+    /// otherwise keep open
+    fn drop(&mut self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
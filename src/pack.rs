@@ -0,0 +1,487 @@
+//! Puzzle packs
+//!
+//! A pack is a named collection of puzzles, either built into the binary or
+//! read from `.txt` files in a packs directory. Pack files hold a header and
+//! one titled puzzle after another in the text format:
+//!
+//! ```text
+//! # Lines starting with # are comments
+//! name: Starter
+//! unlock: yes
+//!
+//! title: First steps
+//! \    12\  3\
+//! \9   _    _
+//! \6   _    _
+//! ```
+//!
+//! With `unlock: yes` each puzzle stays locked until the one before it is
+//! solved. Best times are kept per pack and puzzle title in a local JSON
+//! progress file.
+
+use crate::{
+    Board, error,
+    format::{self, ParseError},
+    menu::Menu,
+    store, term,
+    win::{self, Choice},
+};
+use crossterm::{
+    cursor::MoveTo,
+    event::{Event, KeyCode},
+    execute,
+    style::{Print, Stylize},
+    terminal,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    io::stdout,
+    path::{Path, PathBuf},
+};
+
+/// Environment variable overriding where progress is kept
+pub const PROGRESS_ENV: &str = "KAKURO_PROGRESS";
+/// Environment variable overriding the directory packs are read from
+pub const PACKS_ENV: &str = "KAKURO_PACKS";
+/// Entries shown per menu page
+const PAGE_SIZE: usize = 9;
+
+/// Packs built into the binary
+const BUILTIN: [&str; 2] = [
+    include_str!("../packs/classic.txt"),
+    include_str!("../packs/practice.txt"),
+];
+
+#[derive(Clone)]
+/// Puzzle in a pack
+pub struct Level {
+    /// Title shown in the menu
+    pub title: String,
+    /// Unsolved puzzle
+    pub board: Board,
+}
+
+#[derive(Clone)]
+/// Named collection of puzzles
+pub struct Pack {
+    /// Pack name
+    pub name: String,
+    /// Whether puzzles unlock one at a time
+    pub unlock: bool,
+    /// Puzzles in order of play
+    pub levels: Vec<Level>,
+}
+
+/// Returns a parse error at a line
+fn error(line: usize, message: String) -> ParseError {
+    ParseError {
+        line,
+        column: 1,
+        message,
+    }
+}
+
+/// Parses the grid lines of a puzzle whose title is on line `title_line`
+fn level(title: String, title_line: usize, grid: &str) -> Result<Level, ParseError> {
+    let board = format::parse(grid).map_err(|e| ParseError {
+        line: e.line + title_line,
+        ..e
+    })?;
+    Ok(Level { title, board })
+}
+
+impl Pack {
+    /// Reads a pack file
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        let mut name = None;
+        let mut unlock = false;
+        let mut levels = Vec::new();
+        // Title, its line number and the grid lines read so far
+        let mut current: Option<(String, usize, String)> = None;
+        for (i, line) in text.lines().enumerate() {
+            if line.trim_start().starts_with('#') {
+                if let Some((_, _, grid)) = &mut current {
+                    grid.push('\n');
+                }
+                continue;
+            }
+            match line.split_once(':').map(|(k, v)| (k.trim(), v.trim())) {
+                Some(("title", title)) => {
+                    if let Some((title, at, grid)) = current.take() {
+                        levels.push(level(title, at, &grid)?);
+                    }
+                    current = Some((title.to_string(), i + 1, String::new()));
+                }
+                Some((key, value)) if current.is_none() => match key {
+                    "name" => name = Some(value.to_string()),
+                    "unlock" => {
+                        unlock = match value {
+                            "yes" | "true" => true,
+                            "no" | "false" => false,
+                            v => return Err(error(i + 1, format!("invalid unlock '{}'", v))),
+                        }
+                    }
+                    k => return Err(error(i + 1, format!("unknown header '{}'", k))),
+                },
+                _ => match &mut current {
+                    Some((_, _, grid)) => {
+                        grid.push_str(line);
+                        grid.push('\n');
+                    }
+                    None if line.trim().is_empty() => {}
+                    None => return Err(error(i + 1, "puzzle has no title".to_string())),
+                },
+            }
+        }
+        if let Some((title, at, grid)) = current {
+            levels.push(level(title, at, &grid)?);
+        }
+        let name = name.ok_or_else(|| error(1, "pack has no name".to_string()))?;
+        if levels.is_empty() {
+            return Err(error(1, "pack has no puzzles".to_string()));
+        }
+        Ok(Self {
+            name,
+            unlock,
+            levels,
+        })
+    }
+
+    /// Returns whether a puzzle can be played: always, unless the pack
+    /// unlocks puzzles one at a time and the previous one is unsolved
+    pub fn is_unlocked(&self, index: usize, progress: &Progress) -> bool {
+        !self.unlock || index == 0 || progress.is_solved(&self.name, &self.levels[index - 1].title)
+    }
+
+    /// Returns how many of the pack's puzzles are solved
+    pub fn solved(&self, progress: &Progress) -> usize {
+        self.levels
+            .iter()
+            .filter(|l| progress.is_solved(&self.name, &l.title))
+            .count()
+    }
+}
+
+/// Returns the packs built into the binary
+pub fn builtin() -> Vec<Pack> {
+    BUILTIN
+        .iter()
+        .map(|text| Pack::parse(text).expect("built-in pack is valid"))
+        .collect()
+}
+
+/// Reads every `.txt` pack in a directory, sorted by file name. A missing
+/// directory holds no packs; files that cannot be read give an error
+/// message naming the file.
+pub fn load_dir(dir: &Path) -> Vec<Result<Pack, String>> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|e| e == "txt"))
+        .collect();
+    paths.sort();
+    paths
+        .iter()
+        .map(|path| {
+            let text = fs::read_to_string(path).map_err(|e| e.to_string());
+            text.and_then(|t| Pack::parse(&t).map_err(|e| e.to_string()))
+                .map_err(|e| format!("{}: {}", path.display(), e))
+        })
+        .collect()
+}
+
+/// Returns the built-in packs followed by those in the packs directory,
+/// and an error message for each pack file that could not be read
+pub fn load_all() -> (Vec<Pack>, Vec<String>) {
+    let mut packs = builtin();
+    let mut errors = Vec::new();
+    for result in packs_dir().map(|d| load_dir(&d)).unwrap_or_default() {
+        match result {
+            Ok(pack) => packs.push(pack),
+            Err(e) => errors.push(e),
+        }
+    }
+    (packs, errors)
+}
+
+/// Returns where packs are read from: `$KAKURO_PACKS`, or `.kakuro/packs`
+/// in the home directory
+pub fn packs_dir() -> Option<PathBuf> {
+    store::path(PACKS_ENV, "packs")
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// Local record of solved pack puzzles
+pub struct Progress {
+    /// Best time in seconds, keyed by pack name and then puzzle title
+    pub solved: BTreeMap<String, BTreeMap<String, u64>>,
+}
+
+impl Progress {
+    /// Reads progress from a file, starting empty if it does not exist
+    pub fn load(path: &Path) -> io::Result<Self> {
+        store::load(path)
+    }
+
+    /// Writes progress to a file, creating its directory if needed
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        store::save(self, path)
+    }
+
+    /// Records a solve, keeping the best time for the puzzle
+    pub fn record(&mut self, pack: &str, title: &str, secs: u64) {
+        let times = self.solved.entry(pack.to_string()).or_default();
+        let best = times.entry(title.to_string()).or_insert(secs);
+        *best = (*best).min(secs);
+    }
+
+    /// Returns the best time for a puzzle
+    pub fn time(&self, pack: &str, title: &str) -> Option<u64> {
+        self.solved.get(pack)?.get(title).copied()
+    }
+
+    /// Returns whether a puzzle has been solved
+    pub fn is_solved(&self, pack: &str, title: &str) -> bool {
+        self.time(pack, title).is_some()
+    }
+}
+
+/// Returns where progress is kept: `$KAKURO_PROGRESS`, or
+/// `.kakuro/progress.json` in the home directory
+pub fn progress_path() -> Option<PathBuf> {
+    store::path(PROGRESS_ENV, "progress.json")
+}
+
+/// Reads progress from a file, starting empty if there is none or it
+/// cannot be read
+pub fn load_progress(path: Option<&Path>) -> Progress {
+    path.and_then(|p| Progress::load(p).ok())
+        .unwrap_or_default()
+}
+
+/// Returns the menu label for each puzzle in a pack
+fn labels(pack: &Pack, progress: &Progress) -> Vec<String> {
+    pack.levels
+        .iter()
+        .enumerate()
+        .map(|(i, level)| match progress.time(&pack.name, &level.title) {
+            Some(secs) => format!("[x] {}  {}", level.title, win::clock(secs)),
+            None if pack.is_unlocked(i, progress) => format!("[ ] {}", level.title),
+            None => format!("[-] {} (locked)", level.title),
+        })
+        .collect()
+}
+
+/// Draws a menu under a title, with help and status lines below it
+fn draw_menu(title: &str, menu: &Menu, help: &str, status: &str) -> error::Result<()> {
+    let mut stdout = stdout();
+    execute!(
+        stdout,
+        terminal::Clear(terminal::ClearType::All),
+        MoveTo(0, 0),
+        Print(title.dark_magenta()),
+    )?;
+    let lines = menu.lines();
+    for (i, line) in lines.iter().enumerate() {
+        execute!(stdout, MoveTo(0, i as u16 + 2), Print(line))?;
+    }
+    let y = lines.len() as u16 + 3;
+    execute!(
+        stdout,
+        MoveTo(0, y),
+        Print(help),
+        MoveTo(0, y + 1),
+        Print(status)
+    )?;
+    Ok(())
+}
+
+/// Plays a puzzle if it is unlocked, recording the time if it is solved
+/// and showing the win screen, from which the player may replay it or go on
+/// to the next puzzle. Returns a status message for the menu, empty if there
+/// is nothing to say.
+pub fn play(
+    pack: &Pack,
+    index: usize,
+    progress: &mut Progress,
+    path: Option<&Path>,
+) -> error::Result<String> {
+    let mut index = index;
+    loop {
+        if !pack.is_unlocked(index, progress) {
+            return Ok(format!("Solve {} first", pack.levels[index - 1].title));
+        }
+        let level = &pack.levels[index];
+        let mut board = level.board.clone();
+        let Some(time) = board.run()? else {
+            return Ok(String::new());
+        };
+        progress.record(&pack.name, &level.title, time.as_secs());
+        let mut lines = win::summary(time, board.checks());
+        if let Some(best) = progress.time(&pack.name, &level.title) {
+            lines.push(format!("Best time: {}", win::clock(best)));
+        }
+        lines.push(format!(
+            "{}: {}/{} solved",
+            pack.name,
+            pack.solved(progress),
+            pack.levels.len()
+        ));
+        if let Some(Err(e)) = path.map(|p| progress.save(p)) {
+            lines.push(format!("Could not save progress: {}", e));
+        }
+        let has_next = index + 1 < pack.levels.len();
+        match win::show(&level.title, &lines, has_next)? {
+            Choice::Next => index += 1,
+            Choice::Replay => {}
+            Choice::Back => return Ok(String::new()),
+        }
+    }
+}
+
+/// Lists a pack's puzzles and plays the chosen ones until escape is pressed
+fn play_pack(pack: &Pack, progress: &mut Progress, path: Option<&Path>) -> error::Result<()> {
+    let _guard = term::Guard::new()?;
+    let mut menu = Menu::new(labels(pack, progress), PAGE_SIZE);
+    let mut status = String::new();
+    loop {
+        menu.set_items(labels(pack, progress));
+        let help = "up/down: select | left/right: page | return: play | esc: packs";
+        draw_menu(&pack.name, &menu, help, &status)?;
+        let Event::Key(key) = term::read()? else {
+            continue;
+        };
+        status.clear();
+        match key.code {
+            KeyCode::Enter => status = play(pack, menu.selected(), progress, path)?,
+            KeyCode::Esc => return Ok(()),
+            code => {
+                menu.key(code);
+            }
+        }
+    }
+}
+
+/// Lists the built-in packs and those in the packs directory, and plays
+/// puzzles from the chosen one until escape is pressed
+pub fn browse() -> error::Result<()> {
+    let _guard = term::Guard::new()?;
+    let (packs, errors) = load_all();
+    let path = progress_path();
+    let mut progress = load_progress(path.as_deref());
+    let mut menu = Menu::new(Vec::new(), PAGE_SIZE);
+    loop {
+        menu.set_items(
+            packs
+                .iter()
+                .map(|p| {
+                    format!(
+                        "{} ({}/{} solved)",
+                        p.name,
+                        p.solved(&progress),
+                        p.levels.len()
+                    )
+                })
+                .collect(),
+        );
+        let help = "up/down: select | left/right: page | return: open | esc: home";
+        let status = errors.first().cloned().unwrap_or_default();
+        draw_menu("Puzzle packs", &menu, help, &status)?;
+        let Event::Key(key) = term::read()? else {
+            continue;
+        };
+        match key.code {
+            KeyCode::Enter => play_pack(&packs[menu.selected()], &mut progress, path.as_deref())?,
+            KeyCode::Esc => return Ok(()),
+            code => {
+                menu.key(code);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod pack_tests {
+    use super::*;
+
+    const PACK: &str = "\
+# A comment
+name: Test
+unlock: yes
+
+title: One
+\\ 3\\ 4\\
+\\3 _ _
+\\4 _ _
+
+title: Two
+\\ 12\\ 3\\
+\\9 _ _
+\\6 _ _
+";
+
+    #[test]
+    fn test_parse() {
+        let pack = Pack::parse(PACK).unwrap();
+        assert_eq!((pack.name.as_str(), pack.unlock), ("Test", true));
+        let titles: Vec<&str> = pack.levels.iter().map(|l| l.title.as_str()).collect();
+        assert_eq!(titles, ["One", "Two"]);
+        assert_eq!(pack.levels[1].board.cell(0, 1).down_sum(), Some(12));
+
+        let err = Pack::parse(&PACK.replace("\\6 _ _", "\\6 _ ?"))
+            .err()
+            .unwrap();
+        assert_eq!((err.line, err.column), (13, 3));
+        assert!(Pack::parse("title: One\n\\ 3\\\n").is_err());
+        assert!(Pack::parse("name: Empty\n").is_err());
+        assert!(Pack::parse("name: X\nsize: 3\n").is_err());
+    }
+
+    #[test]
+    fn test_builtin() {
+        let packs = builtin();
+        assert_eq!(packs[0].name, "Classic");
+        assert_eq!(packs[0].levels[0].title, "Level 1 (3 x 3)");
+        for level in packs.iter().flat_map(|p| &p.levels) {
+            assert!(level.board.structure_errors().is_empty(), "{}", level.title);
+            assert!(
+                crate::solver::solve(&level.board).is_some(),
+                "{}",
+                level.title
+            );
+        }
+    }
+
+    #[test]
+    fn test_unlocking_and_progress() {
+        let pack = Pack::parse(PACK).unwrap();
+        let mut progress = Progress::default();
+        assert!(pack.is_unlocked(0, &progress));
+        assert!(!pack.is_unlocked(1, &progress));
+        assert_eq!(labels(&pack, &progress)[1], "[-] Two (locked)");
+        progress.record("Test", "One", 90);
+        progress.record("Test", "One", 120);
+        assert!(pack.is_unlocked(1, &progress));
+        assert_eq!(pack.solved(&progress), 1);
+        assert_eq!(labels(&pack, &progress), ["[x] One  1:30", "[ ] Two"]);
+
+        let dir = std::env::temp_dir().join(format!("kakuro-packs-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.txt"), PACK).unwrap();
+        fs::write(dir.join("b.txt"), "name: Broken\n").unwrap();
+        fs::write(dir.join("c.json"), "{}").unwrap();
+        let loaded = load_dir(&dir);
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].as_ref().unwrap().name, "Test");
+        assert!(loaded[1].as_ref().err().unwrap().contains("b.txt"));
+        let path = dir.join("progress.json");
+        progress.save(&path).unwrap();
+        assert_eq!(Progress::load(&path).unwrap(), progress);
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(load_dir(&dir).is_empty());
+    }
+}
@@ -0,0 +1,376 @@
+//! Two-player race over TCP
+//!
+//! One player hosts a puzzle and the other joins by address, on the same
+//! machine or the local network. Each side writes one JSON message per line:
+//! the host opens with the puzzle, without its solution, then both send
+//! their progress whenever it changes. Digits are never sent, only how much
+//! of the grid is filled, how many checks were made and whether it is
+//! solved, which the opponent sees in the status line. Both players may
+//! solve the board before hearing of each other, so the host settles who
+//! finished first, in the order the two solves reached it, and tells the
+//! other player their place.
+
+use crate::{
+    Board, Result, Turn,
+    json::{Puzzle, PuzzleFile},
+    net::{self, Peer, TICK, invalid, write_line},
+    render, term, win,
+};
+use crossterm::event::{self, Event, KeyCode};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    io::BufReader,
+    net::{TcpListener, ToSocketAddrs},
+    sync::mpsc::TryRecvError,
+    time::{Duration, Instant},
+};
+
+/// Port race hosts listen on unless told otherwise
+pub const DEFAULT_PORT: u16 = 4747;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// How far a player has got, as shared with the opponent
+pub struct Progress {
+    /// Share of the playable cells filled in, from 0 to 100
+    pub percent: u8,
+    /// Number of checks made
+    pub checks: usize,
+    /// Whether a check found the board solved
+    pub solved: bool,
+}
+
+impl Progress {
+    /// Returns the progress made on a board
+    pub fn of(board: &Board, solved: bool) -> Self {
+        let mut cells = 0;
+        let mut filled = 0;
+        for r in 0..board.rows() {
+            for c in 0..board.cols() {
+                let cell = board.cell(r, c);
+                if !cell.is_clue() {
+                    cells += 1;
+                    filled += usize::from(cell.value().is_some());
+                }
+            }
+        }
+        Self {
+            percent: (filled * 100).checked_div(cells).unwrap_or(0) as u8,
+            checks: board.checks(),
+            solved,
+        }
+    }
+}
+
+impl fmt::Display for Progress {
+    /// Describes the progress, such as "40%, 2 checks" or "solved, 1 check"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural = if self.checks == 1 { "" } else { "s" };
+        if self.solved {
+            write!(f, "solved, {} check{}", self.checks, plural)
+        } else {
+            write!(f, "{}%, {} check{}", self.percent, self.checks, plural)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// A line sent between the two players
+pub enum Message {
+    /// The puzzle being raced, sent by the host once a player joins
+    Puzzle(Box<PuzzleFile>),
+    /// The sender's latest progress
+    Progress(Progress),
+    /// The host's word on the finishing order, sent once a player solves
+    Placed {
+        /// Whether the receiver finished first
+        first: bool,
+    },
+}
+
+/// Connection to the opponent
+pub struct Link {
+    /// The opponent's end of the connection
+    peer: Peer<Message>,
+    /// Whether this side hosts the race and so settles the finishing order
+    host: bool,
+    /// Whether this side finished first, once the host has settled it
+    first: Option<bool>,
+}
+
+impl Link {
+    /// Waits for a player to connect to `listener` and sends them the puzzle
+    pub fn host(listener: &TcpListener, puzzle: &Puzzle) -> Result<Self> {
+        let (mut stream, _) = listener.accept()?;
+        let mut file = puzzle.to_file();
+        for cell in &mut file.cells {
+            cell.solution = None;
+        }
+        write_line(&mut stream, &Message::Puzzle(Box::new(file)))?;
+        let reader = BufReader::new(stream.try_clone()?);
+        Ok(Self::new(Peer::new(stream, reader), true))
+    }
+
+    /// Connects to a host and reads the puzzle it is racing
+    pub fn join(addr: impl ToSocketAddrs) -> Result<(Self, Puzzle)> {
+        let (stream, reader, first) = net::connect(addr)?;
+        let Message::Puzzle(file) = first else {
+            return Err(invalid("host did not send a puzzle").into());
+        };
+        let puzzle = Puzzle::from_file(*file).map_err(|e| invalid(e.to_string()))?;
+        Ok((Self::new(Peer::new(stream, reader), false), puzzle))
+    }
+
+    /// Wraps a connection, on the host's side or the joining player's
+    fn new(peer: Peer<Message>, host: bool) -> Self {
+        Self {
+            peer,
+            host,
+            first: None,
+        }
+    }
+
+    /// Sends the player's progress
+    pub fn send(&mut self, progress: Progress) -> Result<()> {
+        self.peer.send(&Message::Progress(progress))
+    }
+
+    /// Applies any progress the opponent has sent since the last call,
+    /// returning false once they have left. The host settles the finishing
+    /// order as soon as the opponent's progress shows them solved.
+    pub fn update(&mut self, opponent: &mut Progress) -> bool {
+        loop {
+            match self.peer.try_recv() {
+                Ok(Message::Progress(progress)) => {
+                    *opponent = progress;
+                    if progress.solved {
+                        self.settle(false);
+                    }
+                }
+                Ok(Message::Placed { first }) if !self.host => self.first = Some(first),
+                Ok(_) => {}
+                Err(TryRecvError::Empty) => return true,
+                Err(TryRecvError::Disconnected) => return false,
+            }
+        }
+    }
+
+    /// Records on the host whether it finished first, unless the order is
+    /// already settled, and tells the opponent their place
+    pub fn settle(&mut self, first: bool) {
+        if !self.host || self.first.is_some() {
+            return;
+        }
+        self.first = Some(first);
+        // An opponent who has left is noticed by reading
+        let _ = self.peer.send(&Message::Placed { first: !first });
+    }
+
+    /// Returns whether this side finished first, None until settled
+    pub fn first(&self) -> Option<bool> {
+        self.first
+    }
+}
+
+/// Returns the race's status line: the opponent's progress, then the
+/// player's, or their time once they have solved the board and their place
+/// once it is settled
+pub fn status(
+    mine: Progress,
+    opponent: Progress,
+    connected: bool,
+    finish: Option<(Duration, Option<bool>)>,
+) -> String {
+    let them = if connected {
+        format!("opponent: {}", opponent)
+    } else {
+        format!("opponent left at {}", opponent)
+    };
+    let you = match finish {
+        Some((time, first)) => format!(
+            "you solved it in {}{}",
+            win::clock(time.as_secs()),
+            match first {
+                Some(true) => ", first",
+                Some(false) => ", second",
+                None => "",
+            }
+        ),
+        None => format!("you: {}", mine),
+    };
+    format!("{} | {}", them, you)
+}
+
+/// Returns the help line, which leaves out the solver walkthrough
+fn help(board: &Board, finished: bool) -> String {
+    if finished {
+        return render::help_line(&[("esc", "leave the race")], "");
+    }
+    let keys = [
+        ("esc", "leave"),
+        ("return", "check"),
+        ("n", "notes"),
+        ("h", "highlight"),
+        ("s", "same digits"),
+        ("v", "view"),
+    ];
+    render::help_line(&keys, &board.mode_tag())
+}
+
+/// Races the opponent on a board until the player leaves, keeping the
+/// status line up to date with both players' progress
+pub fn play(mut link: Link, mut board: Board) -> Result<()> {
+    let start = Instant::now();
+    let mut opponent = Progress::default();
+    let mut connected = true;
+    let mut sent = None;
+    let mut finish = None;
+    let _guard = term::Guard::new()?;
+    render::invalidate();
+    loop {
+        let mine = Progress::of(&board, finish.is_some());
+        if connected && sent != Some(mine) {
+            // A failed send means the opponent left, as reading will show
+            connected = link.send(mine).is_ok();
+            sent = Some(mine);
+        }
+        connected &= link.update(&mut opponent);
+        // Without the host's word, a player left alone is first unless the
+        // host solved it before leaving
+        let first = link
+            .first()
+            .or_else(|| (!connected).then_some(!opponent.solved));
+        let line = status(mine, opponent, connected, finish.map(|time| (time, first)));
+        board.draw_footer(&help(&board, finish.is_some()), &line)?;
+
+        if !event::poll(TICK)? {
+            continue;
+        }
+        let read = term::read()?;
+        if let Event::Resize(..) = read {
+            render::invalidate();
+        }
+        let Event::Key(key) = read else {
+            continue;
+        };
+        match key.code {
+            KeyCode::Esc => return Ok(()),
+            _ if finish.is_some() => {}
+            KeyCode::Char('w') => {}
+            _ => {
+                if board.key(key)? == Turn::Solved {
+                    // A solve that reached the host while checking goes first
+                    connected &= link.update(&mut opponent);
+                    link.settle(true);
+                    finish = Some(start.elapsed());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod race_tests {
+    use super::*;
+    use crate::format;
+    use std::thread;
+
+    #[test]
+    fn test_progress() {
+        let board = format::parse("\\ 3\\ 4\\\n\\3 1 _\n\\4 _ _\n").unwrap();
+        let progress = Progress::of(&board, false);
+        assert_eq!(progress.percent, 25);
+        assert_eq!(progress.to_string(), "25%, 0 checks");
+        let solved = Progress {
+            checks: 1,
+            ..Progress::of(&board, true)
+        };
+        assert_eq!(solved.to_string(), "solved, 1 check");
+    }
+
+    #[test]
+    fn test_status() {
+        let mine = Progress {
+            percent: 40,
+            checks: 1,
+            solved: false,
+        };
+        let opponent = Progress {
+            percent: 55,
+            checks: 2,
+            solved: false,
+        };
+        assert_eq!(
+            status(mine, opponent, true, None),
+            "opponent: 55%, 2 checks | you: 40%, 1 check"
+        );
+        let finish = Some((Duration::from_secs(83), Some(true)));
+        assert_eq!(
+            status(mine, opponent, false, finish),
+            "opponent left at 55%, 2 checks | you solved it in 1:23, first"
+        );
+        let waiting = Some((Duration::from_secs(83), None));
+        assert_eq!(
+            status(mine, opponent, true, waiting),
+            "opponent: 55%, 2 checks | you solved it in 1:23"
+        );
+    }
+
+    #[test]
+    fn test_race_over_loopback() {
+        let board = format::parse("\\ 3\\ 4\\\n\\3 1 _\n\\4 _ _\n").unwrap();
+        let mut puzzle = Puzzle::new(board);
+        puzzle.solution = crate::solver::solve(&puzzle.board);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let joiner = thread::spawn(move || Link::join(addr).unwrap());
+        let mut host = Link::host(&listener, &puzzle).unwrap();
+        let (mut guest, raced) = joiner.join().unwrap();
+        assert_eq!(
+            format::to_text(&raced.board),
+            format::to_text(&puzzle.board)
+        );
+        assert!(raced.solution.is_none());
+
+        let sent = Progress {
+            percent: 50,
+            checks: 1,
+            solved: false,
+        };
+        guest.send(sent).unwrap();
+        let mut seen = Progress::default();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while seen != sent && Instant::now() < deadline {
+            assert!(host.update(&mut seen));
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(seen, sent);
+        host.send(Progress::default()).unwrap();
+
+        // Both solve before hearing of the other: the host's order stands
+        let solved = Progress {
+            solved: true,
+            ..sent
+        };
+        guest.send(solved).unwrap();
+        host.settle(true);
+        let mut heard = Progress::default();
+        while guest.first().is_none() && Instant::now() < deadline {
+            assert!(guest.update(&mut heard));
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(guest.first(), Some(false));
+        while !seen.solved && Instant::now() < deadline {
+            assert!(host.update(&mut seen));
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(host.first(), Some(true));
+
+        drop(guest);
+        while host.update(&mut seen) {
+            assert!(Instant::now() < deadline, "leaving was not noticed");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+}
@@ -0,0 +1,278 @@
+//! Diff-based drawing
+//!
+//! A [`Frame`] remembers the bytes last written for each region of the
+//! screen, a board cell or a whole line, and writes a region again only when
+//! its bytes change. Screens that clear the terminal themselves call
+//! [`invalidate`] so the next frame is drawn in full.
+//!
+//! Boards are drawn boxed, three lines per cell, or compact, two short lines
+//! per cell. The [`View`] setting picks one or, by default, chooses compact
+//! when the boxed board does not fit the terminal.
+
+use crossterm::{
+    cursor::{DisableBlinking, EnableBlinking, MoveTo},
+    queue,
+    style::{Attribute, Stylize},
+    terminal::{self, ClearType},
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    io::{self, Write},
+    sync::{
+        Mutex, MutexGuard, PoisonError,
+        atomic::{AtomicU8, Ordering},
+    },
+};
+
+/// Frame shared by everything that draws a board
+static FRAME: Mutex<Frame> = Mutex::new(Frame::new());
+/// View boards are drawn in, as a `View` discriminant
+static VIEW: AtomicU8 = AtomicU8::new(View::Auto as u8);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// How boards are laid out on screen
+pub enum View {
+    /// Compact when the boxed board does not fit the terminal
+    Auto,
+    /// Boxed cells, three lines each
+    Boxed,
+    /// Two short lines per cell
+    Compact,
+}
+
+impl View {
+    /// Returns the view after this one in the order the view key cycles
+    pub fn next(self) -> Self {
+        match self {
+            View::Auto => View::Boxed,
+            View::Boxed => View::Compact,
+            View::Compact => View::Auto,
+        }
+    }
+}
+
+impl fmt::Display for View {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            View::Auto => "auto",
+            View::Boxed => "boxed",
+            View::Compact => "compact",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Returns the view boards are drawn in
+pub fn view() -> View {
+    match VIEW.load(Ordering::Relaxed) {
+        1 => View::Boxed,
+        2 => View::Compact,
+        _ => View::Auto,
+    }
+}
+
+/// Sets the view boards are drawn in, redrawing the next frame in full
+pub fn set_view(view: View) {
+    VIEW.store(view as u8, Ordering::Relaxed);
+    invalidate();
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+/// Part of the screen redrawn as a whole
+pub enum Region {
+    /// Board cell at a row and column
+    Cell(usize, usize),
+    /// Screen line at a row, cleared to its end when written
+    Line(u16),
+}
+
+#[derive(Clone, Debug)]
+/// Screen contents as of the last frame
+pub struct Frame {
+    /// Bytes written for each region
+    regions: BTreeMap<Region, Vec<u8>>,
+    /// Regions written or kept during the current frame
+    touched: BTreeSet<Region>,
+    /// Whether the cursor was left blinking, None if unknown
+    blinking: Option<bool>,
+    /// Whether the screen must be cleared before the next write
+    stale: bool,
+}
+
+impl Default for Frame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Frame {
+    /// Creates a frame that draws everything on its first use
+    pub const fn new() -> Self {
+        Self {
+            regions: BTreeMap::new(),
+            touched: BTreeSet::new(),
+            blinking: None,
+            stale: true,
+        }
+    }
+
+    /// Forgets the screen contents so the next frame clears and redraws it
+    pub fn invalidate(&mut self) {
+        self.regions.clear();
+        self.touched.clear();
+        self.blinking = None;
+        self.stale = true;
+    }
+
+    /// Writes a region's bytes unless they match the last frame
+    pub fn update(
+        &mut self,
+        out: &mut impl Write,
+        region: Region,
+        bytes: Vec<u8>,
+    ) -> io::Result<()> {
+        self.touched.insert(region);
+        if self.regions.get(&region) == Some(&bytes) {
+            return Ok(());
+        }
+        if self.stale {
+            queue!(out, terminal::Clear(ClearType::All))?;
+            self.stale = false;
+        }
+        out.write_all(&bytes)?;
+        self.regions.insert(region, bytes);
+        Ok(())
+    }
+
+    /// Writes a line of text, clearing whatever followed it before
+    pub fn line(&mut self, out: &mut impl Write, y: u16, text: &str) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        queue!(
+            bytes,
+            MoveTo(0, y),
+            terminal::Clear(ClearType::UntilNewLine)
+        )?;
+        bytes.extend_from_slice(text.as_bytes());
+        self.update(out, Region::Line(y), bytes)
+    }
+
+    /// Ends a frame: blanks lines that were not written this time, sets the
+    /// cursor's blinking if it changed, moves it and flushes
+    pub fn finish(
+        &mut self,
+        out: &mut impl Write,
+        cursor: (u16, u16),
+        blinking: bool,
+    ) -> io::Result<()> {
+        let gone: Vec<Region> = self
+            .regions
+            .keys()
+            .filter(|r| !self.touched.contains(r))
+            .copied()
+            .collect();
+        for region in gone {
+            if let Region::Line(y) = region {
+                queue!(out, MoveTo(0, y), terminal::Clear(ClearType::UntilNewLine))?;
+            }
+            self.regions.remove(&region);
+        }
+        self.touched.clear();
+        if self.blinking != Some(blinking) {
+            if blinking {
+                queue!(out, EnableBlinking)?;
+            } else {
+                queue!(out, DisableBlinking)?;
+            }
+            self.blinking = Some(blinking);
+        }
+        queue!(out, MoveTo(cursor.0, cursor.1))?;
+        out.flush()
+    }
+}
+
+/// Locks the shared frame
+pub fn frame() -> MutexGuard<'static, Frame> {
+    FRAME.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Makes the next board drawn clear the screen and draw everything
+pub fn invalidate() {
+    frame().invalidate();
+}
+
+/// Returns a help line of bold keys and what they do, separated by bars,
+/// followed by `tag`, if any, in bold
+pub fn help_line(keys: &[(&str, &str)], tag: &str) -> String {
+    let mut line = keys
+        .iter()
+        .map(|(key, action)| format!("{}: {}", key.attribute(Attribute::Bold), action))
+        .collect::<Vec<_>>()
+        .join(" | ");
+    if !tag.is_empty() {
+        line.push_str(&tag.attribute(Attribute::Bold).to_string());
+    }
+    line
+}
+
+#[cfg(test)]
+mod render_tests {
+    use super::*;
+
+    #[test]
+    fn test_view_cycle() {
+        assert_eq!(View::Auto.next(), View::Boxed);
+        assert_eq!(View::Boxed.next().next(), View::Auto);
+        assert_eq!(View::Compact.to_string(), "compact");
+    }
+
+    #[test]
+    fn test_only_changes_are_written() {
+        let mut frame = Frame::new();
+        let mut out = Vec::new();
+        frame
+            .update(&mut out, Region::Cell(0, 0), b"a".to_vec())
+            .unwrap();
+        frame.line(&mut out, 5, "help").unwrap();
+        frame.finish(&mut out, (0, 0), true).unwrap();
+        let first = String::from_utf8(out).unwrap();
+        assert!(first.starts_with("\x1b[2J") && first.contains('a') && first.contains("help"));
+
+        let mut out = Vec::new();
+        frame
+            .update(&mut out, Region::Cell(0, 0), b"a".to_vec())
+            .unwrap();
+        frame.line(&mut out, 5, "help").unwrap();
+        frame.finish(&mut out, (0, 0), true).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "\x1b[1;1H");
+
+        let mut out = Vec::new();
+        frame
+            .update(&mut out, Region::Cell(0, 0), b"b".to_vec())
+            .unwrap();
+        frame.finish(&mut out, (2, 1), false).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        // The help line was not written this frame, so it is blanked
+        assert_eq!(text, "b\x1b[6;1H\x1b[K\x1b[?12l\x1b[2;3H");
+
+        frame.invalidate();
+        let mut out = Vec::new();
+        frame
+            .update(&mut out, Region::Cell(0, 0), b"b".to_vec())
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "\x1b[2Jb");
+    }
+
+    #[test]
+    fn test_help_line() {
+        let help = help_line(&[("esc", "home"), ("v", "view")], " [notes]");
+        assert_eq!(
+            help,
+            "\x1b[1mesc\x1b[0m: home | \x1b[1mv\x1b[0m: view\x1b[1m [notes]\x1b[0m"
+        );
+        assert_eq!(
+            help_line(&[("esc", "leave")], ""),
+            "\x1b[1mesc\x1b[0m: leave"
+        );
+    }
+}
@@ -0,0 +1,199 @@
+//! Variant rules
+//!
+//! Standard Kakuro fills cells with the digits 1 to 9. Variants narrow the
+//! range, such as 1 to 6 for mini Kakuro, or open it to 0 to 9, and some
+//! sources let digits repeat within a run. The rules travel with the board
+//! and are stored in both file formats.
+//!
+//! Each clue also has an [`Operation`]: its digits add up to the clue in
+//! ordinary Kakuro and multiply to it in Cross Products puzzles.
+
+use serde::{Deserialize, Serialize};
+use std::{fmt, ops::RangeInclusive, str::FromStr};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+/// Rules a board is played by
+pub struct Rules {
+    /// Smallest digit, 0 or 1
+    pub min: u8,
+    /// Largest digit, at most 9
+    pub max: u8,
+    /// Whether a digit may appear more than once in a run
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub repeats: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+impl Rules {
+    /// Digits 1 to 9
+    pub const STANDARD: Rules = Rules {
+        min: 1,
+        max: 9,
+        repeats: false,
+    };
+
+    /// Creates rules for the digits `min` to `max`, which must be a range
+    /// of at least two digits starting at 0 or 1 and ending at most at 9,
+    /// with no repeats
+    pub fn new(min: u8, max: u8) -> Result<Self, String> {
+        if min > 1 || max > 9 || min >= max {
+            return Err(format!("digits {}-{} are not supported", min, max));
+        }
+        Ok(Self {
+            min,
+            max,
+            repeats: false,
+        })
+    }
+
+    /// Returns whether these are the standard rules
+    pub fn is_standard(&self) -> bool {
+        *self == Self::STANDARD
+    }
+
+    /// Returns the digits cells may hold
+    pub fn digits(&self) -> RangeInclusive<u8> {
+        self.min..=self.max
+    }
+
+    /// Returns whether a cell may hold `d`
+    pub fn contains(&self, d: u8) -> bool {
+        self.digits().contains(&d)
+    }
+
+    /// Returns the digits as a bit set, bit `d` marking digit `d`
+    pub fn mask(&self) -> u16 {
+        self.digits().fold(0, |mask, d| mask | 1 << d)
+    }
+
+    /// Returns whether these rules allow the standard digits 1 to 9
+    pub fn has_standard_digits(&self) -> bool {
+        (self.min, self.max) == (Self::STANDARD.min, Self::STANDARD.max)
+    }
+
+    /// Returns the longest run that can hold distinct digits
+    pub fn max_run(&self) -> usize {
+        self.digits().count()
+    }
+}
+
+impl fmt::Display for Rules {
+    /// Writes the digit range, such as "1-6", leaving out repeats
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.min, self.max)
+    }
+}
+
+impl FromStr for Rules {
+    type Err = String;
+
+    /// Reads a digit range such as "1-6" or "0-9"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad = || format!("invalid digit range '{}', expected e.g. 1-6", s);
+        let (min, max) = s.trim().split_once('-').ok_or_else(bad)?;
+        let min = min.trim().parse().map_err(|_| bad())?;
+        let max = max.trim().parse().map_err(|_| bad())?;
+        Self::new(min, max)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// How a clue combines the digits of its runs
+pub enum Operation {
+    /// The digits add up to the clue
+    #[default]
+    Sum,
+    /// The digits multiply to the clue
+    Product,
+}
+
+impl Operation {
+    /// Returns whether this is the ordinary sum
+    pub fn is_sum(&self) -> bool {
+        *self == Operation::Sum
+    }
+
+    /// Returns the other operation, for switching a clue between them
+    pub fn toggled(self) -> Self {
+        match self {
+            Operation::Sum => Operation::Product,
+            Operation::Product => Operation::Sum,
+        }
+    }
+
+    /// Combines digits: their sum, or their product. Returns None if the
+    /// result does not fit, which no clue can match.
+    pub fn apply(self, digits: impl IntoIterator<Item = u8>) -> Option<u32> {
+        let mut digits = digits.into_iter().map(u32::from);
+        match self {
+            Operation::Sum => digits.try_fold(0u32, u32::checked_add),
+            Operation::Product => digits.try_fold(1u32, u32::checked_mul),
+        }
+    }
+
+    /// Combines the digits of a bit set, bit `d` marking digit `d`
+    pub fn apply_set(self, set: u16) -> Option<u32> {
+        self.apply((0..16).filter(|d| set & (1 << d) != 0))
+    }
+
+    /// Returns the character separating down and across clues: `\` for
+    /// sums and `×` for products
+    pub fn symbol(self) -> char {
+        match self {
+            Operation::Sum => '\\',
+            Operation::Product => '×',
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Operation::Sum => "sum",
+            Operation::Product => "product",
+        };
+        f.write_str(name)
+    }
+}
+
+#[cfg(test)]
+mod rules_tests {
+    use super::*;
+
+    #[test]
+    fn test_ranges() {
+        assert_eq!(Rules::default().mask(), 0b11_1111_1110);
+        let mini: Rules = "1-6".parse().unwrap();
+        assert_eq!((mini.mask(), mini.max_run()), (0b111_1110, 6));
+        assert!(!mini.contains(7) && !mini.is_standard());
+        let zero: Rules = "0-9".parse().unwrap();
+        assert!(zero.contains(0) && zero.max_run() == 10);
+        assert_eq!(zero.to_string(), "0-9");
+        assert!("2-9".parse::<Rules>().is_err());
+        assert!("1-10".parse::<Rules>().is_err());
+        assert!("6".parse::<Rules>().is_err());
+        let repeating = Rules {
+            repeats: true,
+            ..Rules::STANDARD
+        };
+        assert!(!repeating.is_standard() && repeating.has_standard_digits());
+    }
+
+    #[test]
+    fn test_operations() {
+        assert_eq!(Operation::Sum.apply([2, 3, 4]), Some(9));
+        assert_eq!(Operation::Product.apply([2, 3, 4]), Some(24));
+        assert_eq!(Operation::Product.apply_set(0b1_0000_0110), Some(16));
+        assert_eq!(Operation::Product.apply([9; 11]), None);
+        assert_eq!(Operation::Sum.toggled(), Operation::Product);
+        assert_eq!(Operation::Product.symbol(), '×');
+        assert_eq!(Operation::Product.to_string(), "product");
+    }
+}
@@ -0,0 +1,428 @@
+//! Share codes
+//!
+//! A share code packs a board into a short URL-safe string that can be
+//! pasted into a chat: its size, rules, blocks, playable cells, clue sums
+//! and cages, and optionally the digits filled in so far. Numbers are
+//! written as variable-length bytes, the bytes end with a CRC-16 checksum
+//! so a mistyped code is caught rather than read as another puzzle, and the
+//! whole is written in the URL-safe base64 alphabet without padding.
+
+use crate::{
+    Board, Cell, Result,
+    cage::Cage,
+    rules::{Operation, Rules},
+    term,
+};
+use crossterm::{
+    cursor::MoveTo,
+    event::Event,
+    execute,
+    style::{Print, Stylize},
+    terminal,
+};
+use std::{fmt, io::stdout};
+
+/// Layout version written first, so later versions can still read old codes
+const VERSION: u8 = 1;
+
+/// The URL-safe base64 alphabet
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Cell tags: a blank block
+const BLOCK: u8 = 0;
+/// Cell tags: an empty playable cell
+const EMPTY: u8 = 1;
+/// Cell tags: a filled playable cell, followed by its digit
+const FILLED: u8 = 2;
+/// Cell tags: a clue, with the flags below and then its sums
+const CLUE: u8 = 0x10;
+/// Clue flag: has an across sum
+const ACROSS: u8 = 1;
+/// Clue flag: has a down sum
+const DOWN: u8 = 2;
+/// Clue or cage flag: digits multiply to the sum
+const PRODUCT: u8 = 4;
+/// Cage flag: has a sum
+const SUMMED: u8 = 1;
+/// Cage or rules flag: digits may repeat
+const REPEATS: u8 = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Error raised when a share code cannot be read
+pub enum ShareError {
+    /// A character outside the code alphabet
+    Character {
+        /// 1-based position in the code
+        position: usize,
+        /// The character found
+        found: char,
+    },
+    /// The checksum does not match, so the code was mistyped
+    Checksum,
+    /// The checksum matches but the contents do not make a board
+    Invalid(String),
+}
+
+impl fmt::Display for ShareError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShareError::Character { position, found } => write!(
+                f,
+                "character {} ('{}') cannot appear in a share code",
+                position, found
+            ),
+            ShareError::Checksum => {
+                write!(f, "the code's checksum does not match, check for typos")
+            }
+            ShareError::Invalid(message) => write!(f, "invalid share code: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ShareError {}
+
+/// Returns the CRC-16/CCITT checksum of some bytes
+fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc = 0xffffu16;
+    for &byte in bytes {
+        crc ^= u16::from(byte) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                crc << 1 ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Writes bytes in the URL-safe base64 alphabet, without padding
+fn to_base64(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let word = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |word, (i, &b)| word | u32::from(b) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(ALPHABET[(word >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    out
+}
+
+/// Reads bytes written by `to_base64`, which leaves no stray bits
+fn from_base64(code: &str) -> std::result::Result<Vec<u8>, ShareError> {
+    let mut bytes = Vec::new();
+    let (mut word, mut bits) = (0u32, 0);
+    for (i, ch) in code.chars().enumerate() {
+        let index = ALPHABET.iter().position(|&a| char::from(a) == ch);
+        let value = index.ok_or(ShareError::Character {
+            position: i + 1,
+            found: ch,
+        })?;
+        word = word << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((word >> bits) as u8);
+            word &= (1 << bits) - 1;
+        }
+    }
+    // Left over bits are always zero, so a typo in them is still caught
+    if bits >= 6 || word != 0 {
+        return Err(ShareError::Checksum);
+    }
+    Ok(bytes)
+}
+
+/// Appends a number as variable-length bytes, seven bits at a time
+fn push_number(out: &mut Vec<u8>, mut n: u32) {
+    while n >= 0x80 {
+        out.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+/// Returns the share code of a board, with the digits filled in so far if
+/// `progress` is set
+pub fn encode(board: &Board, progress: bool) -> String {
+    let rules = board.rules();
+    let flags = if rules.repeats { REPEATS } else { 0 };
+    let mut out = vec![VERSION];
+    push_number(&mut out, board.rows() as u32);
+    push_number(&mut out, board.cols() as u32);
+    out.extend([rules.min, rules.max, flags]);
+    for r in 0..board.rows() {
+        for c in 0..board.cols() {
+            let cell = board.cell(r, c);
+            let (across, down) = (cell.across_sum(), cell.down_sum());
+            match (cell.is_clue(), cell.value()) {
+                (true, _) if across.is_none() && down.is_none() => out.push(BLOCK),
+                (true, _) => {
+                    let mut tag = CLUE;
+                    tag |= if across.is_some() { ACROSS } else { 0 };
+                    tag |= if down.is_some() { DOWN } else { 0 };
+                    tag |= if cell.operation().is_sum() {
+                        0
+                    } else {
+                        PRODUCT
+                    };
+                    out.push(tag);
+                    across
+                        .into_iter()
+                        .chain(down)
+                        .for_each(|sum| push_number(&mut out, sum));
+                }
+                (false, Some(d)) if progress => out.extend([FILLED, d]),
+                (false, _) => out.push(EMPTY),
+            }
+        }
+    }
+    push_number(&mut out, board.cages().len() as u32);
+    for cage in board.cages() {
+        let mut flags = if cage.sum.is_some() { SUMMED } else { 0 };
+        flags |= if cage.operation.is_sum() { 0 } else { PRODUCT };
+        flags |= if cage.repeats { REPEATS } else { 0 };
+        out.push(flags);
+        if let Some(sum) = cage.sum {
+            push_number(&mut out, sum);
+        }
+        push_number(&mut out, cage.cells.len() as u32);
+        for &(r, c) in &cage.cells {
+            push_number(&mut out, (r * board.cols() + c) as u32);
+        }
+    }
+    out.extend(crc16(&out).to_be_bytes());
+    to_base64(&out)
+}
+
+/// Bytes of a code being read, with the position reached
+struct Reader<'a> {
+    /// Bytes left after the checksum was taken off
+    bytes: &'a [u8],
+    /// Index of the next byte
+    at: usize,
+}
+
+impl Reader<'_> {
+    /// Reads one byte
+    fn byte(&mut self) -> std::result::Result<u8, ShareError> {
+        let byte = self.bytes.get(self.at).copied();
+        self.at += 1;
+        byte.ok_or_else(|| ShareError::Invalid("the code is cut short".to_string()))
+    }
+
+    /// Reads a number written by `push_number`
+    fn number(&mut self) -> std::result::Result<u32, ShareError> {
+        let mut n = 0u32;
+        for shift in (0..32).step_by(7) {
+            let byte = self.byte()?;
+            n |= u32::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(ShareError::Invalid("a number is too large".to_string()))
+    }
+
+    /// Reads a number that must be below `limit`
+    fn below(&mut self, limit: usize, what: &str) -> std::result::Result<usize, ShareError> {
+        let n = self.number()? as usize;
+        if n < limit {
+            Ok(n)
+        } else {
+            Err(ShareError::Invalid(format!(
+                "{} {} is out of range",
+                what, n
+            )))
+        }
+    }
+}
+
+/// Reads a board from its share code, surrounding spaces ignored
+pub fn decode(code: &str) -> std::result::Result<Board, ShareError> {
+    let bytes = from_base64(code.trim())?;
+    let Some((payload, checksum)) = bytes.split_last_chunk::<2>() else {
+        return Err(ShareError::Checksum);
+    };
+    if crc16(payload) != u16::from_be_bytes(*checksum) {
+        return Err(ShareError::Checksum);
+    }
+    let invalid = |message: String| ShareError::Invalid(message);
+    let mut reader = Reader {
+        bytes: payload,
+        at: 0,
+    };
+    let version = reader.byte()?;
+    if version != VERSION {
+        return Err(invalid(format!("version {} is not supported", version)));
+    }
+    let rows = reader.below(256, "row count")?;
+    let cols = reader.below(256, "column count")?;
+    if rows == 0 || cols == 0 {
+        return Err(invalid("the board has no cells".to_string()));
+    }
+    let (min, max, flags) = (reader.byte()?, reader.byte()?, reader.byte()?);
+    let rules = Rules {
+        repeats: flags & REPEATS != 0,
+        ..Rules::new(min, max).map_err(invalid)?
+    };
+    let mut grid = Vec::new();
+    for _ in 0..rows {
+        let mut row = Vec::new();
+        for _ in 0..cols {
+            let tag = reader.byte()?;
+            row.push(match tag {
+                BLOCK => Cell::new_clue(None, None),
+                EMPTY => Cell::new_play(),
+                FILLED => {
+                    let d = reader.byte()?;
+                    if !rules.contains(d) {
+                        return Err(invalid(format!("digit {} is outside {}", d, rules)));
+                    }
+                    let mut cell = Cell::new_play();
+                    cell.set_value(Some(d));
+                    cell
+                }
+                _ if tag & !(ACROSS | DOWN | PRODUCT) == CLUE => {
+                    let across = (tag & ACROSS != 0).then(|| reader.number()).transpose()?;
+                    let down = (tag & DOWN != 0).then(|| reader.number()).transpose()?;
+                    let mut cell = Cell::new_clue(across, down);
+                    if tag & PRODUCT != 0 {
+                        cell.set_operation(Operation::Product);
+                    }
+                    cell
+                }
+                _ => return Err(invalid(format!("unknown cell tag {}", tag))),
+            });
+        }
+        grid.push(row);
+    }
+    let mut cages = Vec::new();
+    for _ in 0..reader.number()? {
+        let flags = reader.byte()?;
+        let sum = (flags & SUMMED != 0).then(|| reader.number()).transpose()?;
+        let len = reader.below(rows * cols + 1, "cage size")?;
+        let mut cells = Vec::new();
+        for _ in 0..len {
+            let i = reader.below(rows * cols, "cage cell")?;
+            cells.push((i / cols, i % cols));
+        }
+        cages.push(Cage {
+            cells,
+            sum,
+            operation: if flags & PRODUCT != 0 {
+                Operation::Product
+            } else {
+                Operation::Sum
+            },
+            repeats: flags & REPEATS != 0,
+        });
+    }
+    if reader.at != payload.len() {
+        return Err(invalid("unexpected bytes after the board".to_string()));
+    }
+    let mut board = Board::new(grid, rows, cols);
+    board.set_rules(rules);
+    board.set_cages(cages);
+    // As with imports, a board that reads is known to be playable
+    if let Some(error) = board.structure_errors().first() {
+        return Err(invalid(error.to_string()));
+    }
+    Ok(board)
+}
+
+/// Shows the board's share codes, with and without the digits filled in so
+/// far, until a key is pressed
+pub fn show(board: &Board) -> Result<()> {
+    let _guard = term::Guard::new()?;
+    let mut stdout = stdout();
+    let (width, _) = terminal::size().unwrap_or((80, 24));
+    let mut lines = vec!["Puzzle:".to_string()];
+    let wrap = |code: String| -> Vec<String> {
+        let chars: Vec<char> = code.chars().collect();
+        let width = usize::from(width.max(1));
+        chars.chunks(width).map(|c| c.iter().collect()).collect()
+    };
+    lines.extend(wrap(encode(board, false)));
+    lines.push(String::new());
+    lines.push("With your progress:".to_string());
+    lines.extend(wrap(encode(board, true)));
+    execute!(
+        stdout,
+        terminal::Clear(terminal::ClearType::All),
+        MoveTo(0, 0),
+        Print("Share code".dark_magenta()),
+    )?;
+    for (i, line) in lines.iter().enumerate() {
+        execute!(stdout, MoveTo(0, i as u16 + 2), Print(line))?;
+    }
+    execute!(
+        stdout,
+        MoveTo(0, lines.len() as u16 + 3),
+        Print("Open it with kakuro play --code CODE. Press any key to go back")
+    )?;
+    while !matches!(term::read()?, Event::Key(_)) {}
+    Ok(())
+}
+
+#[cfg(test)]
+mod share_tests {
+    use super::*;
+    use crate::format;
+
+    #[test]
+    fn test_base64_and_checksum() {
+        for bytes in [&b""[..], b"f", b"fo", b"foo", b"foob", b"\xff\x00\x80"] {
+            assert_eq!(from_base64(&to_base64(bytes)).unwrap(), bytes);
+        }
+        assert_eq!(to_base64(b"foob"), "Zm9vYg");
+        assert_eq!(crc16(b"123456789"), 0x29b1);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let text =
+            "digits: 1-4\nrepeats: yes\ncage: *6 repeats 1,1 1,2\n\\   4\\ 6*\n\\3 1 _\n\\7 _ _\n";
+        let board = format::parse(text).unwrap();
+        let code = encode(&board, true);
+        assert!(
+            code.chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_".contains(c))
+        );
+        let back = decode(&code).unwrap();
+        assert_eq!(format::to_text(&back), format::to_text(&board));
+        let bare = decode(&encode(&board, false)).unwrap();
+        assert_eq!(bare.cell(1, 1).value(), None);
+        assert_eq!(decode(&format!("  {}\n", code)).unwrap().rows(), 3);
+        let impossible = format::parse("\\ 30\\\n\\ _\n\\ _\n").unwrap();
+        assert!(matches!(
+            decode(&encode(&impossible, false)),
+            Err(ShareError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_typos_are_caught() {
+        let board = format::parse("\\ 16\\ 17\\\n\\16 _ _\n\\17 _ _\n").unwrap();
+        let code = encode(&board, false);
+        for i in 0..code.len() {
+            let mut typo: Vec<char> = code.chars().collect();
+            typo[i] = if typo[i] == 'A' { 'B' } else { 'A' };
+            let typo: String = typo.into_iter().collect();
+            assert!(decode(&typo).is_err(), "typo at {} read as a board", i);
+        }
+        assert_eq!(decode(&code[1..]).err(), Some(ShareError::Checksum));
+        assert_eq!(
+            decode("AB!").err(),
+            Some(ShareError::Character {
+                position: 3,
+                found: '!'
+            })
+        );
+        assert_eq!(decode("").err(), Some(ShareError::Checksum));
+    }
+}
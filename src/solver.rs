@@ -0,0 +1,927 @@
+//! Backtracking solver for Kakuro boards
+//!
+//! Candidates are kept as bit sets (bit `d` for digit `d`) and narrowed with
+//! the digit combinations each run's sum allows before any guess is made.
+//! Only the digits the board's rules allow are considered, and product clues
+//! are narrowed the same way with the sets of digits multiplying to them.
+//! When the rules let digits repeat, runs are narrowed by the values their
+//! cells can still reach instead. Cages are solved as constraints alongside
+//! the runs.
+
+use crate::{
+    Board,
+    cage::Constraint,
+    rules::{Operation, Rules},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+    str::FromStr,
+    sync::OnceLock,
+};
+
+/// Returns the sets of digits 0-9 of each size and sum, indexed
+/// `[size][sum]`
+fn combinations() -> &'static Vec<Vec<Vec<u16>>> {
+    static TABLE: OnceLock<Vec<Vec<Vec<u16>>>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = vec![vec![Vec::new(); 46]; 11];
+        for mask in 0..1024u16 {
+            let sum: usize = (0..=9).filter(|d| mask & (1 << d) != 0).sum();
+            table[mask.count_ones() as usize][sum].push(mask);
+        }
+        table
+    })
+}
+
+/// Returns the sets of digits 0-9 of each size and product, keyed by
+/// `(size, product)`
+fn products() -> &'static HashMap<(usize, u32), Vec<u16>> {
+    static TABLE: OnceLock<HashMap<(usize, u32), Vec<u16>>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table: HashMap<(usize, u32), Vec<u16>> = HashMap::new();
+        for mask in 0..1024u16 {
+            let key = (
+                mask.count_ones() as usize,
+                Operation::Product
+                    .apply_set(mask)
+                    .expect("digits 0-9 multiply within range"),
+            );
+            table.entry(key).or_default().push(mask);
+        }
+        table
+    })
+}
+
+/// Returns the sets of `size` distinct digits from the `digits` bit set
+/// that combine to `target` under `operation`
+pub fn combos_of(
+    operation: Operation,
+    size: usize,
+    target: u32,
+    digits: u16,
+) -> impl Iterator<Item = u16> {
+    let sets = match operation {
+        Operation::Sum => combinations()
+            .get(size)
+            .and_then(|by_sum| by_sum.get(target as usize)),
+        Operation::Product => products().get(&(size, target)),
+    };
+    sets.map_or(&[][..], |v| v.as_slice())
+        .iter()
+        .copied()
+        .filter(move |&set| set & !digits == 0)
+}
+
+/// Returns every set of `size` distinct digits from the `digits` bit set
+fn sets_of(size: usize, digits: u16) -> impl Iterator<Item = u16> {
+    let by_sum = combinations().get(size).map_or(&[][..], |v| v.as_slice());
+    by_sum
+        .iter()
+        .flatten()
+        .copied()
+        .filter(move |&set| set & !digits == 0)
+}
+
+/// Returns the sets of `size` distinct digits from the `digits` bit set
+/// adding up to `sum`
+pub fn combos_in(size: usize, sum: u32, digits: u16) -> impl Iterator<Item = u16> {
+    combos_of(Operation::Sum, size, sum, digits)
+}
+
+/// Returns the sets of `size` distinct digits 1-9 adding up to `sum`
+pub fn combos(size: usize, sum: u32) -> Vec<u16> {
+    combos_in(size, sum, Rules::STANDARD.mask()).collect()
+}
+
+/// Combines a partial value with a digit or another partial value, None if
+/// the run could then no longer reach `target` or the value overflows.
+/// Products aiming at 0 only track whether a 0 has been seen.
+fn join(operation: Operation, target: u32, value: u32, other: u32) -> Option<u32> {
+    match operation {
+        Operation::Sum => value.checked_add(other).filter(|&v| v <= target),
+        Operation::Product if target == 0 => value.checked_mul(other).map(|v| v.min(1)),
+        Operation::Product => value
+            .checked_mul(other)
+            .filter(|&v| v != 0 && target.is_multiple_of(v)),
+    }
+}
+
+/// Returns the digits each cell can take when every cell holds one of its
+/// candidates, repeats allowed, and together they combine to `target`
+pub fn repeat_support(operation: Operation, target: u32, masks: &[u16]) -> Vec<u16> {
+    let n = masks.len();
+    let start = match operation {
+        Operation::Sum => 0,
+        Operation::Product => 1,
+    };
+    let digits = |m: u16| (0..16u32).filter(move |d| m & (1 << d) != 0);
+    // before[j] holds the values cells ..j reach, after[j] those of cells j..
+    let mut before = vec![BTreeSet::from([start]); n + 1];
+    let mut after = before.clone();
+    for j in 0..n {
+        before[j + 1] = before[j]
+            .iter()
+            .flat_map(|&v| digits(masks[j]).filter_map(move |d| join(operation, target, v, d)))
+            .collect();
+    }
+    for j in (0..n).rev() {
+        after[j] = after[j + 1]
+            .iter()
+            .flat_map(|&v| digits(masks[j]).filter_map(move |d| join(operation, target, d, v)))
+            .collect();
+    }
+    (0..n)
+        .map(|j| {
+            digits(masks[j])
+                .filter(|&d| {
+                    before[j].iter().any(|&a| {
+                        join(operation, target, a, d).is_some_and(|v| {
+                            after[j + 1]
+                                .iter()
+                                .any(|&b| join(operation, target, v, b) == Some(target))
+                        })
+                    })
+                })
+                .fold(0, |m, d| m | 1 << d)
+        })
+        .collect()
+}
+
+/// Returns whether `size` digits allowed by the rules can combine to
+/// `target`, distinct unless the rules allow repeats
+pub fn can_fill(rules: Rules, operation: Operation, size: usize, target: u32) -> bool {
+    if rules.repeats {
+        let support = repeat_support(operation, target, &vec![rules.mask(); size]);
+        support.iter().all(|&m| m != 0)
+    } else {
+        combos_of(operation, size, target, rules.mask())
+            .next()
+            .is_some()
+    }
+}
+
+/// Adds to `support` the digits each cell can take when the cells are
+/// filled with exactly the digits of `set`, one each
+fn supported_digits(set: u16, masks: &[u16], support: &mut [u16]) {
+    let n = masks.len();
+    let has = |bits: &[u64; 16], used: u16| bits[used as usize / 64] & (1 << (used % 64)) != 0;
+    let mark = |bits: &mut [u64; 16], used: u16| bits[used as usize / 64] |= 1 << (used % 64);
+    // after[j] marks the digit sets cells j.. can be filled with
+    let mut after = [[0u64; 16]; 11];
+    let mut level = vec![0u16];
+    mark(&mut after[n], 0);
+    for j in (0..n).rev() {
+        let mut next = Vec::new();
+        for &used in &level {
+            let mut free = masks[j] & set & !used;
+            while free != 0 {
+                let d = free & free.wrapping_neg();
+                if !has(&after[j], used | d) {
+                    mark(&mut after[j], used | d);
+                    next.push(used | d);
+                }
+                free &= free - 1;
+            }
+        }
+        level = next;
+    }
+    if !has(&after[0], set) {
+        return;
+    }
+    // Walk forwards through the digit sets cells ..j can be filled with
+    let mut before = vec![0u16];
+    for j in 0..n {
+        let mut next = Vec::new();
+        let mut seen = [0u64; 16];
+        for &used in &before {
+            let mut free = masks[j] & set & !used;
+            while free != 0 {
+                let d = free & free.wrapping_neg();
+                if has(&after[j + 1], set & !used & !d) {
+                    support[j] |= d;
+                    if !has(&seen, used | d) {
+                        mark(&mut seen, used | d);
+                        next.push(used | d);
+                    }
+                }
+                free &= free - 1;
+            }
+        }
+        before = next;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// How hard a puzzle is to solve
+pub enum Difficulty {
+    /// Solved by run combinations alone
+    Easy,
+    /// Needs a couple of guesses
+    Medium,
+    /// Needs several guesses
+    Hard,
+    /// Needs extensive searching
+    Expert,
+}
+
+impl Difficulty {
+    /// Maps the number of guesses made during search to a difficulty
+    fn from_guesses(guesses: usize) -> Self {
+        match guesses {
+            0 => Difficulty::Easy,
+            1..=2 => Difficulty::Medium,
+            3..=10 => Difficulty::Hard,
+            _ => Difficulty::Expert,
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+            Difficulty::Expert => "expert",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "easy" => Ok(Difficulty::Easy),
+            "medium" => Ok(Difficulty::Medium),
+            "hard" => Ok(Difficulty::Hard),
+            "expert" => Ok(Difficulty::Expert),
+            _ => Err(format!("unknown difficulty '{}'", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Result of rating a puzzle
+pub struct Rating {
+    /// Difficulty bucket
+    pub difficulty: Difficulty,
+    /// Guesses made while proving the solution unique
+    pub guesses: usize,
+    /// Whether the puzzle has exactly one solution
+    pub unique: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Result of counting a puzzle's solutions
+pub struct SolutionCount {
+    /// Solutions found, at most the cap
+    pub count: usize,
+    /// Whether counting stopped at the cap, so there may be more
+    pub capped: bool,
+    /// Cells whose digits differ between the first two solutions found
+    pub ambiguous: Vec<(usize, usize)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// One logical deduction: the digit combinations a run or cage allows
+/// ruling out candidates in its cells
+pub struct Step {
+    /// The run or cage whose combinations were applied
+    pub constraint: Constraint,
+    /// Candidates ruled out, as (row, col, digit)
+    pub eliminated: Vec<(usize, usize, u8)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// How a chain of logical deductions ended
+pub enum Outcome {
+    /// Every cell has a single candidate
+    Solved,
+    /// No run or cage rules anything more out, so a guess is needed
+    Stuck,
+    /// A run or cage can no longer be made from its candidates
+    Contradiction(Constraint),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Every deduction the logical solver makes, in order
+pub struct Deductions {
+    /// Candidates before the first step, as bit sets indexed by row and
+    /// column, zero on clue cells
+    pub initial: Vec<Vec<u16>>,
+    /// Deductions in the order they were made
+    pub steps: Vec<Step>,
+    /// Why the deductions stopped
+    pub outcome: Outcome,
+}
+
+/// A run or cage, as indices into the solver's cell list
+struct Group {
+    sum: Option<u32>,
+    operation: Operation,
+    distinct: bool,
+    cells: Vec<usize>,
+}
+
+/// Solutions reached by a search
+struct Found {
+    /// Solutions counted so far
+    count: usize,
+    /// Count at which the search stops
+    limit: usize,
+    /// Candidates of the first solutions
+    kept: Vec<Vec<u16>>,
+    /// How many solutions to keep
+    keep: usize,
+}
+
+impl Found {
+    /// Creates an empty tally
+    fn new(limit: usize, keep: usize) -> Self {
+        Self {
+            count: 0,
+            limit,
+            kept: Vec::new(),
+            keep,
+        }
+    }
+
+    /// Returns whether the search should stop
+    fn full(&self) -> bool {
+        self.count >= self.limit
+    }
+}
+
+/// Kakuro solver for a single board
+pub struct Solver {
+    /// Board being solved
+    board: Board,
+    /// Coordinates of the playable cells
+    cells: Vec<(usize, usize)>,
+    /// Clued runs, then cages
+    groups: Vec<Group>,
+    /// Digits every cell starts with, from the board's rules
+    digits: u16,
+    /// Runs and cages each cell belongs to
+    groups_of: Vec<Vec<usize>>,
+    /// Branch points visited so far
+    guesses: usize,
+    /// Branch points allowed per search
+    budget: Option<usize>,
+    /// Whether the last search ran out of budget
+    exhausted: bool,
+}
+
+impl Solver {
+    /// Prepares a solver, keeping any values already on the board as givens
+    pub fn new(board: &Board) -> Self {
+        let mut index = vec![vec![None; board.cols()]; board.rows()];
+        let mut cells = Vec::new();
+        for (r, row) in index.iter_mut().enumerate() {
+            for (c, slot) in row.iter_mut().enumerate() {
+                if !board.cell(r, c).is_clue() {
+                    *slot = Some(cells.len());
+                    cells.push((r, c));
+                }
+            }
+        }
+        let mut groups_of = vec![Vec::new(); cells.len()];
+        let groups = board
+            .constraints()
+            .into_iter()
+            .enumerate()
+            .map(|(i, constraint)| {
+                let cells: Vec<usize> = (constraint.cells.iter())
+                    .filter_map(|&(r, c)| *index.get(r)?.get(c)?)
+                    .collect();
+                for &cell in &cells {
+                    groups_of[cell].push(i);
+                }
+                Group {
+                    sum: constraint.sum,
+                    operation: constraint.operation,
+                    distinct: constraint.distinct,
+                    cells,
+                }
+            })
+            .collect();
+        Self {
+            board: board.clone(),
+            cells,
+            groups,
+            groups_of,
+            digits: board.rules().mask(),
+            guesses: 0,
+            budget: None,
+            exhausted: false,
+        }
+    }
+
+    /// Returns the number of branch points visited by the last search
+    pub fn guesses(&self) -> usize {
+        self.guesses
+    }
+
+    /// Limits how many branch points a search may visit
+    pub fn set_budget(&mut self, guesses: usize) {
+        self.budget = Some(guesses);
+    }
+
+    /// Returns whether the last search stopped early because it ran out of
+    /// budget, in which case its solutions may be incomplete
+    pub fn exhausted(&self) -> bool {
+        self.exhausted
+    }
+
+    /// Returns the starting candidates of every cell
+    fn initial(&self) -> Vec<u16> {
+        self.cells
+            .iter()
+            .map(|&(r, c)| match self.board.cell(r, c).value() {
+                Some(v) if v < 16 => (1 << v) & self.digits,
+                Some(_) => 0,
+                None => self.digits,
+            })
+            .collect()
+    }
+
+    /// Narrows one run or cage, returning false on a contradiction
+    fn narrow(&self, run: &Group, cands: &mut [u16], changed: &mut Vec<usize>) -> bool {
+        if !run.distinct {
+            let Some(sum) = run.sum else {
+                return true;
+            };
+            let masks: Vec<u16> = run.cells.iter().map(|&i| cands[i]).collect();
+            let support = repeat_support(run.operation, sum, &masks);
+            for (&i, &m) in run.cells.iter().zip(&support) {
+                if m == 0 {
+                    return false;
+                }
+                if m != cands[i] {
+                    cands[i] = m;
+                    changed.push(i);
+                }
+            }
+            return true;
+        }
+        let mut fixed = 0u16;
+        let mut open = 0u16;
+        let mut open_count = 0;
+        for &i in &run.cells {
+            let m = cands[i];
+            if m.count_ones() == 1 {
+                if fixed & m != 0 {
+                    return false;
+                }
+                fixed |= m;
+            } else {
+                open |= m;
+                open_count += 1;
+            }
+        }
+        if open_count == 0 {
+            return run
+                .sum
+                .is_none_or(|sum| run.operation.apply_set(fixed) == Some(sum));
+        }
+        let open_cells: Vec<usize> = run
+            .cells
+            .iter()
+            .copied()
+            .filter(|&i| cands[i].count_ones() > 1)
+            .collect();
+        let masks: Vec<u16> = open_cells.iter().map(|&i| cands[i] & !fixed).collect();
+        let mut support = vec![0u16; open_count];
+        // Whole-run sets holding the fixed digits, the rest from open cells
+        let mut consider = |set: u16| {
+            let rest = set & !fixed;
+            if set & fixed == fixed && rest & !open == 0 {
+                supported_digits(rest, &masks, &mut support);
+            }
+        };
+        match run.sum {
+            Some(sum) => {
+                combos_of(run.operation, run.cells.len(), sum, self.digits).for_each(&mut consider)
+            }
+            None => sets_of(run.cells.len(), self.digits).for_each(&mut consider),
+        }
+        for (&i, &m) in open_cells.iter().zip(&support) {
+            if m == 0 {
+                return false;
+            }
+            if m != cands[i] {
+                cands[i] = m;
+                changed.push(i);
+            }
+        }
+        true
+    }
+
+    /// Narrows candidates until nothing changes, returning false on a
+    /// contradiction
+    fn propagate(&self, cands: &mut [u16]) -> bool {
+        if cands.contains(&0) {
+            return false;
+        }
+        let mut queued = vec![true; self.groups.len()];
+        let mut queue: Vec<usize> = (0..self.groups.len()).collect();
+        let mut changed = Vec::new();
+        while let Some(run) = queue.pop() {
+            queued[run] = false;
+            if !self.narrow(&self.groups[run], cands, &mut changed) {
+                return false;
+            }
+            for cell in changed.drain(..) {
+                for &other in &self.groups_of[cell] {
+                    if !queued[other] {
+                        queued[other] = true;
+                        queue.push(other);
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// Depth first search counting solutions until `found` is full
+    fn search(&mut self, mut cands: Vec<u16>, found: &mut Found) {
+        if found.full() || self.exhausted || !self.propagate(&mut cands) {
+            return;
+        }
+        let branch = (0..cands.len())
+            .filter(|&i| cands[i].count_ones() > 1)
+            .min_by_key(|&i| cands[i].count_ones());
+        let Some(i) = branch else {
+            found.count += 1;
+            if found.kept.len() < found.keep {
+                found.kept.push(cands);
+            }
+            return;
+        };
+        if self.budget.is_some_and(|b| self.guesses >= b) {
+            self.exhausted = true;
+            return;
+        }
+        self.guesses += 1;
+        let mask = cands[i];
+        for d in 0..16 {
+            if mask & (1 << d) != 0 {
+                let mut next = cands.clone();
+                next[i] = 1 << d;
+                self.search(next, found);
+                if found.full() || self.exhausted {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Returns candidates as a grid of bit sets, zero on clue cells
+    fn grid(&self, cands: &[u16]) -> Vec<Vec<u16>> {
+        let mut grid = vec![vec![0; self.board.cols()]; self.board.rows()];
+        for (&(r, c), &m) in self.cells.iter().zip(cands) {
+            grid[r][c] = m;
+        }
+        grid
+    }
+
+    /// Applies one run or cage at a time without guessing, always taking the
+    /// first that rules something out, and records each deduction
+    pub fn deductions(&self) -> Deductions {
+        let constraints = self.board.constraints();
+        let mut cands = self.initial();
+        let initial = self.grid(&cands);
+        let mut steps = Vec::new();
+        let outcome = 'search: loop {
+            if let Some(i) = cands.iter().position(|&m| m == 0) {
+                let (r, c) = self.cells[i];
+                let found = constraints.iter().find(|k| k.cells.contains(&(r, c)));
+                let constraint = found.expect("cell has a run or cage").clone();
+                break 'search Outcome::Contradiction(constraint);
+            }
+            for (constraint, group) in constraints.iter().zip(&self.groups) {
+                let mut next = cands.clone();
+                let mut changed = Vec::new();
+                if !self.narrow(group, &mut next, &mut changed) {
+                    break 'search Outcome::Contradiction(constraint.clone());
+                }
+                if changed.is_empty() {
+                    continue;
+                }
+                let mut eliminated = Vec::new();
+                for &i in &group.cells {
+                    let (r, c) = self.cells[i];
+                    let gone = cands[i] & !next[i];
+                    eliminated.extend((0..16).filter(|d| gone & (1 << d) != 0).map(|d| (r, c, d)));
+                }
+                steps.push(Step {
+                    constraint: constraint.clone(),
+                    eliminated,
+                });
+                cands = next;
+                continue 'search;
+            }
+            break if cands.iter().all(|m| m.count_ones() == 1) {
+                Outcome::Solved
+            } else {
+                Outcome::Stuck
+            };
+        };
+        Deductions {
+            initial,
+            steps,
+            outcome,
+        }
+    }
+
+    /// Runs a fresh search
+    fn run(&mut self, found: &mut Found) {
+        self.guesses = 0;
+        self.exhausted = false;
+        if found.limit > 0 {
+            self.search(self.initial(), found);
+        }
+    }
+
+    /// Returns a filled copy of the board for a solution's candidates
+    fn fill(&self, cands: &[u16]) -> Board {
+        let mut board = self.board.clone();
+        for (&(r, c), m) in self.cells.iter().zip(cands) {
+            board
+                .cell_mut(r, c)
+                .set_value(Some(m.trailing_zeros() as u8));
+        }
+        board
+    }
+
+    /// Returns up to `limit` distinct solutions as filled copies of the board
+    pub fn solutions(&mut self, limit: usize) -> Vec<Board> {
+        let mut found = Found::new(limit, limit);
+        self.run(&mut found);
+        found.kept.iter().map(|cands| self.fill(cands)).collect()
+    }
+
+    /// Counts distinct solutions, stopping once `cap` are found, and
+    /// reports the cells the first two solutions disagree on
+    pub fn count(&mut self, cap: usize) -> SolutionCount {
+        let mut found = Found::new(cap, 2);
+        self.run(&mut found);
+        let ambiguous = match found.kept.as_slice() {
+            [first, second] => differences(&self.fill(first), &self.fill(second)),
+            _ => Vec::new(),
+        };
+        SolutionCount {
+            count: found.count,
+            capped: found.full() && cap > 0,
+            ambiguous,
+        }
+    }
+}
+
+/// Returns the playable cells whose values differ between two boards of the
+/// same layout
+pub fn differences(a: &Board, b: &Board) -> Vec<(usize, usize)> {
+    (0..a.rows())
+        .flat_map(|r| (0..a.cols()).map(move |c| (r, c)))
+        .filter(|&(r, c)| !a.cell(r, c).is_clue())
+        .filter(|&(r, c)| a.cell(r, c).value() != b.cell(r, c).value())
+        .collect()
+}
+
+/// Returns a solved copy of the board, if it has a solution
+pub fn solve(board: &Board) -> Option<Board> {
+    Solver::new(board).solutions(1).pop()
+}
+
+/// Counts a puzzle's solutions up to `cap`
+pub fn count_solutions(board: &Board, cap: usize) -> SolutionCount {
+    Solver::new(board).count(cap)
+}
+
+/// Rates a puzzle by the search needed to prove its solution unique, or
+/// returns None if it has no solution
+pub fn rate(board: &Board) -> Option<Rating> {
+    let mut solver = Solver::new(board);
+    let found = solver.solutions(2).len();
+    (found > 0).then(|| Rating {
+        difficulty: Difficulty::from_guesses(solver.guesses()),
+        guesses: solver.guesses(),
+        unique: found == 1,
+    })
+}
+
+#[cfg(test)]
+mod solver_tests {
+    use super::*;
+    use crate::{
+        Direction,
+        cage::{Cage, Source},
+        check::check,
+        format,
+    };
+
+    #[test]
+    fn test_combos() {
+        assert_eq!(combos(2, 3), &[0b110]);
+        assert_eq!(combos(2, 17), &[(1 << 8) | (1 << 9)]);
+        assert_eq!(combos(3, 6).len(), 1);
+        assert!(combos(2, 2).is_empty());
+        assert!(combos(10, 45).is_empty());
+        let digits = Rules::STANDARD.mask();
+        let sets: Vec<u16> = combos_of(Operation::Product, 2, 12, digits).collect();
+        assert_eq!(sets, [0b1_1000, 0b100_0100]);
+        assert_eq!(combos_of(Operation::Product, 2, 17, digits).count(), 0);
+    }
+
+    #[test]
+    fn test_repeat_support() {
+        let all = Rules::STANDARD.mask();
+        assert_eq!(repeat_support(Operation::Sum, 4, &[all, all]), [0b1110; 2]);
+        assert_eq!(
+            repeat_support(Operation::Sum, 4, &[1 << 2, all]),
+            [1 << 2, 1 << 2]
+        );
+        assert_eq!(
+            repeat_support(Operation::Product, 4, &[all, all]),
+            [0b1_0110; 2]
+        );
+        let with_zero = Rules::new(0, 9).unwrap().mask();
+        assert_eq!(
+            repeat_support(Operation::Product, 0, &[1 << 5, with_zero])[1],
+            1
+        );
+        let repeating = Rules {
+            repeats: true,
+            ..Rules::STANDARD
+        };
+        assert!(!can_fill(Rules::STANDARD, Operation::Sum, 2, 2));
+        assert!(can_fill(repeating, Operation::Sum, 2, 2));
+        assert!(!can_fill(repeating, Operation::Sum, 2, 19));
+    }
+
+    #[test]
+    fn test_solve_with_repeats() {
+        let board = format::parse("repeats: yes\n\\ 4\\ 3\\\n\\4 _ _\n\\3 _ _\n").unwrap();
+        let solved = solve(&board).unwrap();
+        assert!(check(&solved).is_solved());
+        assert_eq!(count_solutions(&board, 10).count, 2);
+    }
+
+    #[test]
+    fn test_long_repeating_product() {
+        // 9^10 from twelve cells: partial products reach past u32 on the way
+        let all = Rules::STANDARD.mask();
+        let target = 3_486_784_401;
+        assert_eq!(
+            repeat_support(Operation::Product, target, &[all; 12]),
+            [0b10_0000_1010; 12]
+        );
+        let mut board = format::parse("_ _ _ _\n_ _ _ _\n_ _ _ _\n").unwrap();
+        let cells = (0..3).flat_map(|r| (0..4).map(move |c| (r, c))).collect();
+        let mut cage = Cage::new(cells, Some(target));
+        cage.operation = Operation::Product;
+        cage.repeats = true;
+        board.set_cages(vec![cage]);
+        let solved = solve(&board).unwrap();
+        assert!(check(&solved).is_solved());
+    }
+
+    #[test]
+    fn test_solve_cages() {
+        // A 4x4 Sudoku: rows, columns and boxes are cages of distinct digits
+        let mut board = format::parse("digits: 1-4\n_ _ _ _\n_ _ _ _\n_ _ _ _\n_ _ _ _\n").unwrap();
+        let mut cages = Vec::new();
+        for i in 0..4 {
+            cages.push(Cage::new((0..4).map(|j| (i, j)).collect(), None));
+            cages.push(Cage::new((0..4).map(|j| (j, i)).collect(), None));
+            let (r, c) = (i / 2 * 2, i % 2 * 2);
+            let square = vec![(r, c), (r, c + 1), (r + 1, c), (r + 1, c + 1)];
+            cages.push(Cage::new(square, None));
+        }
+        // Killer cages pin down the solution
+        for (cells, sum) in [
+            (vec![(0, 0)], 1),
+            (vec![(0, 1), (1, 1)], 6),
+            (vec![(0, 2), (0, 3)], 7),
+            (vec![(1, 0), (2, 0)], 5),
+            (vec![(1, 1), (1, 2)], 5),
+            (vec![(1, 3), (2, 3)], 5),
+            (vec![(2, 1), (2, 2)], 5),
+            (vec![(3, 0), (3, 1)], 7),
+            (vec![(3, 2), (3, 3)], 3),
+        ] {
+            cages.push(Cage::new(cells, Some(sum)));
+        }
+        board.set_cages(cages);
+        assert!(board.structure_errors().is_empty());
+        let solved = solve(&board).unwrap();
+        assert!(check(&solved).is_solved());
+        assert!(rate(&board).unwrap().unique);
+        let deductions = Solver::new(&board).deductions();
+        assert!(
+            (deductions.steps.iter()).any(|step| matches!(step.constraint.source, Source::Cage(_)))
+        );
+    }
+
+    #[test]
+    fn test_solve_products() {
+        let board = format::parse("\\ 12* 2*\n*3 _ _\n*8 _ _\n").unwrap();
+        let solved = solve(&board).unwrap();
+        let values: Vec<_> = [(1, 1), (1, 2), (2, 1), (2, 2)]
+            .iter()
+            .map(|&(r, c)| solved.cell(r, c).value().unwrap())
+            .collect();
+        assert_eq!(values, [3, 1, 4, 2]);
+        assert!(rate(&board).unwrap().unique);
+    }
+
+    #[test]
+    fn test_solve_unique() {
+        let board = format::parse("\\ 16\\ 17\\\n\\16 _ _\n\\17 _ _\n").unwrap();
+        let solved = solve(&board).unwrap();
+        assert_eq!(solved.cell(1, 1).value(), Some(7));
+        assert_eq!(solved.cell(1, 2).value(), Some(9));
+        assert_eq!(solved.cell(2, 1).value(), Some(9));
+        assert_eq!(solved.cell(2, 2).value(), Some(8));
+        let mut checked = solved.clone();
+        assert!(checked.check());
+        let rating = rate(&board).unwrap();
+        assert!(rating.unique);
+        assert_eq!(rating.difficulty, Difficulty::Easy);
+    }
+
+    #[test]
+    fn test_unsolvable_and_ambiguous() {
+        let board = format::parse("\\ 3\\ 3\\\n\\4 _ _\n\\2 _ _\n").unwrap();
+        assert!(solve(&board).is_none());
+        assert!(rate(&board).is_none());
+        let board = format::parse("\\ 3\\ 3\\\n\\3 _ _\n\\3 _ _\n").unwrap();
+        assert_eq!(Solver::new(&board).solutions(5).len(), 2);
+        assert!(!rate(&board).unwrap().unique);
+    }
+
+    #[test]
+    fn test_count_solutions() {
+        let board = format::parse("\\ 3\\ 3\\\n\\3 _ _\n\\3 _ _\n").unwrap();
+        let count = count_solutions(&board, 10);
+        assert_eq!((count.count, count.capped), (2, false));
+        assert_eq!(count.ambiguous, vec![(1, 1), (1, 2), (2, 1), (2, 2)]);
+        let count = count_solutions(&board, 1);
+        assert_eq!((count.count, count.capped), (1, true));
+        assert!(count.ambiguous.is_empty());
+        let board = format::parse("\\ 4\\ 13\\ 9\\\n\\13 _ _ _\n\\13 _ _ _\n").unwrap();
+        let count = count_solutions(&board, 100);
+        assert!(count.count > 2 && !count.capped);
+        assert_eq!(count_solutions(&board, 2).ambiguous, count.ambiguous);
+    }
+
+    #[test]
+    fn test_deductions() {
+        let board = format::parse("\\ 16\\ 17\\\n\\16 _ _\n\\17 _ _\n").unwrap();
+        let deductions = Solver::new(&board).deductions();
+        assert_eq!(deductions.outcome, Outcome::Solved);
+        assert_eq!(deductions.initial[1][1], Rules::STANDARD.mask());
+        let first = &deductions.steps[0];
+        assert_eq!(
+            first.constraint.source,
+            Source::Run {
+                row: 0,
+                col: 1,
+                direction: Direction::Down
+            }
+        );
+        assert!(first.eliminated.contains(&(1, 1, 1)));
+        assert!(!first.eliminated.contains(&(1, 1, 7)));
+        let mut cands = deductions.initial.clone();
+        for &(r, c, d) in deductions.steps.iter().flat_map(|s| &s.eliminated) {
+            cands[r][c] &= !(1 << d);
+        }
+        assert_eq!(cands[2][2], 1 << 8);
+
+        let board = format::parse("\\ 3\\ 3\\\n\\3 _ _\n\\3 _ _\n").unwrap();
+        assert_eq!(Solver::new(&board).deductions().outcome, Outcome::Stuck);
+        let board = format::parse("\\ 3\\ 3\\\n\\4 _ _\n\\2 _ _\n").unwrap();
+        let outcome = Solver::new(&board).deductions().outcome;
+        assert!(matches!(outcome, Outcome::Contradiction(_)));
+    }
+
+    #[test]
+    fn test_givens_are_kept() {
+        let board = format::parse("\\ 3\\ 3\\\n\\3 1 _\n\\3 _ _\n").unwrap();
+        let solutions = Solver::new(&board).solutions(5);
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(solutions[0].cell(2, 2).value(), Some(1));
+    }
+
+    #[test]
+    fn test_difficulty_names() {
+        assert_eq!("Hard".parse::<Difficulty>(), Ok(Difficulty::Hard));
+        assert_eq!(Difficulty::Expert.to_string(), "expert");
+        assert!("trivial".parse::<Difficulty>().is_err());
+    }
+}
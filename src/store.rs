@@ -0,0 +1,38 @@
+//! Local JSON files such as stats and progress
+//!
+//! Files live in `.kakuro` in the home directory unless an environment
+//! variable points somewhere else. A file that does not exist yet reads as
+//! the default value.
+
+use serde::{Serialize, de::DeserializeOwned};
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Returns `$<var>` if it is set, or `.kakuro/<file>` in the home directory
+pub(crate) fn path(var: &str, file: &str) -> Option<PathBuf> {
+    if let Some(path) = env::var_os(var) {
+        return Some(PathBuf::from(path));
+    }
+    let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))?;
+    Some(Path::new(&home).join(".kakuro").join(file))
+}
+
+/// Reads a value from a JSON file, or the default if it does not exist
+pub(crate) fn load<T: DeserializeOwned + Default>(path: &Path) -> io::Result<T> {
+    match fs::read_to_string(path) {
+        Ok(text) => serde_json::from_str(&text).map_err(io::Error::other),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Writes a value to a JSON file, creating its directory if needed
+pub(crate) fn save<T: Serialize>(value: &T, path: &Path) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let text = serde_json::to_string_pretty(value).map_err(io::Error::other)?;
+    fs::write(path, text + "\n")
+}
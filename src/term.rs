@@ -0,0 +1,149 @@
+//! Terminal setup and restoration
+//!
+//! A session holds a [`Guard`]: the outermost guard switches to the
+//! alternate screen and enables raw mode, and guards taken by screens opened
+//! inside it change nothing, so raw mode is set up exactly once. Dropping the
+//! outermost guard, including while unwinding from a panic or returning
+//! early with an error, leaves the alternate screen, turns raw mode off and
+//! restores the cursor, so the shell and its scrollback are left as they
+//! were. The first guard also installs a panic hook that restores the
+//! terminal before the panic message is printed, and a handler that does the
+//! same before exiting on SIGINT, SIGTERM or SIGHUP.
+//!
+//! Raw mode delivers Ctrl-C as a key press rather than SIGINT, so screens
+//! read events through [`read`], which turns it into Esc and lets every
+//! screen leave on it the same way.
+
+use crate::error::Result;
+use crossterm::{
+    cursor::{EnableBlinking, SetCursorStyle, Show},
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    execute,
+    style::ResetColor,
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use signal_hook::{
+    consts::{SIGHUP, SIGINT, SIGTERM},
+    iterator::Signals,
+};
+use std::{
+    io::stdout,
+    panic, process,
+    sync::{
+        Once,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+};
+
+/// Ensures the panic hook and signal handler are installed once
+static HOOK: Once = Once::new();
+
+/// Number of live guards
+static DEPTH: AtomicUsize = AtomicUsize::new(0);
+
+/// Leaves the alternate screen, turns raw mode off, resets colours and
+/// shows the user's cursor, ignoring failures so it is safe to call from a
+/// panic or signal handler
+pub fn restore() {
+    let _ = terminal::disable_raw_mode();
+    let _ = execute!(
+        stdout(),
+        ResetColor,
+        SetCursorStyle::DefaultUserShape,
+        EnableBlinking,
+        Show,
+        LeaveAlternateScreen
+    );
+}
+
+/// Installs the panic hook and the signal handler
+fn install() {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if DEPTH.load(Ordering::SeqCst) > 0 {
+            restore();
+        }
+        previous(info);
+    }));
+    // Without a handler the game still works; the terminal is then only
+    // restored on a normal exit or a panic
+    if let Ok(mut signals) = Signals::new([SIGINT, SIGTERM, SIGHUP]) {
+        thread::spawn(move || {
+            if let Some(signal) = signals.forever().next() {
+                if DEPTH.load(Ordering::SeqCst) > 0 {
+                    restore();
+                }
+                process::exit(128 + signal);
+            }
+        });
+    }
+}
+
+/// Reads the next terminal event, with Ctrl-C turned into Esc
+pub fn read() -> Result<Event> {
+    Ok(interrupt_as_escape(event::read()?))
+}
+
+/// Turns a Ctrl-C key press into Esc, leaving other events alone
+fn interrupt_as_escape(event: Event) -> Event {
+    match event {
+        Event::Key(key)
+            if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) =>
+        {
+            Event::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+        }
+        event => event,
+    }
+}
+
+/// Keeps the terminal on the alternate screen in raw mode until the
+/// outermost guard is dropped
+pub struct Guard {
+    /// Prevents construction outside `Guard::new`
+    _private: (),
+}
+
+impl Guard {
+    /// Enters the alternate screen and enables raw mode unless a guard is
+    /// already held, installing the panic hook and signal handler if needed
+    pub fn new() -> Result<Self> {
+        HOOK.call_once(install);
+        if DEPTH.load(Ordering::SeqCst) == 0 {
+            execute!(stdout(), EnterAlternateScreen)?;
+            if let Err(e) = terminal::enable_raw_mode() {
+                restore();
+                return Err(e.into());
+            }
+        }
+        DEPTH.fetch_add(1, Ordering::SeqCst);
+        Ok(Self { _private: () })
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        if DEPTH.fetch_sub(1, Ordering::SeqCst) == 1 {
+            restore();
+        }
+    }
+}
+
+#[cfg(test)]
+mod term_tests {
+    use super::*;
+
+    #[test]
+    fn test_interrupt_as_escape() {
+        let key = |code, modifiers| Event::Key(KeyEvent::new(code, modifiers));
+        let escape = key(KeyCode::Esc, KeyModifiers::NONE);
+        let ctrl_c = key(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert_eq!(interrupt_as_escape(ctrl_c), escape);
+        let c = key(KeyCode::Char('c'), KeyModifiers::NONE);
+        assert_eq!(interrupt_as_escape(c.clone()), c);
+        assert_eq!(
+            interrupt_as_escape(Event::Resize(80, 24)),
+            Event::Resize(80, 24)
+        );
+    }
+}
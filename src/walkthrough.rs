@@ -0,0 +1,247 @@
+//! Step by step view of the logical solver
+//!
+//! The solver's deductions are worked out up front, so stepping back is as
+//! cheap as stepping forward. Each step marks the run or cage it used and
+//! lists the candidates it ruled out; remaining candidates are shown as
+//! pencil marks.
+
+use crate::{
+    Board, Result, render,
+    solver::{Deductions, Outcome, Solver},
+    term,
+};
+use crossterm::event::{self, Event, KeyCode};
+use std::time::Duration;
+
+/// Delays between automatic steps, slowest first, in milliseconds
+const SPEEDS: [u64; 5] = [2000, 1000, 500, 250, 100];
+
+/// Replay of the logical solver's deductions on a board
+pub struct Walkthrough {
+    /// Board being solved, its values taken as givens
+    puzzle: Board,
+    /// Every deduction the solver made
+    deductions: Deductions,
+    /// Number of steps applied
+    step: usize,
+    /// Index into `SPEEDS`
+    speed: usize,
+}
+
+impl Walkthrough {
+    /// Works out the deductions for a board, starting before the first step
+    pub fn new(board: &Board) -> Self {
+        Self {
+            puzzle: board.clone(),
+            deductions: Solver::new(board).deductions(),
+            step: 0,
+            speed: 2,
+        }
+    }
+
+    /// Returns the number of steps applied
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    /// Returns the total number of steps
+    pub fn total(&self) -> usize {
+        self.deductions.steps.len()
+    }
+
+    /// Returns the delay between automatic steps
+    pub fn delay(&self) -> Duration {
+        Duration::from_millis(SPEEDS[self.speed])
+    }
+
+    /// Steps automatic play up to the next faster speed
+    pub fn faster(&mut self) {
+        self.speed = (self.speed + 1).min(SPEEDS.len() - 1);
+    }
+
+    /// Steps automatic play down to the next slower speed
+    pub fn slower(&mut self) {
+        self.speed = self.speed.saturating_sub(1);
+    }
+
+    /// Applies the next step, returning false if there is none
+    pub fn forward(&mut self) -> bool {
+        let more = self.step < self.total();
+        if more {
+            self.step += 1;
+        }
+        more
+    }
+
+    /// Undoes the last step, returning false if none is applied
+    pub fn back(&mut self) -> bool {
+        let any = self.step > 0;
+        if any {
+            self.step -= 1;
+        }
+        any
+    }
+
+    /// Returns the candidates after the applied steps, as bit sets indexed
+    /// by row and column
+    pub fn candidates(&self) -> Vec<Vec<u16>> {
+        let mut cands = self.deductions.initial.clone();
+        let applied = &self.deductions.steps[..self.step];
+        for &(r, c, d) in applied.iter().flat_map(|s| &s.eliminated) {
+            cands[r][c] &= !(1 << d);
+        }
+        cands
+    }
+
+    /// Returns the board to draw: single candidates as values, the rest as
+    /// pencil marks, and the cells of the last step's run marked
+    pub fn board(&self) -> Board {
+        let mut board = self.puzzle.clone();
+        for (r, row) in self.candidates().iter().enumerate() {
+            for (c, &m) in row.iter().enumerate() {
+                let cell = board.cell_mut(r, c);
+                if cell.is_clue() {
+                    continue;
+                }
+                if m.count_ones() == 1 {
+                    cell.set_value(Some(m.trailing_zeros() as u8));
+                    cell.set_notes(0);
+                } else {
+                    cell.set_value(None);
+                    cell.set_notes(m);
+                }
+            }
+        }
+        let constraint = match (self.step, &self.deductions.outcome) {
+            (s, Outcome::Contradiction(constraint)) if s == self.total() => Some(constraint),
+            (0, _) => None,
+            (s, _) => Some(&self.deductions.steps[s - 1].constraint),
+        };
+        board.set_marked(constraint.map(|k| k.cells.clone()).unwrap_or_default());
+        board.set_highlight_same(false);
+        board
+    }
+
+    /// Describes the last step applied, or how the deductions ended
+    pub fn describe(&self) -> String {
+        let count = format!("step {}/{}: ", self.step, self.total());
+        if self.step == self.total() {
+            let end = match &self.deductions.outcome {
+                Outcome::Solved => "solved".to_string(),
+                Outcome::Stuck => {
+                    "no run or cage rules anything out, a guess is needed".to_string()
+                }
+                Outcome::Contradiction(constraint) => format!("{} cannot be made", constraint),
+            };
+            return count + &end;
+        }
+        if self.step == 0 {
+            return count + "start";
+        }
+        let step = &self.deductions.steps[self.step - 1];
+        let mut removed: Vec<String> = Vec::new();
+        for &(r, c, d) in &step.eliminated {
+            match removed.last_mut() {
+                Some(last) if last.starts_with(&format!("({}, {})", r, c)) => {
+                    last.push_str(&d.to_string());
+                }
+                _ => removed.push(format!("({}, {}) {}", r, c, d)),
+            }
+        }
+        format!(
+            "{}{} rules out {}",
+            count,
+            step.constraint,
+            removed.join(", ")
+        )
+    }
+
+    /// Draws the current step with the walkthrough's help and status lines
+    fn draw(&self, playing: bool) -> Result<()> {
+        let state = if playing { " [playing" } else { " [paused" };
+        let tag = format!("{}, {}ms]", state, SPEEDS[self.speed]);
+        let keys = [
+            ("esc", "back to game"),
+            ("left/right", "step"),
+            ("space", "auto-play"),
+            ("+/-", "speed"),
+        ];
+        let help = render::help_line(&keys, &tag);
+        self.board().draw_footer(&help, &self.describe())
+    }
+
+    /// Runs the walkthrough until escape is pressed
+    pub fn run(&mut self) -> Result<()> {
+        let mut playing = false;
+        loop {
+            self.draw(playing)?;
+            if playing && !event::poll(self.delay())? {
+                playing = self.forward() && self.step < self.total();
+                continue;
+            }
+            let read = term::read()?;
+            if let Event::Resize(..) = read {
+                render::invalidate();
+            }
+            if let Event::Key(key) = read {
+                match key.code {
+                    KeyCode::Right => {
+                        self.forward();
+                    }
+                    KeyCode::Left => {
+                        self.back();
+                    }
+                    KeyCode::Char(' ') => {
+                        playing = !playing && self.step < self.total();
+                    }
+                    KeyCode::Char('+') | KeyCode::Char('=') => self.faster(),
+                    KeyCode::Char('-') => self.slower(),
+                    KeyCode::Esc => return Ok(()),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod walkthrough_tests {
+    use super::*;
+    use crate::format;
+
+    #[test]
+    fn test_stepping() {
+        let board = format::parse("\\ 16\\ 17\\\n\\16 _ _\n\\17 _ _\n").unwrap();
+        let mut walk = Walkthrough::new(&board);
+        assert_eq!(walk.describe(), format!("step 0/{}: start", walk.total()));
+        assert!(!walk.back());
+        assert!(walk.forward());
+        assert_eq!(walk.candidates()[1][1], (1 << 7) | (1 << 9));
+        let shown = walk.board();
+        assert!(shown.is_marked(1, 1) && shown.is_marked(2, 1));
+        assert!(!shown.is_marked(1, 2));
+        assert!(shown.cell(1, 1).has_note(9));
+        assert_eq!(
+            walk.describe(),
+            "step 1/5: down 16 in 2 cells at row 0, col 1 rules out \
+             (1, 1) 1234568, (2, 1) 1234568"
+        );
+        while walk.forward() {}
+        assert_eq!(walk.board().cell(2, 2).value(), Some(8));
+        assert!(walk.describe().ends_with("solved"));
+        assert!(walk.back());
+        assert_eq!(walk.step(), walk.total() - 1);
+    }
+
+    #[test]
+    fn test_speed() {
+        let board = format::parse("\\ 3\\ 3\\\n\\3 _ _\n\\3 _ _\n").unwrap();
+        let mut walk = Walkthrough::new(&board);
+        walk.faster();
+        walk.faster();
+        walk.faster();
+        assert_eq!(walk.delay(), Duration::from_millis(100));
+        (0..9).for_each(|_| walk.slower());
+        assert_eq!(walk.delay(), Duration::from_millis(2000));
+    }
+}
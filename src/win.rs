@@ -0,0 +1,91 @@
+//! Screen shown when a puzzle is solved
+
+use crate::{Result, term};
+use crossterm::{
+    cursor::MoveTo,
+    event::{Event, KeyCode},
+    execute,
+    style::{Attribute, Print, Stylize},
+    terminal,
+};
+use std::{io::stdout, time::Duration};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// What the player picked on the win screen
+pub enum Choice {
+    /// Play the next puzzle
+    Next,
+    /// Play the same puzzle again from the start
+    Replay,
+    /// Go back to the menu the puzzle was opened from
+    Back,
+}
+
+/// Formats seconds as `m:ss`
+pub fn clock(secs: u64) -> String {
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+/// Returns the lines describing a solve
+pub fn summary(time: Duration, checks: usize) -> Vec<String> {
+    let plural = if checks == 1 { "" } else { "s" };
+    vec![
+        format!("Solved in {}", clock(time.as_secs())),
+        format!("{} check{}", checks, plural),
+    ]
+}
+
+/// Returns the line listing the keys, leaving out next if there is none
+pub fn options(has_next: bool) -> String {
+    let next = if has_next { "n: next puzzle | " } else { "" };
+    format!("{}r: replay | esc: back", next)
+}
+
+/// Shows the win screen for a puzzle with the given stats until the player
+/// picks what to do next
+pub fn show(title: &str, lines: &[String], has_next: bool) -> Result<Choice> {
+    let _guard = term::Guard::new()?;
+    let mut stdout = stdout();
+    loop {
+        execute!(
+            stdout,
+            terminal::Clear(terminal::ClearType::All),
+            MoveTo(0, 0),
+            Print("You win!".green().attribute(Attribute::Bold)),
+            MoveTo(0, 1),
+            Print(title.dark_magenta()),
+        )?;
+        for (i, line) in lines.iter().enumerate() {
+            execute!(stdout, MoveTo(0, i as u16 + 3), Print(line))?;
+        }
+        execute!(
+            stdout,
+            MoveTo(0, lines.len() as u16 + 4),
+            Print(options(has_next))
+        )?;
+        if let Event::Key(key) = term::read()? {
+            match key.code {
+                KeyCode::Char('n') if has_next => return Ok(Choice::Next),
+                KeyCode::Char('r') => return Ok(Choice::Replay),
+                KeyCode::Esc => return Ok(Choice::Back),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod win_tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_and_options() {
+        assert_eq!(
+            summary(Duration::from_secs(83), 1),
+            ["Solved in 1:23", "1 check"]
+        );
+        assert_eq!(summary(Duration::from_secs(5), 3)[1], "3 checks");
+        assert_eq!(options(true), "n: next puzzle | r: replay | esc: back");
+        assert_eq!(options(false), "r: replay | esc: back");
+    }
+}
@@ -0,0 +1,289 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+const PUZZLE: &str = "\\ 16\\ 17\\\n\\16 _ _\n\\17 _ _\n";
+const SOLVED: &str = "\\ 16\\ 17\\\n\\16 7 9\n\\17 9 8\n";
+
+fn kakuro() -> Command {
+    Command::cargo_bin("kakuro").unwrap()
+}
+
+#[test]
+fn test_solve() {
+    kakuro()
+        .args(["solve", "-"])
+        .write_stdin(PUZZLE)
+        .assert()
+        .success()
+        .stdout("\\   16\\ 17\\\n\\16 7   9\n\\17 9   8\n");
+    kakuro()
+        .args(["solve", "-"])
+        .write_stdin("digits: 1-4\n\\ 4\\ 6\\\n\\3 _ _\n\\7 _ _\n")
+        .assert()
+        .success()
+        .stdout("digits: 1-4\n\\  4\\ 6\\\n\\3 1  2\n\\7 3  4\n");
+    kakuro()
+        .args(["solve", "-"])
+        .write_stdin("digits: 1-4\n\\ 9\\\n\\ _\n\\ _\n")
+        .assert()
+        .code(1);
+    kakuro()
+        .args(["solve", "-"])
+        .write_stdin("\\ 12* 2*\n*3 _ _\n*8 _ _\n")
+        .assert()
+        .success()
+        .stdout("\\   12* 2*\n*3  3   1\n*8  4   2\n");
+    let cages = "digits: 1-3\ncage: _ 0,0 0,1 0,2\ncage: 5 0,0 0,1\ncage: 2 0,0\n";
+    kakuro()
+        .args(["solve", "-"])
+        .write_stdin(format!("{}_ _ _\n", cages))
+        .assert()
+        .success()
+        .stdout(format!("{}2 3 1\n", cages));
+}
+
+#[test]
+fn test_check() {
+    kakuro()
+        .args(["check", "-"])
+        .write_stdin(SOLVED)
+        .assert()
+        .success()
+        .stdout("correct\n");
+    kakuro()
+        .args(["check", "-"])
+        .write_stdin(SOLVED.replace("7 9\n", "9 7\n"))
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("row 2 col 1 across 16").not())
+        .stdout(predicate::str::contains("row 1 col 2 down 16: duplicate 9"))
+        .stdout(predicate::str::contains(
+            "row 1 col 3 down 17: wrong sum, got 15",
+        ));
+    kakuro()
+        .args(["check", "-"])
+        .write_stdin("cage: 5 0,0 0,1\n1 2\n")
+        .assert()
+        .code(1)
+        .stdout("row 1 col 1 cage 1: wrong sum, got 3\n");
+    kakuro()
+        .args(["check", "-", "--json"])
+        .write_stdin(PUZZLE.replace("\\17 _ _", "\\17 _ 8"))
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains(r#""status": "incomplete""#))
+        .stdout(predicate::str::contains(r#""direction": "across""#))
+        .stdout(predicate::str::contains(r#""actual": 8"#));
+}
+
+#[test]
+fn test_generate_is_reproducible() {
+    let run = || {
+        kakuro()
+            .args(["generate", "--size", "6x7", "--seed", "3"])
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone()
+    };
+    let out = run();
+    assert_eq!(out, run());
+    let text = String::from_utf8(out).unwrap();
+    assert_eq!(text.lines().count(), 6);
+    assert_eq!(text.lines().next().unwrap().split_whitespace().count(), 7);
+}
+
+#[test]
+fn test_generate_thin_boards() {
+    // Boards too thin to fill give up rather than search forever
+    for size in ["3x20", "20x3"] {
+        kakuro()
+            .args(["generate", "--size", size, "--seed", "1"])
+            .timeout(std::time::Duration::from_secs(10))
+            .assert()
+            .code(2)
+            .stderr(format!(
+                "kakuro: could not lay out a {} puzzle, try a wider size\n",
+                size
+            ));
+    }
+    kakuro()
+        .args(["generate", "--size", "3x5", "--seed", "1"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_rate_and_render() {
+    kakuro()
+        .args(["rate", "-"])
+        .write_stdin(PUZZLE)
+        .assert()
+        .success()
+        .stdout("easy (0 guesses)\n");
+    kakuro()
+        .args(["render", "-"])
+        .write_stdin(PUZZLE)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("   \\16 |  _  ||  _  |"));
+}
+
+#[test]
+fn test_usage_errors() {
+    kakuro().arg("frobnicate").assert().code(2);
+    kakuro().args(["solve"]).assert().code(2);
+    kakuro()
+        .args(["solve", "-"])
+        .write_stdin("\\ 3\\\n\\3 x\n")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("line 2, cell 2"));
+    kakuro()
+        .args(["generate", "--size", "2x9"])
+        .assert()
+        .code(2);
+    kakuro()
+        .args(["race", "host", "-", "--port", "x"])
+        .assert()
+        .code(2)
+        .stderr("kakuro: invalid port 'x'\n");
+    kakuro().args(["race", "watch"]).assert().code(2);
+    kakuro()
+        .args(["play", "--code", "AQ"])
+        .assert()
+        .code(2)
+        .stderr("kakuro: the code's checksum does not match, check for typos\n");
+    kakuro()
+        .args(["coop", "host"])
+        .assert()
+        .code(2)
+        .stderr("kakuro: coop host takes FILE and an optional --port N\n");
+}
+
+#[test]
+fn test_convert_to_json_and_back() {
+    let out = kakuro()
+        .args(["convert", "-", "--to", "json"])
+        .write_stdin(SOLVED)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"version\": 1"))
+        .get_output()
+        .stdout
+        .clone();
+    // JSON input is accepted by every command
+    kakuro()
+        .args(["check", "-"])
+        .write_stdin(out.clone())
+        .assert()
+        .success();
+    kakuro()
+        .args(["convert", "-", "--to", "text"])
+        .write_stdin(out)
+        .assert()
+        .success()
+        .stdout("\\   16\\ 17\\\n\\16 7   9\n\\17 9   8\n");
+    kakuro()
+        .args(["convert", "-", "--to", "xml"])
+        .write_stdin(SOLVED)
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn test_import_csv_and_tatham() {
+    let expected = "\\   16\\ 17\\\n\\16 _   _\n\\17 _   _\n";
+    kakuro()
+        .args(["convert", "-", "--to", "text"])
+        .write_stdin("#,16\\,17\\\n\\16,,\n\\17,,\n")
+        .assert()
+        .success()
+        .stdout(expected);
+    kakuro()
+        .args(["convert", "-", "--to", "text"])
+        .write_stdin("3x3:A16/_17//16b/17b\n")
+        .assert()
+        .success()
+        .stdout(expected);
+    kakuro()
+        .args(["solve", "-"])
+        .write_stdin("3x3de:A16/_17//16b/17b\n")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("unsupported parameters 'de'"));
+    kakuro()
+        .args(["solve", "-"])
+        .write_stdin("#,#,#\n#,_,_\n")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("has no clue"));
+}
+
+#[test]
+fn test_count() {
+    kakuro()
+        .args(["count", "-"])
+        .write_stdin(PUZZLE)
+        .assert()
+        .success()
+        .stdout("1 solution\n");
+    kakuro()
+        .args(["count", "-", "--cap", "5"])
+        .write_stdin("\\ 3\\ 3\\\n\\3 _ _\n\\3 _ _\n")
+        .assert()
+        .code(1)
+        .stdout(predicate::str::starts_with(
+            "2 solutions\nrow 2 col 2: ambiguous\n",
+        ));
+    kakuro()
+        .args(["count", "-", "--cap", "x"])
+        .write_stdin(PUZZLE)
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn test_daily() {
+    let first = kakuro()
+        .args(["daily", "--date", "2025-03-14"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    kakuro()
+        .args(["daily", "--date", "2025-03-14"])
+        .assert()
+        .success()
+        .stdout(String::from_utf8(first).unwrap());
+    kakuro()
+        .args(["daily", "--date", "2025-02-30"])
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn test_share() {
+    let output = kakuro()
+        .args(["share", "-", "--progress"])
+        .write_stdin(SOLVED)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let code = String::from_utf8(output).unwrap();
+    let board = kakuro::share::decode(&code).unwrap();
+    let solved = kakuro::format::parse(SOLVED).unwrap();
+    assert_eq!(
+        kakuro::format::to_text(&board),
+        kakuro::format::to_text(&solved)
+    );
+    kakuro()
+        .args(["share", "-", "--all"])
+        .write_stdin(PUZZLE)
+        .assert()
+        .code(2);
+}
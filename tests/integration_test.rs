@@ -110,3 +110,429 @@ fn test_invalid_level_then_valid() {
     input.write_all(b"q\n").unwrap();
     let _ = terminal.close();
 }
+
+#[test]
+#[timeout(3000)]
+fn test_editor() {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_kakuro"));
+    let mut terminal = cmd.spawn_terminal().unwrap();
+    let (mut input, mut output) = terminal.split().unwrap();
+
+    match_output(&mut output, b"Welcome to Kakuro");
+
+    // Open the editor and accept the default size
+    input.write_all(b"e\n").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"New puzzle");
+    input.write_all(b"\r").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"save");
+
+    // Check uniqueness of the empty grid
+    input.write_all(b"u").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"no clue");
+
+    input.write_all(b"\x1b").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"Welcome to Kakuro");
+    input.write_all(b"q").unwrap();
+    let _ = terminal.close();
+}
+
+#[test]
+#[timeout(3000)]
+fn test_watch_solver() {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_kakuro"));
+    let mut terminal = cmd.spawn_terminal().unwrap();
+    let (mut input, mut output) = terminal.split().unwrap();
+
+    match_output(&mut output, b"Welcome to Kakuro");
+    input.write_all(b"1\n").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"watch solver");
+
+    // Open the walkthrough and take one step
+    input.write_all(b"w").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"start");
+    input.write_all(b"\x1b[C").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"rules out");
+
+    // Back to the game
+    input.write_all(b"\x1b").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"watch solver");
+    let _ = terminal.close();
+}
+
+#[test]
+#[timeout(4000)]
+fn test_daily_puzzle() {
+    let stats = std::env::temp_dir().join(format!("kakuro-daily-{}.json", std::process::id()));
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_kakuro"));
+    cmd.env("KAKURO_STATS", &stats);
+    let mut terminal = cmd.spawn_terminal().unwrap();
+    let (mut input, mut output) = terminal.split().unwrap();
+
+    match_output(&mut output, b"Daily Kakuro");
+    input.write_all(b"d\n").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"home");
+
+    // Leaving unsolved shows the summary without recording a time
+    input.write_all(b"\x1b").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"Streak: 0 days");
+    input.write_all(b"x").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"Welcome to Kakuro");
+    input.write_all(b"q").unwrap();
+    let _ = terminal.close();
+    assert!(!stats.exists());
+}
+
+#[test]
+#[timeout(4000)]
+fn test_puzzle_packs() {
+    let dir = std::env::temp_dir().join(format!("kakuro-packs-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("mine.txt"),
+        "name: Mine\n\ntitle: Tiny\n\\ 3\\ 4\\\n\\3 _ _\n\\4 _ _\n",
+    )
+    .unwrap();
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_kakuro"));
+    cmd.env("KAKURO_PACKS", &dir)
+        .env("KAKURO_PROGRESS", dir.join("progress.json"));
+    let mut terminal = cmd.spawn_terminal().unwrap();
+    let (mut input, mut output) = terminal.split().unwrap();
+
+    match_output(&mut output, b"Puzzle packs");
+    input.write_all(b"p\n").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"Mine (0/1 solved)");
+
+    // Practice unlocks one puzzle at a time
+    input.write_all(b"\x1b[B\r").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"[-] Practice 2 (5 x 5) (locked)");
+    input.write_all(b"\x1b[B\r").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"Solve Practice 1 (5 x 5) first");
+    input.write_all(b"\x1b[A\r").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"home");
+    input.write_all(b"\x1b").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"esc: packs");
+    input.write_all(b"\x1b").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"esc: home");
+    input.write_all(b"\x1b").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"Welcome to Kakuro");
+    input.write_all(b"q").unwrap();
+    let _ = terminal.close();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+#[timeout(4000)]
+fn test_home_menu_search_and_select() {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_kakuro"));
+    cmd.env("KAKURO_PACKS", std::env::temp_dir().join("kakuro-no-packs"));
+    let mut terminal = cmd.spawn_terminal().unwrap();
+    let (mut input, mut output) = terminal.split().unwrap();
+
+    match_output(&mut output, b"Welcome to Kakuro");
+    input.write_all(b"/level 3").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"search: level 3_");
+
+    // Enter ends the search, a second Enter plays the only match
+    input.write_all(b"\r").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"search: level 3\x1b");
+    input.write_all(b"\r").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"home");
+    input.write_all(b"\x1b").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"Welcome to Kakuro");
+    input.write_all(b"q").unwrap();
+    let _ = terminal.close();
+}
+
+#[test]
+#[timeout(4000)]
+fn test_check_status_and_win_screen() {
+    let progress = std::env::temp_dir().join(format!("kakuro-win-{}.json", std::process::id()));
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_kakuro"));
+    cmd.env("KAKURO_PROGRESS", &progress);
+    let mut terminal = cmd.spawn_terminal().unwrap();
+    let (mut input, mut output) = terminal.split().unwrap();
+
+    match_output(&mut output, b"Welcome to Kakuro");
+    input.write_all(b"1").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"home");
+
+    // A wrong first row is reported per run
+    input.write_all(b"\x1b[B\x1b[C8\x1b[C2\r").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"check: 1 wrong sum, 3 incomplete");
+
+    input.write_all(b"1\x1b[B\x1b[D4\x1b[C2\r").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"You win!");
+    // Other keys redraw the screen
+    input.write_all(b"x").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"n: next puzzle | r: replay | esc: back");
+    input.write_all(b"n").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"13");
+    input.write_all(b"\x1b").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"Welcome to Kakuro");
+    input.write_all(b"q").unwrap();
+    let _ = terminal.close();
+    std::fs::remove_file(&progress).unwrap();
+}
+
+#[test]
+#[timeout(2000)]
+fn test_alternate_screen_restored_on_quit() {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_kakuro"));
+    let mut terminal = cmd.spawn_terminal().unwrap();
+    let (mut input, mut output) = terminal.split().unwrap();
+
+    // The home screen is drawn on the alternate screen
+    match_output(&mut output, b"\x1b[?1049h");
+    input.write_all(b"x").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"Welcome to Kakuro");
+
+    // Quitting restores the cursor style and leaves the alternate screen
+    input.write_all(b"q").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"\x1b[0 q");
+    let _ = terminal.close();
+}
+
+#[test]
+#[timeout(2000)]
+fn test_ctrl_c_quits() {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_kakuro"));
+    let mut terminal = cmd.spawn_terminal().unwrap();
+    let (mut input, mut output) = terminal.split().unwrap();
+
+    match_output(&mut output, b"Welcome to Kakuro");
+    input.write_all(b"\x03").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"\x1b[?1049l");
+    let _ = terminal.close();
+}
+
+#[test]
+#[timeout(2000)]
+fn test_ctrl_c_leaves_board() {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_kakuro"));
+    let mut terminal = cmd.spawn_terminal().unwrap();
+    let (mut input, mut output) = terminal.split().unwrap();
+
+    match_output(&mut output, b"Welcome to Kakuro");
+    input.write_all(b"1").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"watch solver");
+
+    // Ctrl-C leaves the board like Esc, back to the home menu
+    input.write_all(b"\x03").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"Welcome to Kakuro");
+    input.write_all(b"\x03").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"\x1b[?1049l");
+    let _ = terminal.close();
+}
+
+#[test]
+#[timeout(2000)]
+fn test_compact_view() {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_kakuro"));
+    cmd.arg("--compact");
+    let mut terminal = cmd.spawn_terminal().unwrap();
+    let (mut input, mut output) = terminal.split().unwrap();
+
+    match_output(&mut output, b"Welcome to Kakuro");
+    input.write_all(b"1").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"view (compact)");
+
+    // The view key cycles on to auto
+    input.write_all(b"v").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"view (auto)");
+
+    input.write_all(b"\x1b").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"Welcome to Kakuro");
+    let _ = terminal.close();
+}
+
+#[test]
+#[timeout(5000)]
+fn test_race_over_loopback() {
+    let puzzle = std::env::temp_dir().join(format!("kakuro-race-{}.txt", std::process::id()));
+    std::fs::write(&puzzle, "\\ 16\\ 17\\\n\\16 _ _\n\\17 _ _\n").unwrap();
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+        .to_string();
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_kakuro"));
+    cmd.args(["race", "host"])
+        .arg(&puzzle)
+        .args(["--port", &port]);
+    let mut host = cmd.spawn_terminal().unwrap();
+    let (mut host_input, mut host_output) = host.split().unwrap();
+    match_output(&mut host_output, b"waiting for a player");
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_kakuro"));
+    cmd.args(["race", "join", &format!("127.0.0.1:{}", port)]);
+    let mut guest = cmd.spawn_terminal().unwrap();
+    let (mut guest_input, mut guest_output) = guest.split().unwrap();
+    match_output(
+        &mut guest_output,
+        b"opponent: 0%, 0 checks | you: 0%, 0 checks",
+    );
+    match_output(&mut host_output, b"opponent: 0%, 0 checks");
+
+    // Filling a cell and checking shows up on the other side
+    guest_input.write_all(b"\x1b[B\x1b[C7\r").unwrap();
+    guest_input.flush().unwrap();
+    match_output(&mut host_output, b"opponent: 25%, 1 check");
+
+    // Leaving the race is noticed by the host
+    guest_input.write_all(b"\x1b").unwrap();
+    guest_input.flush().unwrap();
+    match_output(&mut host_output, b"opponent left at 25%, 1 check");
+    host_input.write_all(b"\x1b").unwrap();
+    host_input.flush().unwrap();
+    let _ = guest.close();
+    let _ = host.close();
+    std::fs::remove_file(&puzzle).unwrap();
+}
+
+#[test]
+#[timeout(6000)]
+fn test_coop_shared_board() {
+    let puzzle = std::env::temp_dir().join(format!("kakuro-coop-{}.txt", std::process::id()));
+    std::fs::write(&puzzle, "\\ 16\\ 17\\\n\\16 _ _\n\\17 _ _\n").unwrap();
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+        .to_string();
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_kakuro"));
+    cmd.args(["coop", "host"])
+        .arg(&puzzle)
+        .args(["--port", &port]);
+    let mut host = cmd.spawn_terminal().unwrap();
+    let (_, mut host_output) = host.split().unwrap();
+    match_output(&mut host_output, b"sharing the board");
+
+    let addr = format!("127.0.0.1:{}", port);
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_kakuro"));
+    cmd.args(["coop", "join", &addr]);
+    let mut first = cmd.spawn_terminal().unwrap();
+    let (mut first_input, mut first_output) = first.split().unwrap();
+    match_output(&mut first_output, b"you: player 1 | no one else here");
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_kakuro"));
+    cmd.args(["coop", "join", &addr]);
+    let mut second = cmd.spawn_terminal().unwrap();
+    let (mut second_input, mut second_output) = second.split().unwrap();
+    match_output(&mut second_output, b"you: player 2 | others: 1 magenta");
+    match_output(&mut first_output, b"others: 2 yellow");
+
+    // Each change reaches the other player, the later write winning
+    first_input.write_all(b"\x1b[B\x1b[C7").unwrap();
+    first_input.flush().unwrap();
+    match_output(&mut second_output, b"|  7  |");
+    second_input.write_all(b"\x1b[B\x1b[C9").unwrap();
+    second_input.flush().unwrap();
+    match_output(&mut first_output, b"|  9  |");
+
+    // Undo takes back the second player's change only
+    second_input.write_all(b"u").unwrap();
+    second_input.flush().unwrap();
+    match_output(&mut first_output, b"|  7  |");
+
+    second_input.write_all(b"\x1b").unwrap();
+    second_input.flush().unwrap();
+    match_output(&mut first_output, b"no one else here");
+    first_input.write_all(b"\x1b").unwrap();
+    first_input.flush().unwrap();
+    let _ = first.close();
+    let _ = second.close();
+    let _ = host.close();
+    std::fs::remove_file(&puzzle).unwrap();
+}
+
+#[test]
+#[timeout(3000)]
+fn test_play_share_code() {
+    let board = kakuro::format::parse("\\ 16\\ 17\\\n\\16 _ _\n\\17 _ _\n").unwrap();
+    let code = kakuro::share::encode(&board, false);
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_kakuro"));
+    cmd.args(["play", "--code", &code]);
+    let mut terminal = cmd.spawn_terminal().unwrap();
+    let (mut input, mut output) = terminal.split().unwrap();
+    match_output(&mut output, b"share");
+
+    // The share screen shows the code the puzzle was opened with. Reads
+    // past a match are lost, so the heading is not matched separately
+    input.write_all(b"c").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, code.as_bytes());
+
+    input.write_all(b" ").unwrap();
+    input.flush().unwrap();
+    match_output(&mut output, b"share");
+    input.write_all(b"\x1b").unwrap();
+    input.flush().unwrap();
+    let _ = terminal.close();
+}
+
+#[test]
+#[timeout(3000)]
+fn test_ctrl_c_skips_share_screen() {
+    let board = kakuro::format::parse("\\ 16\\ 17\\\n\\16 _ _\n\\17 _ _\n").unwrap();
+    let code = kakuro::share::encode(&board, false);
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_kakuro"));
+    cmd.args(["play", "--code", &code]);
+    let mut terminal = cmd.spawn_terminal().unwrap();
+    let (mut input, mut output) = terminal.split().unwrap();
+    match_output(&mut output, b"share");
+
+    // Ctrl-C leaves the board and the program without showing the code
+    input.write_all(b"\x03").unwrap();
+    input.flush().unwrap();
+    let mut rest = String::new();
+    let mut buffer = [0u8; 256];
+    while !rest.contains("\x1b[?1049l") {
+        let n = output.read(&mut buffer).unwrap();
+        assert!(n > 0, "the terminal was not restored");
+        rest.push_str(&String::from_utf8_lossy(&buffer[..n]));
+    }
+    assert!(!rest.contains("Share code"));
+    let _ = terminal.close();
+}